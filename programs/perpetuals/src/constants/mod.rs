@@ -1,11 +1,5 @@
 use anchor_lang::prelude::*;
 
-#[constant]
-pub const ORACLE_MAXIMUM_AGE: u64 = 60; // seconds, should be lowered in prod
-
-#[constant]
-pub const ADMIN_SEED: &str = "admin";
-
 #[constant]
 pub const PERPETUALS_SEED: &str = "perpetuals";
 
@@ -24,5 +18,26 @@ pub const CUSTODY_TOKEN_MINT_SEED: &str = "custody_token_mint";
 #[constant]
 pub const CUSTODY_TOKEN_ACCOUNT_SEED: &str = "custody_token_account";
 
+#[constant]
+pub const INSURANCE_FUND_TOKEN_ACCOUNT_SEED: &str = "insurance_fund_token_account";
+
+#[constant]
+pub const CUSTOM_ORACLE_SEED: &str = "oracle_account";
+
 #[constant]
 pub const POSITION_SEED: &str = "position";
+
+#[constant]
+pub const POSITION_COUNTER_SEED: &str = "position_counter";
+
+#[constant]
+pub const ORDER_SEED: &str = "order";
+
+#[constant]
+pub const ORDER_TOKEN_ACCOUNT_SEED: &str = "order_token_account";
+
+#[constant]
+pub const MARGIN_ACCOUNT_SEED: &str = "margin_account";
+
+#[constant]
+pub const PROPOSAL_SEED: &str = "proposal";
@@ -72,4 +72,34 @@ pub enum PerpetualsError {
     InvalidEmaOracle,
     #[msg("EMA oracle is required")]
     EmaOracleRequired,
+    #[msg("Order trigger price has not been reached")]
+    OrderNotTriggered,
+    #[msg("Primary and secondary oracle prices diverge too much")]
+    OraclePriceDivergence,
+    #[msg("Custody is above the auto-deleveraging threshold")]
+    AutoDeleverageNotAllowed,
+    #[msg("Position is outranked by another position for auto-deleveraging")]
+    AutoDeleverageRankMismatch,
+    #[msg("Long open interest limit exceeded")]
+    MaxOpenInterestLong,
+    #[msg("Short open interest limit exceeded")]
+    MaxOpenInterestShort,
+    #[msg("Pool open interest limit exceeded")]
+    MaxPoolOpenInterest,
+    #[msg("Margin account is full")]
+    MarginAccountFull,
+    #[msg("Position is linked to a margin account")]
+    PositionLinkedToMarginAccount,
+    #[msg("Invalid position id")]
+    InvalidPositionId,
+    #[msg("Invalid proposal state")]
+    InvalidProposalState,
+    #[msg("Proposal is not queued or its timelock has not expired")]
+    ProposalNotExecutable,
+    #[msg("Change requires a timelocked proposal")]
+    ProposalRequired,
+    #[msg("Instruction is not allowed in the current market status")]
+    MarketStatusRestricted,
+    #[msg("Oracle publish time is in the future")]
+    OraclePublishTimeInFuture,
 }
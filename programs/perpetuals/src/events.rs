@@ -0,0 +1,424 @@
+//! Events emitted by state-changing instructions for off-chain indexing
+
+use {
+    crate::state::{
+        custody::MarketStatus, multisig::Role, order::OrderType, perpetuals::Permissions,
+        position::Side,
+    },
+    anchor_lang::prelude::*,
+};
+
+// Prices have implied PRICE_DECIMALS decimals, USD amounts have implied USD_DECIMALS
+// decimals and token amounts are in the collateral custody token unless stated otherwise.
+
+#[event]
+pub struct OpenPositionEvent {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub collateral_custody: Pubkey,
+    pub position: Pubkey,
+    pub position_id: u64,
+    pub side: Side,
+    pub price: u64,
+    pub size_usd: u64,
+    pub collateral_usd: u64,
+    pub collateral_amount: u64,
+    pub locked_amount: u64,
+    pub fee_amount: u64,
+    pub fee_amount_usd: u64,
+    pub open_time: i64,
+}
+
+#[event]
+pub struct IncreasePositionEvent {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub collateral_custody: Pubkey,
+    pub position: Pubkey,
+    pub side: Side,
+    pub price: u64,
+    pub size_usd: u64,
+    pub collateral_amount: u64,
+    pub fee_amount: u64,
+    pub fee_amount_usd: u64,
+    // resulting position
+    pub position_price: u64,
+    pub position_size_usd: u64,
+    pub position_collateral_usd: u64,
+    pub update_time: i64,
+}
+
+#[event]
+pub struct ClosePositionEvent {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub collateral_custody: Pubkey,
+    pub position: Pubkey,
+    pub side: Side,
+    pub price: u64,
+    pub size_usd: u64,
+    pub collateral_amount: u64,
+    pub profit_usd: u64,
+    pub loss_usd: u64,
+    pub fee_amount: u64,
+    pub fee_amount_usd: u64,
+    pub transfer_amount: u64,
+    // zero if the position has been closed entirely
+    pub remaining_size_usd: u64,
+    pub update_time: i64,
+}
+
+#[event]
+pub struct LiquidateEvent {
+    pub signer: Pubkey,
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub collateral_custody: Pubkey,
+    pub position: Pubkey,
+    pub side: Side,
+    pub price: u64,
+    pub size_usd: u64,
+    pub collateral_amount: u64,
+    pub profit_usd: u64,
+    pub loss_usd: u64,
+    pub fee_amount: u64,
+    pub fee_amount_usd: u64,
+    pub transfer_amount: u64,
+    pub reward_amount: u64,
+    // bad debt covered by the margin account of a linked position
+    pub margin_amount: u64,
+    // bad debt covered by the insurance fund
+    pub insurance_amount: u64,
+    // zero if the position has been liquidated entirely
+    pub remaining_size_usd: u64,
+    pub update_time: i64,
+}
+
+#[event]
+pub struct FlagLiquidatableEvent {
+    pub signer: Pubkey,
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    // zero if the flag has been cleared
+    pub liquidatable_since: i64,
+}
+
+#[event]
+pub struct AutoDeleverageEvent {
+    pub signer: Pubkey,
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub collateral_custody: Pubkey,
+    pub position: Pubkey,
+    pub side: Side,
+    pub price: u64,
+    pub size_usd: u64,
+    pub collateral_amount: u64,
+    pub profit_usd: u64,
+    pub fee_amount: u64,
+    pub fee_amount_usd: u64,
+    pub transfer_amount: u64,
+    // part of the close amount the custody could not pay out
+    pub shortfall_amount: u64,
+    pub adl_score: u128,
+    pub update_time: i64,
+}
+
+#[event]
+pub struct AddCollateralEvent {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub collateral_custody: Pubkey,
+    pub position: Pubkey,
+    pub collateral_amount: u64,
+    pub collateral_usd: u64,
+    // resulting position
+    pub position_collateral_amount: u64,
+    pub position_collateral_usd: u64,
+    pub update_time: i64,
+}
+
+#[event]
+pub struct RemoveCollateralEvent {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub collateral_custody: Pubkey,
+    pub position: Pubkey,
+    pub collateral_amount: u64,
+    pub collateral_usd: u64,
+    // resulting position
+    pub position_collateral_amount: u64,
+    pub position_collateral_usd: u64,
+    pub update_time: i64,
+}
+
+#[event]
+pub struct InitMarginAccountEvent {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub margin_account: Pubkey,
+}
+
+#[event]
+pub struct AddMarginCollateralEvent {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub margin_account: Pubkey,
+    pub custody: Pubkey,
+    pub amount: u64,
+    // resulting margin account collateral in the custody
+    pub margin_amount: u64,
+}
+
+#[event]
+pub struct RemoveMarginCollateralEvent {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub margin_account: Pubkey,
+    pub custody: Pubkey,
+    pub amount: u64,
+    // resulting margin account collateral in the custody
+    pub margin_amount: u64,
+}
+
+#[event]
+pub struct LinkPositionEvent {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub margin_account: Pubkey,
+    pub position: Pubkey,
+}
+
+#[event]
+pub struct UnlinkPositionEvent {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub margin_account: Pubkey,
+    pub position: Pubkey,
+}
+
+#[event]
+pub struct SwapEvent {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub receiving_custody: Pubkey,
+    pub dispensing_custody: Pubkey,
+    // receiving custody token
+    pub amount_in: u64,
+    pub fee_in: u64,
+    // dispensing custody token
+    pub amount_out: u64,
+    pub fee_out: u64,
+    // resulting balances
+    pub receiving_custody_owned: u64,
+    pub dispensing_custody_owned: u64,
+}
+
+#[event]
+pub struct AddLiquidityEvent {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub amount_in: u64,
+    pub fee_amount: u64,
+    pub lp_amount: u64,
+    // resulting balances
+    pub custody_owned: u64,
+    pub pool_aum_usd: u128,
+}
+
+#[event]
+pub struct RemoveLiquidityEvent {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub lp_amount: u64,
+    pub fee_amount: u64,
+    pub amount_out: u64,
+    // resulting balances
+    pub custody_owned: u64,
+    pub pool_aum_usd: u128,
+}
+
+#[event]
+pub struct PlaceOrderEvent {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub collateral_custody: Pubkey,
+    pub order: Pubkey,
+    pub order_id: u64,
+    pub position_id: u64,
+    pub order_type: OrderType,
+    pub side: Side,
+    pub trigger_price: u64,
+    pub size: u64,
+    pub collateral_amount: u64,
+}
+
+#[event]
+pub struct CancelOrderEvent {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub order: Pubkey,
+    pub order_id: u64,
+    pub refund_amount: u64,
+}
+
+#[event]
+pub struct ExecuteOrderEvent {
+    pub keeper: Pubkey,
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub collateral_custody: Pubkey,
+    pub order: Pubkey,
+    pub position: Pubkey,
+    pub order_id: u64,
+    pub order_type: OrderType,
+    pub side: Side,
+    pub price: u64,
+    pub size_usd: u64,
+    pub collateral_amount: u64,
+    pub profit_usd: u64,
+    pub loss_usd: u64,
+    pub fee_amount: u64,
+    pub fee_amount_usd: u64,
+    pub reward_amount: u64,
+    pub update_time: i64,
+}
+
+#[event]
+pub struct WithdrawFeesEvent {
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub receiving_account: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct WithdrawSolFeesEvent {
+    pub receiving_account: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct AddPoolEvent {
+    pub pool: Pubkey,
+    pub name: String,
+    pub lp_token_mint: Pubkey,
+}
+
+#[event]
+pub struct RemovePoolEvent {
+    pub pool: Pubkey,
+}
+
+#[event]
+pub struct AddCustodyEvent {
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub mint: Pubkey,
+}
+
+#[event]
+pub struct RemoveCustodyEvent {
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+}
+
+#[event]
+pub struct SetCustodyConfigEvent {
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+}
+
+#[event]
+pub struct SetPoolConfigEvent {
+    pub pool: Pubkey,
+    pub max_oi_usd: u64,
+}
+
+#[event]
+pub struct SetPermissionsEvent {
+    pub permissions: Permissions,
+}
+
+#[event]
+pub struct SetPoolPermissionsEvent {
+    pub pool: Pubkey,
+    pub permissions: Permissions,
+}
+
+#[event]
+pub struct UpgradePoolEvent {
+    pub pool: Pubkey,
+    pub permissions: Permissions,
+}
+
+#[event]
+pub struct SetMarketStatusEvent {
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub market_status: MarketStatus,
+}
+
+#[event]
+pub struct SetCustomOraclePriceEvent {
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub oracle_account: Pubkey,
+    pub price: u64,
+    pub expo: i32,
+    pub conf: u64,
+    pub ema: u64,
+    pub publish_time: i64,
+    // true if pushed through a signed permissionless update
+    pub permissionless: bool,
+}
+
+#[event]
+pub struct GrantRoleEvent {
+    pub role: Role,
+    pub member: Pubkey,
+    pub min_signatures: u8,
+}
+
+#[event]
+pub struct RevokeRoleEvent {
+    pub role: Role,
+    pub member: Pubkey,
+    pub min_signatures: u8,
+}
+
+#[event]
+pub struct ProposeEvent {
+    pub proposal: Pubkey,
+    pub proposal_id: u64,
+    pub role: Role,
+    pub instruction_type: u8,
+    pub instruction_hash: u64,
+    pub eta: i64,
+}
+
+#[event]
+pub struct ExecuteProposalEvent {
+    pub proposal: Pubkey,
+    pub proposal_id: u64,
+    pub instruction_type: u8,
+}
+
+#[event]
+pub struct CancelProposalEvent {
+    pub proposal: Pubkey,
+    pub proposal_id: u64,
+}
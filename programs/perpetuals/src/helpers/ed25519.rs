@@ -0,0 +1,164 @@
+//! Ed25519 program instruction parsing
+
+use {
+    crate::error::PerpetualsError,
+    anchor_lang::prelude::*,
+    solana_program::{ed25519_program, instruction::Instruction},
+};
+
+// Layout of the native Ed25519 program instruction data:
+// [num_signatures: u8, padding: u8, offsets: Ed25519SignatureOffsets, public_key, signature, message]
+const SIGNATURE_OFFSETS_START: usize = 2;
+const SIGNATURE_OFFSETS_SERIALIZED_SIZE: usize = 14;
+const PUBKEY_SERIALIZED_SIZE: usize = 32;
+// instruction index value meaning the data is located in the Ed25519 instruction itself
+const CURRENT_INSTRUCTION_INDEX: u16 = u16::MAX;
+
+struct Ed25519SignatureOffsets {
+    public_key_offset: u16,
+    public_key_instruction_index: u16,
+    message_data_offset: u16,
+    message_data_size: u16,
+    message_instruction_index: u16,
+    signature_instruction_index: u16,
+}
+
+impl Ed25519SignatureOffsets {
+    fn unpack(data: &[u8]) -> Result<Self> {
+        let read_u16 = |offset: usize| -> Result<u16> {
+            let bytes = data
+                .get(offset..offset + 2)
+                .ok_or(PerpetualsError::PermissionlessOracleMalformedEd25519Data)?;
+            Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+        };
+
+        Ok(Self {
+            signature_instruction_index: read_u16(2)?,
+            public_key_offset: read_u16(4)?,
+            public_key_instruction_index: read_u16(6)?,
+            message_data_offset: read_u16(8)?,
+            message_data_size: read_u16(10)?,
+            message_instruction_index: read_u16(12)?,
+        })
+    }
+}
+
+/// Checks that `instruction` is an Ed25519 program instruction verifying a single signature
+/// of `expected_message` by `expected_signer`. The signature itself is verified by the runtime.
+pub fn validate_ed25519_signature_instruction(
+    instruction: &Instruction,
+    expected_signer: &Pubkey,
+    expected_message: &[u8],
+) -> Result<()> {
+    require_keys_eq!(
+        instruction.program_id,
+        ed25519_program::ID,
+        PerpetualsError::PermissionlessOracleMissingSignature
+    );
+
+    let data = &instruction.data;
+    require!(
+        instruction.accounts.is_empty()
+            && data.len() >= SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_SERIALIZED_SIZE
+            && data[0] == 1,
+        PerpetualsError::PermissionlessOracleMalformedEd25519Data
+    );
+
+    let offsets = Ed25519SignatureOffsets::unpack(
+        &data[SIGNATURE_OFFSETS_START..SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_SERIALIZED_SIZE],
+    )?;
+
+    // all data must come from the Ed25519 instruction, otherwise it could point anywhere
+    require!(
+        offsets.signature_instruction_index == CURRENT_INSTRUCTION_INDEX
+            && offsets.public_key_instruction_index == CURRENT_INSTRUCTION_INDEX
+            && offsets.message_instruction_index == CURRENT_INSTRUCTION_INDEX,
+        PerpetualsError::PermissionlessOracleMalformedEd25519Data
+    );
+
+    let public_key_offset = offsets.public_key_offset as usize;
+    let signer = data
+        .get(public_key_offset..public_key_offset + PUBKEY_SERIALIZED_SIZE)
+        .ok_or(PerpetualsError::PermissionlessOracleMalformedEd25519Data)?;
+    require!(
+        signer == expected_signer.as_ref(),
+        PerpetualsError::PermissionlessOracleSignerMismatch
+    );
+
+    let message_data_offset = offsets.message_data_offset as usize;
+    let message = data
+        .get(message_data_offset..message_data_offset + offsets.message_data_size as usize)
+        .ok_or(PerpetualsError::PermissionlessOracleMalformedEd25519Data)?;
+    require!(
+        message == expected_message,
+        PerpetualsError::PermissionlessOracleMessageMismatch
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_fixture(signer: &Pubkey, message: &[u8]) -> Instruction {
+        let public_key_offset = SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_SERIALIZED_SIZE;
+        let signature_offset = public_key_offset + PUBKEY_SERIALIZED_SIZE;
+        let message_data_offset = signature_offset + 64;
+
+        let mut data = vec![1u8, 0];
+        for value in [
+            signature_offset as u16,
+            CURRENT_INSTRUCTION_INDEX,
+            public_key_offset as u16,
+            CURRENT_INSTRUCTION_INDEX,
+            message_data_offset as u16,
+            message.len() as u16,
+            CURRENT_INSTRUCTION_INDEX,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(signer.as_ref());
+        data.extend_from_slice(&[0u8; 64]);
+        data.extend_from_slice(message);
+
+        Instruction {
+            program_id: ed25519_program::ID,
+            accounts: vec![],
+            data,
+        }
+    }
+
+    #[test]
+    fn test_validate_ed25519_signature_instruction() {
+        let signer = Pubkey::new_unique();
+        let message = [1u8, 2, 3, 4];
+        let instruction = get_fixture(&signer, &message);
+
+        assert!(validate_ed25519_signature_instruction(&instruction, &signer, &message).is_ok());
+
+        assert_eq!(
+            validate_ed25519_signature_instruction(&instruction, &Pubkey::new_unique(), &message)
+                .unwrap_err(),
+            PerpetualsError::PermissionlessOracleSignerMismatch.into()
+        );
+        assert_eq!(
+            validate_ed25519_signature_instruction(&instruction, &signer, &[1, 2, 3]).unwrap_err(),
+            PerpetualsError::PermissionlessOracleMessageMismatch.into()
+        );
+
+        let mut other_program = instruction.clone();
+        other_program.program_id = Pubkey::new_unique();
+        assert_eq!(
+            validate_ed25519_signature_instruction(&other_program, &signer, &message).unwrap_err(),
+            PerpetualsError::PermissionlessOracleMissingSignature.into()
+        );
+
+        let mut truncated = instruction;
+        truncated.data.truncate(20);
+        assert_eq!(
+            validate_ed25519_signature_instruction(&truncated, &signer, &message).unwrap_err(),
+            PerpetualsError::PermissionlessOracleMalformedEd25519Data.into()
+        );
+    }
+}
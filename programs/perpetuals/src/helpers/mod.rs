@@ -1,2 +1,4 @@
 pub mod account_map;
 pub use account_map::*;
+pub mod ed25519;
+pub use ed25519::*;
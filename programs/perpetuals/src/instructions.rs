@@ -1,12 +1,20 @@
 // admin instructions
 pub mod add_custody;
 pub mod add_pool;
+pub mod cancel_proposal;
+pub mod grant_role;
 pub mod init;
+pub mod propose;
 pub mod remove_custody;
 pub mod remove_pool;
-pub mod set_admin_signers;
+pub mod revoke_role;
 pub mod set_custody_config;
+pub mod set_custom_oracle_price;
+pub mod set_market_status;
 pub mod set_permissions;
+pub mod set_pool_config;
+pub mod set_pool_permissions;
+pub mod upgrade_pool;
 pub mod withdraw_fees;
 pub mod withdraw_sol_fees;
 
@@ -16,32 +24,54 @@ pub mod set_test_time;
 // public instructions
 pub mod add_collateral;
 pub mod add_liquidity;
+pub mod add_margin_collateral;
+pub mod auto_deleverage;
+pub mod cancel_order;
 pub mod close_position;
+pub mod execute_order;
+pub mod execute_proposal;
+pub mod flag_liquidatable;
 pub mod get_add_liquidity_amount_and_fee;
 pub mod get_assets_under_management;
 pub mod get_entry_price_and_fee;
 pub mod get_exit_price_and_fee;
+pub mod get_insurance_fund_balance;
+pub mod get_insurance_fund_coverage;
 pub mod get_liquidation_price;
 pub mod get_liquidation_state;
 pub mod get_lp_token_price;
 pub mod get_pnl;
 pub mod get_remove_liquidity_amount_and_fee;
 pub mod get_swap_amount_and_fees;
+pub mod increase_position;
+pub mod init_margin_account;
+pub mod link_position;
 pub mod liquidate;
 pub mod open_position;
+pub mod open_position_with_swap;
+pub mod place_order;
 pub mod remove_collateral;
 pub mod remove_liquidity;
+pub mod remove_margin_collateral;
+pub mod set_custom_oracle_price_permissionless;
 pub mod swap;
+pub mod unlink_position;
 pub mod update_pool_aum;
 
 // bring everything in scope
 pub use {
-    add_collateral::*, add_custody::*, add_liquidity::*, add_pool::*, close_position::*,
-    get_add_liquidity_amount_and_fee::*, get_assets_under_management::*,
-    get_entry_price_and_fee::*, get_exit_price_and_fee::*, get_liquidation_price::*,
+    add_collateral::*, add_custody::*, add_liquidity::*, add_margin_collateral::*, add_pool::*,
+    auto_deleverage::*, cancel_order::*, cancel_proposal::*, close_position::*, execute_order::*,
+    execute_proposal::*, flag_liquidatable::*, get_add_liquidity_amount_and_fee::*,
+    get_assets_under_management::*, get_entry_price_and_fee::*, get_exit_price_and_fee::*,
+    get_insurance_fund_balance::*, get_insurance_fund_coverage::*, get_liquidation_price::*,
     get_liquidation_state::*, get_lp_token_price::*, get_pnl::*,
-    get_remove_liquidity_amount_and_fee::*, get_swap_amount_and_fees::*, init::*, liquidate::*,
-    open_position::*, remove_collateral::*, remove_custody::*, remove_liquidity::*, remove_pool::*,
-    set_admin_signers::*, set_custody_config::*, set_permissions::*, set_test_time::*, swap::*,
-    update_pool_aum::*, withdraw_fees::*, withdraw_sol_fees::*,
+    get_remove_liquidity_amount_and_fee::*, get_swap_amount_and_fees::*, grant_role::*,
+    increase_position::*, init::*, init_margin_account::*, link_position::*, liquidate::*,
+    open_position::*, open_position_with_swap::*, place_order::*, propose::*, remove_collateral::*,
+    remove_custody::*, remove_liquidity::*, remove_margin_collateral::*, remove_pool::*,
+    revoke_role::*, set_custody_config::*, set_custom_oracle_price::*,
+    set_custom_oracle_price_permissionless::*, set_market_status::*, set_permissions::*,
+    set_pool_config::*, set_pool_permissions::*, set_test_time::*, swap::*, unlink_position::*,
+    update_pool_aum::*, upgrade_pool::*, withdraw_fees::*, withdraw_sol_fees::*,
 };
@@ -4,6 +4,7 @@ use {
     crate::{
         constants::{CUSTODY_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED, POSITION_SEED},
         error::PerpetualsError,
+        events::AddCollateralEvent,
         math,
         oracle::OraclePrice,
         state::{
@@ -59,7 +60,8 @@ pub struct AddCollateral<'info> {
                  owner.key().as_ref(),
                  pool.key().as_ref(),
                  custody.key().as_ref(),
-                 &[position.side as u8]],
+                 &[position.side as u8],
+                 &position.position_id.to_le_bytes()],
         bump = position.bump
     )]
     pub position: Box<Account<'info, Position>>,
@@ -116,6 +118,10 @@ pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams)
     let collateral_custody = ctx.accounts.collateral_custody.as_mut();
     let position = ctx.accounts.position.as_mut();
     let pool = ctx.accounts.pool.as_mut();
+    require!(
+        custody.market_status.allow_collateral_deposit(),
+        PerpetualsError::MarketStatusRestricted
+    );
 
     // compute position price
     let curtime = perpetuals.get_time()?;
@@ -125,6 +131,8 @@ pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams)
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -132,6 +140,8 @@ pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams)
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         custody.pricing.use_ema,
     )?;
 
@@ -141,6 +151,8 @@ pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams)
             .to_account_info(),
         &clock,
         collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -150,6 +162,8 @@ pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams)
             .to_account_info(),
         &clock,
         collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
         collateral_custody.pricing.use_ema,
     )?;
 
@@ -184,6 +198,7 @@ pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams)
         )?,
         PerpetualsError::MaxLeverage
     );
+    position.liquidatable_since = 0;
 
     // transfer tokens
     msg!("Transfer tokens");
@@ -204,8 +219,24 @@ pub fn add_collateral(ctx: Context<AddCollateral>, params: &AddCollateralParams)
 
     // if custody and collateral_custody accounts are the same, ensure that data is in sync
     if position.side == Side::Long && !custody.is_virtual {
+        collateral_custody.add_position_collateral(position.side, collateral_usd)?;
         *custody = collateral_custody.clone();
+    } else {
+        custody.add_position_collateral(position.side, collateral_usd)?;
     }
 
+    emit!(AddCollateralEvent {
+        owner: position.owner,
+        pool: pool.key(),
+        custody: custody.key(),
+        collateral_custody: collateral_custody.key(),
+        position: position.key(),
+        collateral_amount: params.collateral,
+        collateral_usd,
+        position_collateral_amount: position.collateral_amount,
+        position_collateral_usd: position.collateral_usd,
+        update_time: position.update_time,
+    });
+
     Ok(())
 }
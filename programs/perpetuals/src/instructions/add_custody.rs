@@ -1,10 +1,16 @@
 //! AddCustody instruction handler
 use {
     crate::{
-        constants::{CUSTODY_SEED, CUSTODY_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED},
+        constants::{
+            CUSTODY_SEED, CUSTODY_TOKEN_ACCOUNT_SEED, CUSTOM_ORACLE_SEED,
+            INSURANCE_FUND_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED,
+        },
         error::PerpetualsError,
+        events::AddCustodyEvent,
         state::{
-            custody::{BorrowRateParams, Custody, Fees, Oracle, PricingParams},
+            custody::{BorrowRateParams, Custody, Fees, FundingRateParams, Oracle, PricingParams},
+            multisig::{AdminInstruction, Multisig, Role},
+            oracle::OracleParams,
             perpetuals::{Permissions, Perpetuals},
             pool::{Pool, TokenRatios},
         },
@@ -14,12 +20,23 @@ use {
 };
 
 #[derive(Accounts)]
-#[instruction(
-    args: AddCustodyParams
-)]
 pub struct AddCustody<'info> {
     #[account(mut)]
-    pub signer: Signer<'info>,
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig", &[Role::PoolAdmin as u8]],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
 
     #[account(
         seeds = [
@@ -32,19 +49,19 @@ pub struct AddCustody<'info> {
     #[account(
         mut,
         realloc = Pool::LEN + (pool.ratios.len() + 1) * std::mem::size_of::<TokenRatios>(),
-        realloc::payer = signer,
+        realloc::payer = admin,
         realloc::zero = false,
         seeds = [
             POOL_SEED.as_bytes(),
-            &args.pool_id.to_le_bytes()
+            pool.name.as_bytes()
         ],
         bump = pool.bump
     )]
     pub pool: Box<Account<'info, Pool>>,
 
     #[account(
-        init,
-        payer = signer,
+        init_if_needed,
+        payer = admin,
         space = Custody::LEN,
         seeds = [
             CUSTODY_SEED.as_bytes(),
@@ -56,10 +73,10 @@ pub struct AddCustody<'info> {
     pub custody: Box<Account<'info, Custody>>,
 
     #[account(
-        init,
-        payer = signer,
+        init_if_needed,
+        payer = admin,
         token::mint = custody_token_mint,
-        token::authority = custody,
+        token::authority = transfer_authority,
         seeds = [
             CUSTODY_TOKEN_ACCOUNT_SEED.as_bytes(),
             pool.key().as_ref(),
@@ -69,6 +86,20 @@ pub struct AddCustody<'info> {
     )]
     pub custody_token_account: Box<Account<'info, TokenAccount>>,
 
+    #[account(
+        init_if_needed,
+        payer = admin,
+        token::mint = custody_token_mint,
+        token::authority = transfer_authority,
+        seeds = [
+            INSURANCE_FUND_TOKEN_ACCOUNT_SEED.as_bytes(),
+            pool.key().as_ref(),
+            custody_token_mint.key().as_ref()
+        ],
+        bump
+    )]
+    pub insurance_fund_token_account: Box<Account<'info, TokenAccount>>,
+
     #[account()]
     pub custody_token_mint: Box<Account<'info, Mint>>,
 
@@ -76,6 +107,10 @@ pub struct AddCustody<'info> {
     #[account()]
     pub oracle_account: AccountInfo<'info>,
 
+    /// CHECK: We're deserializing and validating it later
+    #[account()]
+    pub secondary_oracle_account: Option<AccountInfo<'info>>,
+
     system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
     rent: Sysvar<'info, Rent>,
@@ -83,13 +118,14 @@ pub struct AddCustody<'info> {
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct AddCustodyParams {
-    pub pool_id: u64,
     pub is_stable: bool,
     pub is_virtual: bool,
+    pub oracle_params: OracleParams,
     pub pricing: PricingParams,
     pub permissions: Permissions,
     pub fees: Fees,
     pub borrow_rate: BorrowRateParams,
+    pub funding_rate: FundingRateParams,
     pub ratios: Vec<TokenRatios>,
 }
 
@@ -97,6 +133,22 @@ pub fn add_custody<'info>(
     ctx: Context<'_, '_, '_, 'info, AddCustody<'info>>,
     params: &AddCustodyParams,
 ) -> Result<u8> {
+    // validate signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::AddCustody, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
     if params.ratios.len() != ctx.accounts.pool.ratios.len() + 1 {
         return Err(ProgramError::InvalidArgument.into());
     }
@@ -115,29 +167,65 @@ pub fn add_custody<'info>(
     let custody = ctx.accounts.custody.as_mut();
     let oracle_account = &ctx.accounts.oracle_account;
 
-    let oracle = Oracle::from_account_info(oracle_account, &clock)?;
+    // custom oracle accounts are created on the first price update
+    let (custom_oracle, _) = Pubkey::find_program_address(
+        &[
+            CUSTOM_ORACLE_SEED.as_bytes(),
+            pool.key().as_ref(),
+            ctx.accounts.custody_token_mint.key().as_ref(),
+        ],
+        &crate::ID,
+    );
+    let oracle = if oracle_account.key() == custom_oracle {
+        Oracle::Custom(custom_oracle)
+    } else {
+        Oracle::from_account_info(oracle_account, &clock, &params.oracle_params)?
+    };
+    let secondary_oracle = match &ctx.accounts.secondary_oracle_account {
+        Some(account) if account.key() == custom_oracle => Some(Oracle::Custom(custom_oracle)),
+        Some(account) => Some(Oracle::from_account_info(
+            account,
+            &clock,
+            &params.oracle_params,
+        )?),
+        None => None,
+    };
     custody.oracle = oracle;
+    custody.secondary_oracle = secondary_oracle;
     custody.pool = pool.key();
     custody.mint = ctx.accounts.custody_token_mint.key();
     custody.token_account = ctx.accounts.custody_token_account.key();
     custody.decimals = ctx.accounts.custody_token_mint.decimals;
     custody.is_stable = params.is_stable;
     custody.is_virtual = params.is_virtual;
+    custody.oracle_params = params.oracle_params;
     custody.pricing = params.pricing;
     custody.permissions = params.permissions;
     custody.fees = params.fees;
     custody.borrow_rate = params.borrow_rate;
+    custody.funding_rate = params.funding_rate;
     custody.borrow_rate_state.current_rate = params.borrow_rate.base_rate;
     custody.borrow_rate_state.last_update = ctx.accounts.perpetuals.get_time()?;
+    custody.funding_rate_state.last_update = custody.borrow_rate_state.last_update;
     custody.bump = *ctx.bumps.get("custody").ok_or(ProgramError::InvalidSeeds)?;
     custody.token_account_bump = *ctx
         .bumps
         .get("custody_token_account")
         .ok_or(ProgramError::InvalidSeeds)?;
+    custody.insurance_fund_token_account_bump = *ctx
+        .bumps
+        .get("insurance_fund_token_account")
+        .ok_or(ProgramError::InvalidSeeds)?;
 
     if !custody.validate() {
-        err!(PerpetualsError::InvalidCustodyConfig)
-    } else {
-        Ok(0)
+        return err!(PerpetualsError::InvalidCustodyConfig);
     }
+
+    emit!(AddCustodyEvent {
+        pool: custody.pool,
+        custody: custody.key(),
+        mint: custody.mint,
+    });
+
+    Ok(0)
 }
@@ -7,6 +7,7 @@ use {
             POOL_SEED,
         },
         error::PerpetualsError,
+        events::AddLiquidityEvent,
         helpers::AccountMap,
         math,
         oracle::OraclePrice,
@@ -41,6 +42,13 @@ pub struct AddLiquidity<'info> {
     )]
     pub lp_token_account: Box<Account<'info, TokenAccount>>,
 
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
     #[account(
         seeds = [
             PERPETUALS_SEED.as_bytes(),
@@ -105,7 +113,6 @@ pub struct AddLiquidity<'info> {
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct AddLiquidityParams {
-    pub pool_id: u64,
     pub amount_in: u64,
     pub min_lp_amount_out: u64,
 }
@@ -117,6 +124,7 @@ pub fn add_liquidity(ctx: Context<AddLiquidity>, params: &AddLiquidityParams) ->
     let custody = ctx.accounts.custody.as_mut();
     require!(
         perpetuals.permissions.allow_add_liquidity
+            && ctx.accounts.pool.permissions.allow_add_liquidity
             && custody.permissions.allow_add_liquidity
             && !custody.is_virtual,
         PerpetualsError::InstructionNotAllowed
@@ -145,6 +153,8 @@ pub fn add_liquidity(ctx: Context<AddLiquidity>, params: &AddLiquidityParams) ->
         &ctx.accounts.custody_oracle_account.to_account_info(),
         clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -156,6 +166,8 @@ pub fn add_liquidity(ctx: Context<AddLiquidity>, params: &AddLiquidityParams) ->
         oracle_account,
         clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         custody.pricing.use_ema,
     )?;
 
@@ -225,7 +237,7 @@ pub fn add_liquidity(ctx: Context<AddLiquidity>, params: &AddLiquidityParams) ->
     perpetuals.mint_tokens(
         ctx.accounts.lp_token_mint.to_account_info(),
         ctx.accounts.lp_token_account.to_account_info(),
-        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
         ctx.accounts.token_program.to_account_info(),
         lp_amount,
     )?;
@@ -253,5 +265,16 @@ pub fn add_liquidity(ctx: Context<AddLiquidity>, params: &AddLiquidityParams) ->
     custody.exit(&crate::ID)?;
     pool.aum_usd = pool.get_assets_under_management_usd(AumCalcMode::EMA, &account_map, clock)?;
 
+    emit!(AddLiquidityEvent {
+        owner: ctx.accounts.owner.key(),
+        pool: pool.key(),
+        custody: custody.key(),
+        amount_in: params.amount_in,
+        fee_amount,
+        lp_amount,
+        custody_owned: custody.assets.owned,
+        pool_aum_usd: pool.aum_usd,
+    });
+
     Ok(())
 }
@@ -0,0 +1,125 @@
+//! AddMarginCollateral instruction handler
+
+use {
+    crate::{
+        constants::{
+            CUSTODY_SEED, CUSTODY_TOKEN_ACCOUNT_SEED, MARGIN_ACCOUNT_SEED, PERPETUALS_SEED,
+            POOL_SEED,
+        },
+        error::PerpetualsError,
+        events::AddMarginCollateralEvent,
+        math,
+        state::{
+            custody::Custody, margin_account::MarginAccount, perpetuals::Perpetuals, pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+pub struct AddMarginCollateral<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = funding_account.mint == custody.mint,
+        has_one = owner
+    )]
+    pub funding_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [PERPETUALS_SEED.as_bytes()],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [POOL_SEED.as_bytes(),
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MARGIN_ACCOUNT_SEED.as_bytes(),
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump = margin_account.bump
+    )]
+    pub margin_account: Box<Account<'info, MarginAccount>>,
+
+    #[account(
+        mut,
+        seeds = [CUSTODY_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    #[account(
+        mut,
+        seeds = [CUSTODY_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.token_account_bump
+    )]
+    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AddMarginCollateralParams {
+    pub amount: u64,
+}
+
+pub fn add_margin_collateral(
+    ctx: Context<AddMarginCollateral>,
+    params: &AddMarginCollateralParams,
+) -> Result<()> {
+    // validate inputs
+    msg!("Validate inputs");
+    if params.amount == 0 {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    require!(
+        custody.is_stable && !custody.is_virtual,
+        PerpetualsError::InvalidCollateralCustody
+    );
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens_from_user(
+        ctx.accounts.funding_account.to_account_info(),
+        ctx.accounts.custody_token_account.to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.amount,
+    )?;
+
+    // update margin account and custody stats
+    msg!("Update margin account");
+    let margin_account = ctx.accounts.margin_account.as_mut();
+    margin_account.add_collateral(&custody.key(), params.amount)?;
+
+    custody.assets.collateral = math::checked_add(custody.assets.collateral, params.amount)?;
+
+    emit!(AddMarginCollateralEvent {
+        owner: margin_account.owner,
+        pool: margin_account.pool,
+        margin_account: margin_account.key(),
+        custody: custody.key(),
+        amount: params.amount,
+        margin_amount: margin_account.get_collateral_amount(&custody.key()),
+    });
+
+    Ok(())
+}
@@ -3,7 +3,12 @@ use {
     crate::{
         constants::{LP_TOKEN_MINT_SEED, PERPETUALS_SEED, POOL_SEED},
         error::PerpetualsError,
-        state::{perpetuals::Perpetuals, pool::Pool},
+        events::AddPoolEvent,
+        state::{
+            multisig::{AdminInstruction, Multisig, Role},
+            perpetuals::{Permissions, Perpetuals},
+            pool::Pool,
+        },
     },
     anchor_lang::prelude::*,
     anchor_spl::token::{Mint, Token},
@@ -12,9 +17,22 @@ use {
 #[derive(Accounts)]
 #[instruction(params: AddPoolParams)]
 pub struct AddPool<'info> {
-    // Allow anyone to add new pool.
     #[account(mut)]
-    pub signer: Signer<'info>,
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig", &[Role::PoolAdmin as u8]],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
 
     #[account(
         mut,
@@ -26,22 +44,22 @@ pub struct AddPool<'info> {
     pub perpetuals: Box<Account<'info, Perpetuals>>,
 
     #[account(
-        init,
-        payer = signer,
+        init_if_needed,
+        payer = admin,
         space = Pool::LEN,
         seeds = [
             POOL_SEED.as_bytes(),
-            &perpetuals.pools.to_le_bytes(),
+            params.name.as_bytes()
         ],
         bump
     )]
     pub pool: Box<Account<'info, Pool>>,
 
     #[account(
-        init,
-        payer = signer,
-        mint::authority = pool,
-        mint::freeze_authority = pool,
+        init_if_needed,
+        payer = admin,
+        mint::authority = transfer_authority,
+        mint::freeze_authority = transfer_authority,
         mint::decimals = Perpetuals::LP_DECIMALS,
         seeds = [
             LP_TOKEN_MINT_SEED.as_bytes(),
@@ -59,18 +77,40 @@ pub struct AddPool<'info> {
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct AddPoolParams {
     pub name: String,
+    pub permissions: Permissions,
 }
 
 pub fn add_pool<'info>(
     ctx: Context<'_, '_, '_, 'info, AddPool<'info>>,
     params: &AddPoolParams,
 ) -> Result<u8> {
+    // validate signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::AddPool, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
     // record pool data
     let perpetuals = ctx.accounts.perpetuals.as_mut();
     let pool = ctx.accounts.pool.as_mut();
 
+    // pool accounts are created with the first signature, never overwrite a live pool
+    require!(pool.name.is_empty(), PerpetualsError::InvalidPoolState);
+
     pool.inception_time = perpetuals.get_time()?;
     pool.name = params.name.clone();
+    pool.permissions = params.permissions;
+    pool.version = Pool::VERSION;
     pool.bump = *ctx.bumps.get("pool").ok_or(ProgramError::InvalidSeeds)?;
     pool.lp_token_bump = *ctx
         .bumps
@@ -84,7 +124,11 @@ pub fn add_pool<'info>(
 
     perpetuals.pools += 1;
 
-    // TODO: Add event for off-chain indexing.
+    emit!(AddPoolEvent {
+        pool: pool.key(),
+        name: pool.name.clone(),
+        lp_token_mint: ctx.accounts.lp_token_mint.key(),
+    });
 
     Ok(0)
 }
@@ -0,0 +1,433 @@
+//! AutoDeleverage instruction handler
+
+use {
+    crate::{
+        constants::{CUSTODY_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED, POSITION_SEED},
+        error::PerpetualsError,
+        events::AutoDeleverageEvent,
+        helpers::AccountMap,
+        math,
+        oracle::OraclePrice,
+        state::{
+            custody::Custody,
+            margin_account::MarginAccount,
+            perpetuals::Perpetuals,
+            pool::Pool,
+            position::{Position, Side},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+};
+
+#[derive(Accounts)]
+pub struct AutoDeleverage<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    /// CHECK: position owner, receives the position account rent
+    #[account(
+        mut,
+        constraint = owner.key() == position.owner
+    )]
+    pub owner: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = receiving_account.mint == collateral_custody.mint,
+        constraint = receiving_account.owner == position.owner
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [PERPETUALS_SEED.as_bytes()],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [POOL_SEED.as_bytes(),
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        seeds = [POSITION_SEED.as_bytes(),
+                 position.owner.as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8],
+                 &position.position_id.to_le_bytes()],
+        bump = position.bump,
+        close = owner
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(
+        mut,
+        constraint = position.custody == custody.key()
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the position token
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.key()
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = position.collateral_custody == collateral_custody.key()
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the collateral token
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.key()
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [CUSTODY_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.token_account_bump
+    )]
+    pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+    // remaining accounts:
+    //   other profitable positions of the same custody and side, the deleveraged
+    //   position must rank at least as high as each of them, in addition to the
+    //   side-wide score threshold (read-only, unsigned)
+    //   for positions linked to a margin account: the margin account (writable)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AutoDeleverageParams {}
+
+pub fn auto_deleverage(ctx: Context<AutoDeleverage>, _params: &AutoDeleverageParams) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    require!(
+        perpetuals.permissions.allow_close_position
+            && ctx.accounts.pool.permissions.allow_close_position
+            && custody.permissions.allow_close_position,
+        PerpetualsError::InstructionNotAllowed
+    );
+    require!(
+        custody.market_status.allow_liquidation(),
+        PerpetualsError::MarketStatusRestricted
+    );
+
+    let position = ctx.accounts.position.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
+
+    // compute exit price
+    let curtime = perpetuals.get_time()?;
+    let clock = Clock::get()?;
+
+    let token_price = OraclePrice::new_from_oracle(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        &clock,
+        custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
+        false,
+    )?;
+
+    let token_ema_price = OraclePrice::new_from_oracle(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        &clock,
+        custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
+        custody.pricing.use_ema,
+    )?;
+
+    let collateral_token_price = OraclePrice::new_from_oracle(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        &clock,
+        collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
+        false,
+    )?;
+
+    let collateral_token_ema_price = OraclePrice::new_from_oracle(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        &clock,
+        collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
+        collateral_custody.pricing.use_ema,
+    )?;
+
+    // check custody solvency
+    msg!("Check auto-deleveraging threshold");
+    require!(
+        pool.check_adl_threshold(
+            position.side,
+            &token_price,
+            &token_ema_price,
+            custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+        )?,
+        PerpetualsError::AutoDeleverageNotAllowed
+    );
+
+    // check position ranking
+    msg!("Check position ranking");
+    let adl_score = pool.get_adl_score(
+        position,
+        &token_price,
+        &token_ema_price,
+        custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+    )?;
+    msg!("ADL score: {}", adl_score);
+    require!(adl_score > 0, PerpetualsError::InvalidPositionState);
+
+    let adl_score_threshold = pool.get_adl_score_threshold(
+        position.side,
+        &token_price,
+        &token_ema_price,
+        custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+    )?;
+    msg!("ADL score threshold: {}", adl_score_threshold);
+    require_gte!(
+        adl_score,
+        adl_score_threshold,
+        PerpetualsError::AutoDeleverageRankMismatch
+    );
+
+    // best-effort ranking against positions supplied by the caller, a keeper can leave
+    // out higher ranked positions, only the side-wide score threshold above is enforced
+    for account in ctx.remaining_accounts {
+        if account.key() == position.key() {
+            continue;
+        }
+        if let Ok(other_position) = Account::<Position>::try_from(account) {
+            if other_position.custody != position.custody
+                || other_position.collateral_custody != position.collateral_custody
+                || other_position.side != position.side
+            {
+                continue;
+            }
+            let other_score = pool.get_adl_score(
+                &other_position,
+                &token_price,
+                &token_ema_price,
+                custody,
+                &collateral_token_price,
+                &collateral_token_ema_price,
+                collateral_custody,
+                curtime,
+            )?;
+            require_gte!(
+                adl_score,
+                other_score,
+                PerpetualsError::AutoDeleverageRankMismatch
+            );
+        }
+    }
+
+    msg!("Settle position");
+    let exit_price = pool.get_exit_price(
+        &token_price,
+        &token_ema_price,
+        position.side,
+        position.size_usd,
+        custody,
+    )?;
+    msg!("Exit price: {}", exit_price);
+
+    let (close_amount, mut fee_amount, profit_usd, _) = pool.get_close_amount(
+        position,
+        &token_price,
+        &token_ema_price,
+        custody,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+        curtime,
+        false,
+    )?;
+
+    let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
+    if position.side == Side::Short || custody.is_virtual {
+        fee_amount = collateral_token_ema_price
+            .get_token_amount(fee_amount_usd, collateral_custody.decimals)?;
+    }
+
+    msg!("Net profit: {}", profit_usd);
+    msg!("Collected fee: {}", fee_amount);
+
+    // deleveraged positions are profitable, linked positions are only removed from the margin account
+    if position.margin_account != Pubkey::default() {
+        let account_map = AccountMap::from_remaining_accounts(ctx.remaining_accounts);
+        let mut margin_account =
+            Account::<MarginAccount>::try_from(account_map.get_account(&position.margin_account)?)?;
+        margin_account.remove_position(&position.key());
+        margin_account.exit(&crate::ID)?;
+    }
+
+    // unlock pool funds
+    collateral_custody.unlock_funds(position.locked_amount)?;
+
+    // pay out as much as the custody can afford, the rest of the profit is forfeited
+    let available_amount = math::checked_sub(
+        math::checked_add(
+            collateral_custody.assets.owned,
+            collateral_custody.assets.collateral,
+        )?,
+        collateral_custody.assets.locked,
+    )?;
+    let transfer_amount = std::cmp::min(close_amount, available_amount);
+    let shortfall_amount = math::checked_sub(close_amount, transfer_amount)?;
+
+    msg!("Amount out: {}", transfer_amount);
+    msg!("Shortfall: {}", shortfall_amount);
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        transfer_amount,
+    )?;
+
+    // update custody stats
+    msg!("Update custody stats");
+    collateral_custody.collected_fees.close_position_usd = collateral_custody
+        .collected_fees
+        .close_position_usd
+        .wrapping_add(fee_amount_usd);
+
+    if transfer_amount > position.collateral_amount {
+        let amount_lost = transfer_amount.saturating_sub(position.collateral_amount);
+        collateral_custody.assets.owned =
+            math::checked_sub(collateral_custody.assets.owned, amount_lost)?;
+    } else {
+        let amount_gained = position.collateral_amount.saturating_sub(transfer_amount);
+        collateral_custody.assets.owned =
+            math::checked_add(collateral_custody.assets.owned, amount_gained)?;
+    }
+    collateral_custody.assets.collateral = math::checked_sub(
+        collateral_custody.assets.collateral,
+        position.collateral_amount,
+    )?;
+
+    let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
+
+    // Pay protocol_fee from custody if possible, otherwise no protocol_fee
+    if pool.check_available_amount(protocol_fee, collateral_custody)? {
+        collateral_custody.assets.protocol_fees =
+            math::checked_add(collateral_custody.assets.protocol_fees, protocol_fee)?;
+
+        collateral_custody.assets.owned =
+            math::checked_sub(collateral_custody.assets.owned, protocol_fee)?;
+    }
+
+    // if custody and collateral_custody accounts are the same, ensure that data is in sync
+    if position.side == Side::Long && !custody.is_virtual {
+        collateral_custody.volume_stats.close_position_usd = collateral_custody
+            .volume_stats
+            .close_position_usd
+            .wrapping_add(position.size_usd);
+
+        collateral_custody.trade_stats.oi_long_usd = collateral_custody
+            .trade_stats
+            .oi_long_usd
+            .saturating_sub(position.size_usd);
+
+        collateral_custody.trade_stats.profit_usd = collateral_custody
+            .trade_stats
+            .profit_usd
+            .wrapping_add(profit_usd);
+
+        collateral_custody.remove_position(position, curtime, None)?;
+        collateral_custody.update_borrow_rate(curtime)?;
+        collateral_custody.update_funding_rate(curtime)?;
+        *custody = collateral_custody.clone();
+    } else {
+        custody.volume_stats.close_position_usd = custody
+            .volume_stats
+            .close_position_usd
+            .wrapping_add(position.size_usd);
+
+        if position.side == Side::Long {
+            custody.trade_stats.oi_long_usd = custody
+                .trade_stats
+                .oi_long_usd
+                .saturating_sub(position.size_usd);
+        } else {
+            custody.trade_stats.oi_short_usd = custody
+                .trade_stats
+                .oi_short_usd
+                .saturating_sub(position.size_usd);
+        }
+
+        custody.trade_stats.profit_usd = custody.trade_stats.profit_usd.wrapping_add(profit_usd);
+
+        custody.remove_position(position, curtime, Some(collateral_custody))?;
+        collateral_custody.update_borrow_rate(curtime)?;
+        custody.update_funding_rate(curtime)?;
+    }
+
+    pool.remove_open_interest(position.size_usd);
+
+    emit!(AutoDeleverageEvent {
+        signer: ctx.accounts.signer.key(),
+        owner: position.owner,
+        pool: pool.key(),
+        custody: custody.key(),
+        collateral_custody: collateral_custody.key(),
+        position: position.key(),
+        side: position.side,
+        price: exit_price,
+        size_usd: position.size_usd,
+        collateral_amount: position.collateral_amount,
+        profit_usd,
+        fee_amount,
+        fee_amount_usd,
+        transfer_amount,
+        shortfall_amount,
+        adl_score,
+        update_time: curtime,
+    });
+
+    Ok(())
+}
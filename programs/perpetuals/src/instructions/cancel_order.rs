@@ -0,0 +1,101 @@
+//! CancelOrder instruction handler
+
+use {
+    crate::{
+        constants::{ORDER_SEED, ORDER_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED},
+        events::CancelOrderEvent,
+        state::{order::Order, perpetuals::Perpetuals},
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+};
+
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = receiving_account.mint == order_token_account.mint,
+        has_one = owner
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [PERPETUALS_SEED.as_bytes()],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [ORDER_SEED.as_bytes(),
+                 owner.key().as_ref(),
+                 order.pool.as_ref(),
+                 order.custody.as_ref(),
+                 &order.order_id.to_le_bytes()],
+        bump = order.bump,
+        close = owner
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        seeds = [ORDER_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 order.key().as_ref()],
+        bump = order.token_account_bump
+    )]
+    pub order_token_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CancelOrderParams {}
+
+pub fn cancel_order(ctx: Context<CancelOrder>, _params: &CancelOrderParams) -> Result<()> {
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+
+    // return escrowed tokens
+    msg!("Transfer tokens");
+    let amount = ctx.accounts.order_token_account.amount;
+    msg!("Amount out: {}", amount);
+    if amount > 0 {
+        perpetuals.transfer_tokens(
+            ctx.accounts.order_token_account.to_account_info(),
+            ctx.accounts.receiving_account.to_account_info(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            amount,
+        )?;
+    }
+
+    Perpetuals::close_token_account(
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.order_token_account.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        &[&[b"transfer_authority", &[perpetuals.transfer_authority_bump]]],
+    )?;
+
+    let order = ctx.accounts.order.as_ref();
+    emit!(CancelOrderEvent {
+        owner: order.owner,
+        pool: order.pool,
+        custody: order.custody,
+        order: order.key(),
+        order_id: order.order_id,
+        refund_amount: amount,
+    });
+
+    Ok(())
+}
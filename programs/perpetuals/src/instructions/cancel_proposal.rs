@@ -0,0 +1,75 @@
+//! CancelProposal instruction handler
+
+use {
+    crate::{
+        constants::PROPOSAL_SEED,
+        error::PerpetualsError,
+        events::CancelProposalEvent,
+        state::{
+            multisig::{AdminInstruction, Multisig, Role},
+            proposal::{Proposal, ProposalStatus},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct CancelProposal<'info> {
+    #[account()]
+    pub admin: Signer<'info>,
+
+    // the pauser acts as guardian and can veto any queued proposal
+    #[account(
+        mut,
+        seeds = [b"multisig", &[Role::Pauser as u8]],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    #[account(
+        mut,
+        seeds = [PROPOSAL_SEED.as_bytes(),
+                 &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CancelProposalParams {}
+
+pub fn cancel_proposal<'info>(
+    ctx: Context<'_, '_, '_, 'info, CancelProposal<'info>>,
+    params: &CancelProposalParams,
+) -> Result<u8> {
+    // validate signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::CancelProposal, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // cancel proposal
+    let proposal = ctx.accounts.proposal.as_mut();
+    require!(
+        proposal.status == ProposalStatus::Queued,
+        PerpetualsError::InvalidProposalState
+    );
+    proposal.status = ProposalStatus::Cancelled;
+
+    emit!(CancelProposalEvent {
+        proposal: ctx.accounts.proposal.key(),
+        proposal_id: ctx.accounts.proposal.proposal_id,
+    });
+
+    Ok(0)
+}
@@ -2,12 +2,18 @@
 
 use {
     crate::{
-        constants::{CUSTODY_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED, POSITION_SEED},
+        constants::{
+            CUSTODY_TOKEN_ACCOUNT_SEED, INSURANCE_FUND_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED,
+            POOL_SEED, POSITION_SEED,
+        },
         error::PerpetualsError,
+        events::ClosePositionEvent,
+        helpers::AccountMap,
         math,
         oracle::OraclePrice,
         state::{
             custody::Custody,
+            margin_account::MarginAccount,
             perpetuals::Perpetuals,
             pool::Pool,
             position::{Position, Side},
@@ -57,9 +63,9 @@ pub struct ClosePosition<'info> {
                  owner.key().as_ref(),
                  pool.key().as_ref(),
                  custody.key().as_ref(),
-                 &[position.side as u8]],
-        bump = position.bump,
-        close = owner
+                 &[position.side as u8],
+                 &position.position_id.to_le_bytes()],
+        bump = position.bump
     )]
     pub position: Box<Account<'info, Position>>,
 
@@ -96,12 +102,25 @@ pub struct ClosePosition<'info> {
     )]
     pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
 
+    #[account(
+        mut,
+        seeds = [INSURANCE_FUND_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.insurance_fund_token_account_bump
+    )]
+    pub insurance_fund_token_account: Box<Account<'info, TokenAccount>>,
+
     token_program: Program<'info, Token>,
+    // remaining accounts:
+    //   for positions linked to a margin account: the margin account (writable)
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
 pub struct ClosePositionParams {
     pub price: u64,
+    // size to close, the position is closed entirely if it's greater or equal to position.size_usd
+    pub size_usd: u64,
 }
 
 pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams) -> Result<()> {
@@ -111,17 +130,23 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
     let custody = ctx.accounts.custody.as_mut();
     let collateral_custody = ctx.accounts.collateral_custody.as_mut();
     require!(
-        perpetuals.permissions.allow_close_position && custody.permissions.allow_close_position,
+        perpetuals.permissions.allow_close_position
+            && ctx.accounts.pool.permissions.allow_close_position
+            && custody.permissions.allow_close_position,
         PerpetualsError::InstructionNotAllowed
     );
 
     // validate inputs
     msg!("Validate inputs");
-    if params.price == 0 {
+    if params.price == 0 || params.size_usd == 0 {
         return Err(ProgramError::InvalidArgument.into());
     }
     let position = ctx.accounts.position.as_mut();
     let pool = ctx.accounts.pool.as_mut();
+    require!(
+        params.size_usd >= position.size_usd || custody.market_status.allow_partial_close(),
+        PerpetualsError::MarketStatusRestricted
+    );
 
     // compute exit price
     let curtime = perpetuals.get_time()?;
@@ -131,6 +156,8 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -138,6 +165,8 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         custody.pricing.use_ema,
     )?;
 
@@ -147,6 +176,8 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
             .to_account_info(),
         &clock,
         collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -156,10 +187,18 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
             .to_account_info(),
         &clock,
         collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
         collateral_custody.pricing.use_ema,
     )?;
 
-    let exit_price = pool.get_exit_price(&token_price, &token_ema_price, position.side, custody)?;
+    let exit_price = pool.get_exit_price(
+        &token_price,
+        &token_ema_price,
+        position.side,
+        std::cmp::min(params.size_usd, position.size_usd),
+        custody,
+    )?;
     msg!("Exit price: {}", exit_price);
 
     if position.side == Side::Long {
@@ -169,8 +208,18 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
     }
 
     msg!("Settle position");
+    let (closed_position, mut remaining_position) = position.split(params.size_usd)?;
+    let partial_close = remaining_position.size_usd > 0;
+    if partial_close {
+        msg!(
+            "Partial close: {} / {}",
+            closed_position.size_usd,
+            position.size_usd
+        );
+    }
+
     let (transfer_amount, mut fee_amount, profit_usd, loss_usd) = pool.get_close_amount(
-        position,
+        &closed_position,
         &token_price,
         &token_ema_price,
         custody,
@@ -191,15 +240,85 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
     msg!("Collected fee: {}", fee_amount);
     msg!("Amount out: {}", transfer_amount);
 
-    // unlock pool funds
-    collateral_custody.unlock_funds(position.locked_amount)?;
+    // settle interest accrued by the remaining part, it keeps running from the current snapshot
+    if partial_close {
+        let interest_usd =
+            collateral_custody.get_interest_amount_usd(&remaining_position, curtime)?;
+        remaining_position.unrealized_loss_usd =
+            math::checked_add(remaining_position.unrealized_loss_usd, interest_usd)?;
+        remaining_position.cumulative_interest_snapshot =
+            collateral_custody.get_cumulative_interest(curtime)?;
+        remaining_position.update_time = curtime;
+
+        // check position risk
+        msg!("Check position risks");
+        require!(
+            remaining_position.locked_amount > 0,
+            PerpetualsError::InsufficientAmountReturned
+        );
+        require!(
+            pool.check_leverage(
+                &remaining_position,
+                &token_price,
+                &token_ema_price,
+                custody,
+                &collateral_token_price,
+                &collateral_token_ema_price,
+                collateral_custody,
+                curtime,
+                false
+            )?,
+            PerpetualsError::MaxLeverage
+        );
+    }
 
-    // check pool constraints
-    msg!("Check pool constraints");
-    require!(
-        pool.check_available_amount(transfer_amount, collateral_custody)?,
-        PerpetualsError::CustodyAmountLimit
-    );
+    // linked positions cover losses in excess of their collateral from the margin account
+    let mut margin_account = if position.margin_account != Pubkey::default() {
+        let account_map = AccountMap::from_remaining_accounts(ctx.remaining_accounts);
+        Some(Account::<MarginAccount>::try_from(
+            account_map.get_account(&position.margin_account)?,
+        )?)
+    } else {
+        None
+    };
+    if let Some(margin_account) = margin_account.as_mut() {
+        let bad_debt = pool.get_bad_debt_amount(
+            &closed_position,
+            loss_usd,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            collateral_custody,
+        )?;
+        let margin_amount = margin_account.cover_bad_debt(
+            &collateral_custody.key(),
+            collateral_custody,
+            bad_debt,
+        )?;
+        msg!("Bad debt covered by margin account: {}", margin_amount);
+
+        if !partial_close {
+            margin_account.remove_position(&position.key());
+        }
+        margin_account.exit(&crate::ID)?;
+    }
+
+    // update custody stats
+    msg!("Update custody stats");
+    let insurance_fee = pool.settle_close_position(
+        position,
+        &closed_position,
+        &remaining_position,
+        transfer_amount,
+        fee_amount,
+        fee_amount_usd,
+        profit_usd,
+        loss_usd,
+        &token_ema_price,
+        custody,
+        collateral_custody,
+        curtime,
+        false,
+    )?;
 
     // transfer tokens
     msg!("Transfer tokens");
@@ -213,92 +332,44 @@ pub fn close_position(ctx: Context<ClosePosition>, params: &ClosePositionParams)
         transfer_amount,
     )?;
 
-    // update custody stats
-    msg!("Update custody stats");
-    collateral_custody.collected_fees.close_position_usd = collateral_custody
-        .collected_fees
-        .close_position_usd
-        .wrapping_add(fee_amount_usd);
-
-    if transfer_amount > position.collateral_amount {
-        let amount_lost = transfer_amount.saturating_sub(position.collateral_amount);
-        collateral_custody.assets.owned =
-            math::checked_sub(collateral_custody.assets.owned, amount_lost)?;
-    } else {
-        let amount_gained = position.collateral_amount.saturating_sub(transfer_amount);
-        collateral_custody.assets.owned =
-            math::checked_add(collateral_custody.assets.owned, amount_gained)?;
+    if insurance_fee > 0 {
+        perpetuals.transfer_tokens(
+            ctx.accounts
+                .collateral_custody_token_account
+                .to_account_info(),
+            ctx.accounts.insurance_fund_token_account.to_account_info(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            insurance_fee,
+        )?;
     }
-    collateral_custody.assets.collateral = math::checked_sub(
-        collateral_custody.assets.collateral,
-        position.collateral_amount,
-    )?;
-
-    let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
-
-    // Pay protocol_fee from custody if possible, otherwise no protocol_fee
-    if pool.check_available_amount(protocol_fee, collateral_custody)? {
-        collateral_custody.assets.protocol_fees =
-            math::checked_add(collateral_custody.assets.protocol_fees, protocol_fee)?;
 
-        collateral_custody.assets.owned =
-            math::checked_sub(collateral_custody.assets.owned, protocol_fee)?;
-    }
-
-    // if custody and collateral_custody accounts are the same, ensure that data is in sync
-    if position.side == Side::Long && !custody.is_virtual {
-        collateral_custody.volume_stats.close_position_usd = collateral_custody
-            .volume_stats
-            .close_position_usd
-            .wrapping_add(position.size_usd);
-
-        if position.side == Side::Long {
-            collateral_custody.trade_stats.oi_long_usd = collateral_custody
-                .trade_stats
-                .oi_long_usd
-                .saturating_sub(position.size_usd);
-        } else {
-            collateral_custody.trade_stats.oi_short_usd = collateral_custody
-                .trade_stats
-                .oi_short_usd
-                .saturating_sub(position.size_usd);
-        }
+    emit!(ClosePositionEvent {
+        owner: position.owner,
+        pool: pool.key(),
+        custody: custody.key(),
+        collateral_custody: collateral_custody.key(),
+        position: position.key(),
+        side: position.side,
+        price: exit_price,
+        size_usd: closed_position.size_usd,
+        collateral_amount: closed_position.collateral_amount,
+        profit_usd,
+        loss_usd,
+        fee_amount,
+        fee_amount_usd,
+        transfer_amount,
+        remaining_size_usd: remaining_position.size_usd,
+        update_time: curtime,
+    });
 
-        collateral_custody.trade_stats.profit_usd = collateral_custody
-            .trade_stats
-            .profit_usd
-            .wrapping_add(profit_usd);
-        collateral_custody.trade_stats.loss_usd = collateral_custody
-            .trade_stats
-            .loss_usd
-            .wrapping_add(loss_usd);
-
-        collateral_custody.remove_position(position, curtime, None)?;
-        collateral_custody.update_borrow_rate(curtime)?;
-        *custody = collateral_custody.clone();
+    // keep the remaining part open or close the position account
+    if partial_close {
+        position.set_inner(remaining_position);
     } else {
-        custody.volume_stats.close_position_usd = custody
-            .volume_stats
-            .close_position_usd
-            .wrapping_add(position.size_usd);
-
-        if position.side == Side::Long {
-            custody.trade_stats.oi_long_usd = custody
-                .trade_stats
-                .oi_long_usd
-                .saturating_sub(position.size_usd);
-        } else {
-            custody.trade_stats.oi_short_usd = custody
-                .trade_stats
-                .oi_short_usd
-                .saturating_sub(position.size_usd);
-        }
-
-        custody.trade_stats.profit_usd = custody.trade_stats.profit_usd.wrapping_add(profit_usd);
-        custody.trade_stats.loss_usd = custody.trade_stats.loss_usd.wrapping_add(loss_usd);
-
-        custody.remove_position(position, curtime, Some(collateral_custody))?;
-        collateral_custody.update_borrow_rate(curtime)?;
+        ctx.accounts
+            .position
+            .close(ctx.accounts.owner.to_account_info())?;
     }
 
     Ok(())
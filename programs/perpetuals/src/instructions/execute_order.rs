@@ -0,0 +1,695 @@
+//! ExecuteOrder instruction handler
+
+use {
+    crate::{
+        constants::{
+            CUSTODY_TOKEN_ACCOUNT_SEED, INSURANCE_FUND_TOKEN_ACCOUNT_SEED, ORDER_SEED,
+            ORDER_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED, POSITION_SEED,
+        },
+        error::PerpetualsError,
+        events::ExecuteOrderEvent,
+        helpers::AccountMap,
+        math,
+        oracle::OraclePrice,
+        state::{
+            custody::Custody,
+            margin_account::MarginAccount,
+            order::{Order, OrderType},
+            perpetuals::Perpetuals,
+            pool::Pool,
+            position::{Position, Side},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+pub struct ExecuteOrder<'info> {
+    // keeper, pays for the position account if a limit order opens a new one
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    /// CHECK: owner of the order, receives rent of the closed accounts
+    #[account(
+        mut,
+        address = order.owner
+    )]
+    pub owner: AccountInfo<'info>,
+
+    // owner's account for stop-loss and take-profit proceeds, not used by limit orders
+    #[account(
+        mut,
+        constraint = receiving_account.mint == collateral_custody.mint,
+        constraint = receiving_account.owner == order.owner
+    )]
+    pub receiving_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        constraint = rewards_receiving_account.mint == collateral_custody.mint,
+        constraint = rewards_receiving_account.owner == signer.key()
+    )]
+    pub rewards_receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [PERPETUALS_SEED.as_bytes()],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [POOL_SEED.as_bytes(),
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        seeds = [ORDER_SEED.as_bytes(),
+                 order.owner.as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &order.order_id.to_le_bytes()],
+        bump = order.bump,
+        close = owner
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        mut,
+        seeds = [ORDER_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 order.key().as_ref()],
+        bump = order.token_account_bump
+    )]
+    pub order_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = Position::LEN,
+        seeds = [POSITION_SEED.as_bytes(),
+                 order.owner.as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[order.side as u8],
+                 &order.position_id.to_le_bytes()],
+        bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(
+        mut,
+        constraint = order.custody == custody.key()
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the position token
+    #[account(
+        address = custody.oracle.key()
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = custody.ema_oracle.is_none() || Some(custody_ema_oracle_account.key()) == custody.ema_oracle.map(|o| o.key()) @ PerpetualsError::InvalidEmaOracle
+    )]
+    pub custody_ema_oracle_account: Option<AccountInfo<'info>>,
+
+    #[account(
+        mut,
+        constraint = order.collateral_custody == collateral_custody.key()
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the collateral token
+    #[account(
+        address = collateral_custody.oracle.key()
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = collateral_custody.ema_oracle.is_none() || Some(collateral_custody_ema_oracle_account.key()) == collateral_custody.ema_oracle.map(|o| o.key()) @ PerpetualsError::InvalidEmaOracle
+    )]
+    pub collateral_custody_ema_oracle_account: Option<AccountInfo<'info>>,
+
+    #[account(
+        mut,
+        seeds = [CUSTODY_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.token_account_bump
+    )]
+    pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [INSURANCE_FUND_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.insurance_fund_token_account_bump
+    )]
+    pub insurance_fund_token_account: Box<Account<'info, TokenAccount>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+    // remaining accounts:
+    //   for trigger orders on positions linked to a margin account: the margin account (writable)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ExecuteOrderParams {}
+
+pub fn execute_order(mut ctx: Context<ExecuteOrder>, _params: &ExecuteOrderParams) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    let perpetuals = &ctx.accounts.perpetuals;
+    let custody = &ctx.accounts.custody;
+    let order_type = ctx.accounts.order.order_type;
+    if order_type == OrderType::Limit {
+        require!(
+            perpetuals.permissions.allow_open_position
+                && ctx.accounts.pool.permissions.allow_open_position
+                && custody.permissions.allow_open_position,
+            PerpetualsError::InstructionNotAllowed
+        );
+        require!(
+            custody.market_status.allow_open_position(),
+            PerpetualsError::MarketStatusRestricted
+        );
+    } else {
+        require!(
+            perpetuals.permissions.allow_close_position
+                && ctx.accounts.pool.permissions.allow_close_position
+                && custody.permissions.allow_close_position,
+            PerpetualsError::InstructionNotAllowed
+        );
+    }
+
+    // Validate ema oracle
+    if custody.needs_ema_oracle() {
+        require!(
+            ctx.accounts.custody_ema_oracle_account.is_some(),
+            PerpetualsError::EmaOracleRequired
+        );
+    }
+
+    // check trigger
+    msg!("Check trigger");
+    let curtime = perpetuals.get_time()?;
+    let clock = Clock::get()?;
+
+    // limit orders open positions and require fresher prices
+    let (oracle_params, collateral_oracle_params) = if order_type == OrderType::Limit {
+        (
+            custody.oracle_params.get_open_params(),
+            ctx.accounts
+                .collateral_custody
+                .oracle_params
+                .get_open_params(),
+        )
+    } else {
+        (
+            custody.oracle_params,
+            ctx.accounts.collateral_custody.oracle_params,
+        )
+    };
+
+    let (token_price, token_ema_price) = custody.oracle.extract_prices(
+        &ctx.accounts.custody_oracle_account,
+        &ctx.accounts.custody_ema_oracle_account,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
+        &clock,
+        &oracle_params,
+    )?;
+
+    let (collateral_price, collateral_ema_price) =
+        ctx.accounts.collateral_custody.oracle.extract_prices(
+            &ctx.accounts.collateral_custody_oracle_account,
+            &ctx.accounts.collateral_custody_ema_oracle_account,
+            ctx.accounts
+                .collateral_custody
+                .get_secondary_oracle(ctx.remaining_accounts),
+            &clock,
+            &collateral_oracle_params,
+        )?;
+
+    let price = token_price
+        .scale_to_exponent(-(Perpetuals::PRICE_DECIMALS as i32))?
+        .price;
+    msg!("Oracle price: {}", price);
+    require!(
+        ctx.accounts.order.is_triggered(price),
+        PerpetualsError::OrderNotTriggered
+    );
+
+    let prices = [
+        token_price,
+        token_ema_price,
+        collateral_price,
+        collateral_ema_price,
+    ];
+    let position_closed = if order_type == OrderType::Limit {
+        execute_limit_order(&mut ctx, &prices, curtime)?;
+        false
+    } else {
+        execute_trigger_order(&mut ctx, &prices, curtime)?
+    };
+
+    // the order account itself is closed by the `close` constraint
+    Perpetuals::close_token_account(
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.order_token_account.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        &[&[
+            b"transfer_authority",
+            &[ctx.accounts.perpetuals.transfer_authority_bump],
+        ]],
+    )?;
+
+    // lamports are moved out of the position account last, after all token CPIs
+    if position_closed {
+        ctx.accounts
+            .position
+            .close(ctx.accounts.owner.to_account_info())?;
+    }
+
+    Ok(())
+}
+
+// Opens a position from the escrowed collateral, same as open_position
+fn execute_limit_order(
+    ctx: &mut Context<ExecuteOrder>,
+    prices: &[OraclePrice; 4],
+    curtime: i64,
+) -> Result<()> {
+    let [token_price, token_ema_price, collateral_price, collateral_ema_price] = prices;
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    let order = &ctx.accounts.order;
+    let position = ctx.accounts.position.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
+
+    // positions can't be opened twice, only a fresh account is accepted here
+    require!(
+        position.size_usd == 0,
+        PerpetualsError::InvalidPositionState
+    );
+
+    // compute position price
+    let min_collateral_price =
+        collateral_price.get_min_price(collateral_ema_price, collateral_custody.is_stable)?;
+
+    let position_price = pool.get_entry_price(
+        token_price,
+        token_ema_price,
+        order.side,
+        order.size,
+        custody,
+    )?;
+    msg!("Entry price: {}", position_price);
+
+    // trigger price is the limit price
+    if order.side == Side::Long {
+        require_gte!(
+            order.trigger_price,
+            position_price,
+            PerpetualsError::MaxPriceSlippage
+        );
+    } else {
+        require_gte!(
+            position_price,
+            order.trigger_price,
+            PerpetualsError::MaxPriceSlippage
+        );
+    }
+
+    // compute position parameters, fees are paid from the escrowed amount
+    let (size_usd, locked_amount, borrow_size_usd, fee_amount, fee_amount_usd) = pool
+        .get_open_position_amounts(
+            order.side,
+            order.size,
+            position_price,
+            token_ema_price,
+            custody,
+            collateral_price,
+            collateral_ema_price,
+            collateral_custody,
+        )?;
+    let reward = Pool::get_fee_amount(custody.fees.execute_order, order.collateral_amount)?;
+    msg!("Collected fee: {}", fee_amount);
+    msg!("Reward: {}", reward);
+
+    let collateral = math::checked_sub(
+        order.collateral_amount,
+        math::checked_add(fee_amount, reward)?,
+    )?;
+    let collateral_usd =
+        min_collateral_price.get_asset_amount_usd(collateral, collateral_custody.decimals)?;
+
+    // init new position
+    msg!("Initialize new position");
+    position.owner = order.owner;
+    position.pool = pool.key();
+    position.custody = custody.key();
+    position.collateral_custody = collateral_custody.key();
+    position.position_id = order.position_id;
+    position.open_time = curtime;
+    position.update_time = 0;
+    position.side = order.side;
+    position.price = position_price;
+    position.size_usd = size_usd;
+    position.borrow_size_usd = borrow_size_usd;
+    position.collateral_usd = collateral_usd;
+    position.unrealized_profit_usd = 0;
+    position.unrealized_loss_usd = 0;
+    position.cumulative_interest_snapshot = collateral_custody.get_cumulative_interest(curtime)?;
+    position.cumulative_funding_snapshot = custody.get_cumulative_funding(order.side, curtime)?;
+    position.locked_amount = locked_amount;
+    position.collateral_amount = collateral;
+    position.bump = *ctx
+        .bumps
+        .get("position")
+        .ok_or(ProgramError::InvalidSeeds)?;
+
+    // check position risk
+    msg!("Check position risks");
+    require!(
+        position.locked_amount > 0,
+        PerpetualsError::InsufficientAmountReturned
+    );
+    require!(
+        pool.check_leverage(
+            position,
+            token_price,
+            token_ema_price,
+            custody,
+            collateral_price,
+            collateral_ema_price,
+            collateral_custody,
+            curtime,
+            true
+        )?,
+        PerpetualsError::MaxLeverage
+    );
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts.order_token_account.to_account_info(),
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        math::checked_add(collateral, fee_amount)?,
+    )?;
+
+    perpetuals.transfer_tokens(
+        ctx.accounts.order_token_account.to_account_info(),
+        ctx.accounts.rewards_receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        reward,
+    )?;
+
+    // update custody stats
+    msg!("Update custody stats");
+    let insurance_fee = pool.settle_open_position(
+        position,
+        fee_amount,
+        fee_amount_usd,
+        token_ema_price,
+        custody,
+        collateral_custody,
+        curtime,
+    )?;
+    if insurance_fee > 0 {
+        perpetuals.transfer_tokens(
+            ctx.accounts
+                .collateral_custody_token_account
+                .to_account_info(),
+            ctx.accounts.insurance_fund_token_account.to_account_info(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            insurance_fee,
+        )?;
+    }
+
+    emit!(ExecuteOrderEvent {
+        keeper: ctx.accounts.signer.key(),
+        owner: order.owner,
+        pool: pool.key(),
+        custody: custody.key(),
+        collateral_custody: collateral_custody.key(),
+        order: order.key(),
+        position: position.key(),
+        order_id: order.order_id,
+        order_type: order.order_type,
+        side: order.side,
+        price: position_price,
+        size_usd,
+        collateral_amount: collateral,
+        profit_usd: 0,
+        loss_usd: 0,
+        fee_amount,
+        fee_amount_usd,
+        reward_amount: reward,
+        update_time: curtime,
+    });
+
+    Ok(())
+}
+
+// Closes the position entirely or partially, same as close_position.
+// Returns true if the position account has to be closed.
+fn execute_trigger_order(
+    ctx: &mut Context<ExecuteOrder>,
+    prices: &[OraclePrice; 4],
+    curtime: i64,
+) -> Result<bool> {
+    let [token_price, token_ema_price, collateral_price, collateral_ema_price] = prices;
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    let order = &ctx.accounts.order;
+    let position = ctx.accounts.position.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
+
+    require!(
+        position.size_usd > 0 && position.collateral_custody == collateral_custody.key(),
+        PerpetualsError::InvalidPositionState
+    );
+    require!(
+        order.size >= position.size_usd || custody.market_status.allow_partial_close(),
+        PerpetualsError::MarketStatusRestricted
+    );
+    let receiving_account = ctx
+        .accounts
+        .receiving_account
+        .as_ref()
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    let exit_price = pool.get_exit_price(
+        token_price,
+        token_ema_price,
+        position.side,
+        std::cmp::min(order.size, position.size_usd),
+        custody,
+    )?;
+    msg!("Exit price: {}", exit_price);
+
+    msg!("Settle position");
+    let (closed_position, mut remaining_position) = position.split(order.size)?;
+    let partial_close = remaining_position.size_usd > 0;
+    if partial_close {
+        msg!(
+            "Partial close: {} / {}",
+            closed_position.size_usd,
+            position.size_usd
+        );
+    }
+
+    let (total_amount_out, mut fee_amount, profit_usd, loss_usd) = pool.get_close_amount(
+        &closed_position,
+        token_price,
+        token_ema_price,
+        custody,
+        collateral_price,
+        collateral_ema_price,
+        collateral_custody,
+        curtime,
+        false,
+    )?;
+
+    let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
+    if position.side == Side::Short || custody.is_virtual {
+        fee_amount =
+            collateral_ema_price.get_token_amount(fee_amount_usd, collateral_custody.decimals)?;
+    }
+
+    msg!("Net profit: {}, loss: {}", profit_usd, loss_usd);
+    msg!("Collected fee: {}", fee_amount);
+
+    let reward = Pool::get_fee_amount(custody.fees.execute_order, total_amount_out)?;
+    let user_amount = math::checked_sub(total_amount_out, reward)?;
+
+    msg!("Amount out: {}", user_amount);
+    msg!("Reward: {}", reward);
+
+    // settle interest accrued by the remaining part, it keeps running from the current snapshot
+    if partial_close {
+        let interest_usd =
+            collateral_custody.get_interest_amount_usd(&remaining_position, curtime)?;
+        remaining_position.unrealized_loss_usd =
+            math::checked_add(remaining_position.unrealized_loss_usd, interest_usd)?;
+        remaining_position.cumulative_interest_snapshot =
+            collateral_custody.get_cumulative_interest(curtime)?;
+        remaining_position.update_time = curtime;
+
+        // check position risk
+        msg!("Check position risks");
+        require!(
+            remaining_position.locked_amount > 0,
+            PerpetualsError::InsufficientAmountReturned
+        );
+        require!(
+            pool.check_leverage(
+                &remaining_position,
+                token_price,
+                token_ema_price,
+                custody,
+                collateral_price,
+                collateral_ema_price,
+                collateral_custody,
+                curtime,
+                false
+            )?,
+            PerpetualsError::MaxLeverage
+        );
+    }
+
+    // linked positions cover losses in excess of their collateral from the margin account
+    let mut margin_account = if position.margin_account != Pubkey::default() {
+        let account_map = AccountMap::from_remaining_accounts(ctx.remaining_accounts);
+        Some(Account::<MarginAccount>::try_from(
+            account_map.get_account(&position.margin_account)?,
+        )?)
+    } else {
+        None
+    };
+    if let Some(margin_account) = margin_account.as_mut() {
+        let bad_debt = pool.get_bad_debt_amount(
+            &closed_position,
+            loss_usd,
+            collateral_price,
+            collateral_ema_price,
+            collateral_custody,
+        )?;
+        let margin_amount = margin_account.cover_bad_debt(
+            &collateral_custody.key(),
+            collateral_custody,
+            bad_debt,
+        )?;
+        msg!("Bad debt covered by margin account: {}", margin_amount);
+
+        if !partial_close {
+            margin_account.remove_position(&position.key());
+        }
+        margin_account.exit(&crate::ID)?;
+    }
+
+    // update custody stats
+    msg!("Update custody stats");
+    let insurance_fee = pool.settle_close_position(
+        position,
+        &closed_position,
+        &remaining_position,
+        total_amount_out,
+        fee_amount,
+        fee_amount_usd,
+        profit_usd,
+        loss_usd,
+        token_ema_price,
+        custody,
+        collateral_custody,
+        curtime,
+        false,
+    )?;
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        user_amount,
+    )?;
+
+    perpetuals.transfer_tokens(
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.rewards_receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        reward,
+    )?;
+
+    if insurance_fee > 0 {
+        perpetuals.transfer_tokens(
+            ctx.accounts
+                .collateral_custody_token_account
+                .to_account_info(),
+            ctx.accounts.insurance_fund_token_account.to_account_info(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            insurance_fee,
+        )?;
+    }
+
+    emit!(ExecuteOrderEvent {
+        keeper: ctx.accounts.signer.key(),
+        owner: order.owner,
+        pool: pool.key(),
+        custody: custody.key(),
+        collateral_custody: collateral_custody.key(),
+        order: order.key(),
+        position: position.key(),
+        order_id: order.order_id,
+        order_type: order.order_type,
+        side: order.side,
+        price: exit_price,
+        size_usd: closed_position.size_usd,
+        collateral_amount: closed_position.collateral_amount,
+        profit_usd,
+        loss_usd,
+        fee_amount,
+        fee_amount_usd,
+        reward_amount: reward,
+        update_time: curtime,
+    });
+
+    // keep the remaining part open
+    if partial_close {
+        position.set_inner(remaining_position);
+    }
+
+    Ok(!partial_close)
+}
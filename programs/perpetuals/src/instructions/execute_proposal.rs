@@ -0,0 +1,154 @@
+//! ExecuteProposal instruction handler
+
+use {
+    crate::{
+        constants::{PERPETUALS_SEED, PROPOSAL_SEED},
+        error::PerpetualsError,
+        events::ExecuteProposalEvent,
+        instructions::{
+            apply_market_status, apply_permissions, apply_pool_permissions,
+            is_risk_reducing_proposal, set_custody_config, set_pool_config, SetCustodyConfigParams,
+            SetMarketStatusParams, SetPermissionsParams, SetPoolConfigParams,
+            SetPoolPermissionsParams,
+        },
+        math,
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, Multisig},
+            perpetuals::Perpetuals,
+            pool::Pool,
+            proposal::{Proposal, ProposalStatus},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct ExecuteProposal<'info> {
+    #[account()]
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PERPETUALS_SEED.as_bytes()],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [PROPOSAL_SEED.as_bytes(),
+                 &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    // remaining accounts:
+    //   accounts of the proposed instruction, same as passed to propose (writable, unsigned)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ExecuteProposalParams {}
+
+pub fn execute_proposal<'info>(
+    ctx: Context<'_, '_, '_, 'info, ExecuteProposal<'info>>,
+    _params: &ExecuteProposalParams,
+) -> Result<()> {
+    // validate proposal
+    msg!("Validate proposal");
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let proposal = ctx.accounts.proposal.as_mut();
+    let curtime = perpetuals.get_time()?;
+    require!(
+        proposal.is_executable(curtime),
+        PerpetualsError::ProposalNotExecutable
+    );
+    require_eq!(
+        Multisig::get_instruction_hash(ctx.remaining_accounts, &proposal.instruction_data),
+        proposal.instruction_hash,
+        PerpetualsError::InvalidProposalState
+    );
+
+    // the state could have changed since the proposal was exempt from the delay
+    let instruction_type = Proposal::get_instruction_type(&proposal.instruction_data)?;
+    if proposal.risk_reducing
+        && !is_risk_reducing_proposal(
+            perpetuals,
+            instruction_type,
+            proposal.get_params_data(),
+            ctx.remaining_accounts,
+        )?
+    {
+        require_gte!(
+            curtime,
+            math::checked_add(proposal.eta, perpetuals.proposal_delay)?,
+            PerpetualsError::ProposalNotExecutable
+        );
+    }
+
+    // execute proposed instruction
+    msg!("Execute proposed instruction");
+    let params_data = proposal.get_params_data();
+    let accounts = ctx.remaining_accounts;
+
+    if instruction_type == AdminInstruction::SetCustodyConfig as u8 {
+        if accounts.len() != 2 {
+            return Err(ProgramError::NotEnoughAccountKeys.into());
+        }
+        let mut pool = Account::<Pool>::try_from(&accounts[0])?;
+        let mut custody = Account::<Custody>::try_from(&accounts[1])?;
+        set_custody_config(
+            &mut pool,
+            &mut custody,
+            &SetCustodyConfigParams::try_from_slice(params_data)?,
+        )?;
+        pool.exit(&crate::ID)?;
+        custody.exit(&crate::ID)?;
+    } else if instruction_type == AdminInstruction::SetPoolConfig as u8 {
+        if accounts.len() != 1 {
+            return Err(ProgramError::NotEnoughAccountKeys.into());
+        }
+        let mut pool = Account::<Pool>::try_from(&accounts[0])?;
+        set_pool_config(
+            &mut pool,
+            &SetPoolConfigParams::try_from_slice(params_data)?,
+        )?;
+        pool.exit(&crate::ID)?;
+    } else if instruction_type == AdminInstruction::SetPermissions as u8 {
+        apply_permissions(
+            perpetuals,
+            &SetPermissionsParams::try_from_slice(params_data)?,
+        )?;
+    } else if instruction_type == AdminInstruction::SetPoolPermissions as u8 {
+        if accounts.len() != 1 {
+            return Err(ProgramError::NotEnoughAccountKeys.into());
+        }
+        let mut pool = Account::<Pool>::try_from(&accounts[0])?;
+        apply_pool_permissions(
+            &mut pool,
+            &SetPoolPermissionsParams::try_from_slice(params_data)?,
+        )?;
+        pool.exit(&crate::ID)?;
+    } else if instruction_type == AdminInstruction::SetMarketStatus as u8 {
+        if accounts.len() != 1 {
+            return Err(ProgramError::NotEnoughAccountKeys.into());
+        }
+        let mut custody = Account::<Custody>::try_from(&accounts[0])?;
+        apply_market_status(
+            &mut custody,
+            &SetMarketStatusParams::try_from_slice(params_data)?,
+        )?;
+        custody.exit(&crate::ID)?;
+    } else {
+        return Err(ProgramError::InvalidInstructionData.into());
+    }
+
+    proposal.status = ProposalStatus::Executed;
+
+    emit!(ExecuteProposalEvent {
+        proposal: ctx.accounts.proposal.key(),
+        proposal_id: ctx.accounts.proposal.proposal_id,
+        instruction_type,
+    });
+
+    Ok(())
+}
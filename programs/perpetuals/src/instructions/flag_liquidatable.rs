@@ -0,0 +1,193 @@
+//! FlagLiquidatable instruction handler
+
+use {
+    crate::{
+        constants::{CUSTODY_SEED, PERPETUALS_SEED, POOL_SEED, POSITION_SEED},
+        error::PerpetualsError,
+        events::FlagLiquidatableEvent,
+        helpers::AccountMap,
+        oracle::OraclePrice,
+        state::{
+            custody::Custody, margin_account::MarginAccount, perpetuals::Perpetuals, pool::Pool,
+            position::Position,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct FlagLiquidatable<'info> {
+    #[account()]
+    pub signer: Signer<'info>,
+
+    #[account(
+        seeds = [PERPETUALS_SEED.as_bytes()],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [POOL_SEED.as_bytes(),
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        seeds = [POSITION_SEED.as_bytes(),
+                 position.owner.as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8],
+                 &position.position_id.to_le_bytes()],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(
+        seeds = [CUSTODY_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the position token
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.key()
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        constraint = position.collateral_custody == collateral_custody.key()
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the collateral token
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.key()
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+    // remaining accounts:
+    //   for positions linked to a margin account: the margin account, its linked positions,
+    //   their custodies and collateral custodies, custodies of the margin collateral
+    //   and oracle accounts of all of these custodies (read-only, unsigned)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct FlagLiquidatableParams {}
+
+pub fn flag_liquidatable(
+    ctx: Context<FlagLiquidatable>,
+    _params: &FlagLiquidatableParams,
+) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    let perpetuals = &ctx.accounts.perpetuals;
+    let custody = &ctx.accounts.custody;
+    let collateral_custody = &ctx.accounts.collateral_custody;
+    require!(
+        perpetuals.permissions.allow_close_position
+            && ctx.accounts.pool.permissions.allow_close_position
+            && custody.permissions.allow_close_position,
+        PerpetualsError::InstructionNotAllowed
+    );
+    require!(
+        custody.market_status.allow_liquidation(),
+        PerpetualsError::MarketStatusRestricted
+    );
+
+    // compute position state
+    let curtime = perpetuals.get_time()?;
+    let clock = Clock::get()?;
+
+    let token_price = OraclePrice::new_from_oracle(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        &clock,
+        custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
+        false,
+    )?;
+
+    let token_ema_price = OraclePrice::new_from_oracle(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        &clock,
+        custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
+        custody.pricing.use_ema,
+    )?;
+
+    let collateral_token_price = OraclePrice::new_from_oracle(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        &clock,
+        collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
+        false,
+    )?;
+
+    let collateral_token_ema_price = OraclePrice::new_from_oracle(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        &clock,
+        collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
+        collateral_custody.pricing.use_ema,
+    )?;
+
+    // linked positions are evaluated on the margin account as a whole
+    let margin_account_key = ctx.accounts.position.margin_account;
+    let healthy = if margin_account_key != Pubkey::default() {
+        let account_map = AccountMap::from_remaining_accounts(ctx.remaining_accounts);
+        let margin_account =
+            Account::<MarginAccount>::try_from(account_map.get_account(&margin_account_key)?)?;
+        ctx.accounts.pool.check_margin_account_health(
+            &margin_account_key,
+            &margin_account,
+            &account_map,
+            &clock,
+            curtime,
+        )?
+    } else {
+        ctx.accounts.pool.check_leverage(
+            &ctx.accounts.position,
+            &token_price,
+            &token_ema_price,
+            custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+            false,
+        )?
+    };
+
+    // update the flag, the auction starts when the position is first seen underwater
+    // and is reset if the position recovers before being liquidated
+    msg!("Update liquidation flag");
+    let position = ctx.accounts.position.as_mut();
+    if !healthy && position.liquidatable_since == 0 {
+        position.liquidatable_since = curtime;
+    } else if healthy && position.liquidatable_since != 0 {
+        position.liquidatable_since = 0;
+    } else {
+        return err!(PerpetualsError::InvalidPositionState);
+    }
+
+    emit!(FlagLiquidatableEvent {
+        signer: ctx.accounts.signer.key(),
+        owner: position.owner,
+        pool: ctx.accounts.pool.key(),
+        position: position.key(),
+        liquidatable_since: position.liquidatable_since,
+    });
+
+    Ok(())
+}
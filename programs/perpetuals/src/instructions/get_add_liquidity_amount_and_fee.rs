@@ -78,6 +78,8 @@ pub fn get_add_liquidity_amount_and_fee(
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -85,6 +87,8 @@ pub fn get_add_liquidity_amount_and_fee(
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         custody.pricing.use_ema,
     )?;
 
@@ -86,6 +86,8 @@ pub fn get_entry_price_and_fee(
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params.get_open_params(),
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -93,6 +95,8 @@ pub fn get_entry_price_and_fee(
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params.get_open_params(),
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         custody.pricing.use_ema,
     )?;
 
@@ -102,6 +106,8 @@ pub fn get_entry_price_and_fee(
             .to_account_info(),
         &clock,
         collateral_custody.oracle,
+        &collateral_custody.oracle_params.get_open_params(),
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -111,18 +117,29 @@ pub fn get_entry_price_and_fee(
             .to_account_info(),
         &clock,
         collateral_custody.oracle,
+        &collateral_custody.oracle_params.get_open_params(),
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
         collateral_custody.pricing.use_ema,
     )?;
 
     let min_collateral_price = collateral_token_price
         .get_min_price(&collateral_token_ema_price, collateral_custody.is_stable)?;
 
-    let entry_price = pool.get_entry_price(&token_price, &token_ema_price, params.side, custody)?;
+    let entry_price = pool.get_entry_price(
+        &token_price,
+        &token_ema_price,
+        params.side,
+        params.size,
+        custody,
+    )?;
 
-    let position_oracle_price = OraclePrice {
-        price: entry_price,
-        exponent: -(Perpetuals::PRICE_DECIMALS as i32),
-    };
+    let price_impact = pool.get_price_impact(
+        &token_ema_price,
+        token_ema_price.get_asset_amount_usd(params.size, custody.decimals)?,
+        custody,
+    )?;
+
+    let position_oracle_price = OraclePrice::new(entry_price, -(Perpetuals::PRICE_DECIMALS as i32));
     let size_usd = position_oracle_price.get_asset_amount_usd(params.size, custody.decimals)?;
     let collateral_usd = min_collateral_price
         .get_asset_amount_usd(params.collateral, collateral_custody.decimals)?;
@@ -142,6 +159,7 @@ pub fn get_entry_price_and_fee(
         size_usd,
         collateral_usd,
         cumulative_interest_snapshot: collateral_custody.get_cumulative_interest(curtime)?,
+        cumulative_funding_snapshot: custody.get_cumulative_funding(params.side, curtime)?,
         ..Position::default()
     };
 
@@ -170,5 +188,6 @@ pub fn get_entry_price_and_fee(
         entry_price,
         liquidation_price,
         fee,
+        price_impact,
     })
 }
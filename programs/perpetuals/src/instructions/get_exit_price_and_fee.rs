@@ -15,9 +15,6 @@ use {
 };
 
 #[derive(Accounts)]
-#[instruction(
-    args: GetExitPriceAndFeeParams
-)]
 pub struct GetExitPriceAndFee<'info> {
     #[account(
         seeds = [
@@ -30,7 +27,7 @@ pub struct GetExitPriceAndFee<'info> {
     #[account(
         seeds = [
             POOL_SEED.as_bytes(),
-            &args.pool_id.to_le_bytes()
+            pool.name.as_bytes()
         ],
         bump = pool.bump
     )]
@@ -42,7 +39,8 @@ pub struct GetExitPriceAndFee<'info> {
             position.owner.as_ref(),
             pool.key().as_ref(),
             custody.key().as_ref(),
-            &[position.side as u8]
+            &[position.side as u8],
+            &position.position_id.to_le_bytes()
         ],
         bump = position.bump
     )]
@@ -90,9 +88,7 @@ pub struct GetExitPriceAndFee<'info> {
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
-pub struct GetExitPriceAndFeeParams {
-    pool_id: u64,
-}
+pub struct GetExitPriceAndFeeParams {}
 
 pub fn get_exit_price_and_fee(
     ctx: Context<GetExitPriceAndFee>,
@@ -109,17 +105,29 @@ pub fn get_exit_price_and_fee(
     let (token_price, token_ema_price) = custody.oracle.extract_prices(
         &ctx.accounts.custody_oracle_account,
         &ctx.accounts.custody_ema_oracle_account,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         clock,
+        &custody.oracle_params,
     )?;
 
     let (_collateral_token_price, collateral_token_ema_price) =
         collateral_custody.oracle.extract_prices(
             &ctx.accounts.collateral_custody_oracle_account,
             &ctx.accounts.collateral_custody_ema_oracle_account,
+            collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
             clock,
+            &collateral_custody.oracle_params,
         )?;
 
-    let price = pool.get_exit_price(&token_price, &token_ema_price, position.side, custody)?;
+    let price = pool.get_exit_price(
+        &token_price,
+        &token_ema_price,
+        position.side,
+        position.size_usd,
+        custody,
+    )?;
+
+    let price_impact = pool.get_price_impact(&token_ema_price, position.size_usd, custody)?;
 
     let size = token_ema_price.get_token_amount(position.size_usd, custody.decimals)?;
 
@@ -131,5 +139,9 @@ pub fn get_exit_price_and_fee(
             .get_token_amount(fee_amount_usd, collateral_custody.decimals)?;
     }
 
-    Ok(PriceAndFee { price, fee })
+    Ok(PriceAndFee {
+        price,
+        fee,
+        price_impact,
+    })
 }
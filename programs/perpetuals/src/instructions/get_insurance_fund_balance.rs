@@ -0,0 +1,52 @@
+//! GetInsuranceFundBalance instruction handler
+
+use {
+    crate::{
+        constants::{CUSTODY_SEED, INSURANCE_FUND_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED},
+        state::{custody::Custody, perpetuals::Perpetuals, pool::Pool},
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::TokenAccount,
+};
+
+#[derive(Accounts)]
+pub struct GetInsuranceFundBalance<'info> {
+    #[account(
+        seeds = [PERPETUALS_SEED.as_bytes()],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [POOL_SEED.as_bytes(),
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        seeds = [CUSTODY_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    #[account(
+        seeds = [INSURANCE_FUND_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.insurance_fund_token_account_bump
+    )]
+    pub insurance_fund_token_account: Box<Account<'info, TokenAccount>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetInsuranceFundBalanceParams {}
+
+pub fn get_insurance_fund_balance(
+    ctx: Context<GetInsuranceFundBalance>,
+    _params: &GetInsuranceFundBalanceParams,
+) -> Result<u64> {
+    Ok(ctx.accounts.insurance_fund_token_account.amount)
+}
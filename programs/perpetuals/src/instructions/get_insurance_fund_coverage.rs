@@ -0,0 +1,68 @@
+//! GetInsuranceFundCoverage instruction handler
+
+use {
+    crate::{
+        constants::{CUSTODY_SEED, INSURANCE_FUND_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED},
+        math,
+        state::{custody::Custody, perpetuals::Perpetuals, pool::Pool},
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::TokenAccount,
+};
+
+#[derive(Accounts)]
+pub struct GetInsuranceFundCoverage<'info> {
+    #[account(
+        seeds = [PERPETUALS_SEED.as_bytes()],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [POOL_SEED.as_bytes(),
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        seeds = [CUSTODY_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    #[account(
+        seeds = [INSURANCE_FUND_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.insurance_fund_token_account_bump
+    )]
+    pub insurance_fund_token_account: Box<Account<'info, TokenAccount>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GetInsuranceFundCoverageParams {}
+
+// Returns insurance fund balance relative to the trader collateral held by the custody,
+// with implied BPS_DECIMALS decimals
+pub fn get_insurance_fund_coverage(
+    ctx: Context<GetInsuranceFundCoverage>,
+    _params: &GetInsuranceFundCoverageParams,
+) -> Result<u64> {
+    let balance = ctx.accounts.insurance_fund_token_account.amount;
+    let collateral = ctx.accounts.custody.assets.collateral;
+
+    msg!("Insurance fund balance: {}", balance);
+    msg!("Custody collateral: {}", collateral);
+
+    if collateral == 0 {
+        return Ok(if balance > 0 { u64::MAX } else { 0 });
+    }
+
+    math::checked_as_u64(math::checked_div(
+        math::checked_mul(balance as u128, Perpetuals::BPS_POWER)?,
+        collateral as u128,
+    )?)
+}
@@ -30,7 +30,8 @@ pub struct GetLiquidationPrice<'info> {
                  position.owner.as_ref(),
                  pool.key().as_ref(),
                  custody.key().as_ref(),
-                 &[position.side as u8]],
+                 &[position.side as u8],
+                 &position.position_id.to_le_bytes()],
         bump = position.bump
     )]
     pub position: Box<Account<'info, Position>>,
@@ -81,6 +82,8 @@ pub fn get_liquidation_price(
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         custody.pricing.use_ema,
     )?;
 
@@ -90,6 +93,8 @@ pub fn get_liquidation_price(
             .to_account_info(),
         &clock,
         collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -99,6 +104,8 @@ pub fn get_liquidation_price(
             .to_account_info(),
         &clock,
         collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
         collateral_custody.pricing.use_ema,
     )?;
 
@@ -3,8 +3,12 @@
 use {
     crate::{
         constants::{CUSTODY_SEED, PERPETUALS_SEED, POOL_SEED, POSITION_SEED},
+        helpers::AccountMap,
         oracle::OraclePrice,
-        state::{custody::Custody, perpetuals::Perpetuals, pool::Pool, position::Position},
+        state::{
+            custody::Custody, margin_account::MarginAccount, perpetuals::Perpetuals, pool::Pool,
+            position::Position,
+        },
     },
     anchor_lang::prelude::*,
 };
@@ -29,7 +33,8 @@ pub struct GetLiquidationState<'info> {
                  position.owner.as_ref(),
                  pool.key().as_ref(),
                  custody.key().as_ref(),
-                 &[position.side as u8]],
+                 &[position.side as u8],
+                 &position.position_id.to_le_bytes()],
         bump = position.bump
     )]
     pub position: Box<Account<'info, Position>>,
@@ -58,6 +63,10 @@ pub struct GetLiquidationState<'info> {
         constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.key()
     )]
     pub collateral_custody_oracle_account: AccountInfo<'info>,
+    // remaining accounts:
+    //   for positions linked to a margin account: the margin account, its linked positions,
+    //   their custodies and collateral custodies, custodies of the margin collateral
+    //   and oracle accounts of all of these custodies (read-only, unsigned)
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
@@ -76,6 +85,8 @@ pub fn get_liquidation_state(
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -83,6 +94,8 @@ pub fn get_liquidation_state(
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         custody.pricing.use_ema,
     )?;
 
@@ -92,6 +105,8 @@ pub fn get_liquidation_state(
             .to_account_info(),
         &clock,
         collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -101,20 +116,39 @@ pub fn get_liquidation_state(
             .to_account_info(),
         &clock,
         collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
         collateral_custody.pricing.use_ema,
     )?;
 
-    if ctx.accounts.pool.check_leverage(
-        &ctx.accounts.position,
-        &token_price,
-        &token_ema_price,
-        custody,
-        &collateral_token_price,
-        &collateral_token_ema_price,
-        collateral_custody,
-        curtime,
-        false,
-    )? {
+    // linked positions are evaluated on the margin account as a whole
+    let margin_account_key = ctx.accounts.position.margin_account;
+    let healthy = if margin_account_key != Pubkey::default() {
+        let account_map = AccountMap::from_remaining_accounts(ctx.remaining_accounts);
+        let margin_account =
+            Account::<MarginAccount>::try_from(account_map.get_account(&margin_account_key)?)?;
+        ctx.accounts.pool.check_margin_account_health(
+            &margin_account_key,
+            &margin_account,
+            &account_map,
+            &clock,
+            curtime,
+        )?
+    } else {
+        ctx.accounts.pool.check_leverage(
+            &ctx.accounts.position,
+            &token_price,
+            &token_ema_price,
+            custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+            false,
+        )?
+    };
+
+    if healthy {
         Ok(0)
     } else {
         Ok(1)
@@ -91,6 +91,8 @@ pub fn get_swap_amount_and_fees(
             .to_account_info(),
         &clock,
         receiving_custody.oracle,
+        &receiving_custody.oracle_params,
+        receiving_custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -100,6 +102,8 @@ pub fn get_swap_amount_and_fees(
             .to_account_info(),
         &clock,
         receiving_custody.oracle,
+        &receiving_custody.oracle_params,
+        receiving_custody.get_secondary_oracle(ctx.remaining_accounts),
         receiving_custody.pricing.use_ema,
     )?;
 
@@ -109,6 +113,8 @@ pub fn get_swap_amount_and_fees(
             .to_account_info(),
         &clock,
         dispensing_custody.oracle,
+        &dispensing_custody.oracle_params,
+        dispensing_custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -118,6 +124,8 @@ pub fn get_swap_amount_and_fees(
             .to_account_info(),
         &clock,
         dispensing_custody.oracle,
+        &dispensing_custody.oracle_params,
+        dispensing_custody.get_secondary_oracle(ctx.remaining_accounts),
         dispensing_custody.pricing.use_ema,
     )?;
 
@@ -0,0 +1,67 @@
+//! GrantRole instruction handler
+
+use {
+    crate::{
+        events::GrantRoleEvent,
+        state::multisig::{AdminInstruction, Multisig, Role},
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+#[instruction(params: GrantRoleParams)]
+pub struct GrantRole<'info> {
+    #[account()]
+    pub admin: Signer<'info>,
+
+    // roles are managed by their own members
+    #[account(
+        mut,
+        seeds = [b"multisig", &[params.role as u8]],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: new member of the role
+    #[account()]
+    pub member: AccountInfo<'info>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GrantRoleParams {
+    pub role: Role,
+    // number of signatures required by the role afterwards
+    pub min_signatures: u8,
+}
+
+pub fn grant_role<'info>(
+    ctx: Context<'_, '_, '_, 'info, GrantRole<'info>>,
+    params: &GrantRoleParams,
+) -> Result<u8> {
+    // validate signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::GrantRole, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // add role member
+    multisig.add_signer(ctx.accounts.member.key, params.min_signatures)?;
+
+    emit!(GrantRoleEvent {
+        role: params.role,
+        member: ctx.accounts.member.key(),
+        min_signatures: params.min_signatures,
+    });
+
+    Ok(0)
+}
@@ -0,0 +1,400 @@
+//! IncreasePosition instruction handler
+
+use {
+    crate::{
+        constants::{
+            CUSTODY_TOKEN_ACCOUNT_SEED, INSURANCE_FUND_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED,
+            POOL_SEED, POSITION_SEED,
+        },
+        error::PerpetualsError,
+        events::IncreasePositionEvent,
+        math,
+        oracle::OraclePrice,
+        state::{
+            custody::Custody,
+            perpetuals::Perpetuals,
+            pool::Pool,
+            position::{Position, Side},
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+#[instruction(params: IncreasePositionParams)]
+pub struct IncreasePosition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = funding_account.mint == collateral_custody.mint,
+        has_one = owner
+    )]
+    pub funding_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [PERPETUALS_SEED.as_bytes()],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [POOL_SEED.as_bytes(),
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [POSITION_SEED.as_bytes(),
+                 owner.key().as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8],
+                 &position.position_id.to_le_bytes()],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(
+        mut,
+        constraint = position.custody == custody.key()
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the position token
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.key()
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = position.collateral_custody == collateral_custody.key()
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the collateral token
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.key()
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [CUSTODY_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.token_account_bump
+    )]
+    pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [INSURANCE_FUND_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.insurance_fund_token_account_bump
+    )]
+    pub insurance_fund_token_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct IncreasePositionParams {
+    pub price: u64,
+    // additional collateral, can be zero
+    pub collateral: u64,
+    pub size: u64,
+}
+
+pub fn increase_position(
+    ctx: Context<IncreasePosition>,
+    params: &IncreasePositionParams,
+) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    require!(
+        perpetuals.permissions.allow_size_change
+            && ctx.accounts.pool.permissions.allow_size_change
+            && custody.permissions.allow_size_change,
+        PerpetualsError::InstructionNotAllowed
+    );
+    require!(
+        custody.market_status.allow_size_increase(),
+        PerpetualsError::MarketStatusRestricted
+    );
+
+    // validate inputs
+    msg!("Validate inputs");
+    if params.price == 0 || params.size == 0 {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    let position = ctx.accounts.position.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
+
+    // compute position price
+    let curtime = perpetuals.get_time()?;
+    let clock = Clock::get()?;
+
+    let token_price = OraclePrice::new_from_oracle(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        &clock,
+        custody.oracle,
+        &custody.oracle_params.get_open_params(),
+        custody.get_secondary_oracle(ctx.remaining_accounts),
+        false,
+    )?;
+
+    let token_ema_price = OraclePrice::new_from_oracle(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        &clock,
+        custody.oracle,
+        &custody.oracle_params.get_open_params(),
+        custody.get_secondary_oracle(ctx.remaining_accounts),
+        custody.pricing.use_ema,
+    )?;
+
+    let collateral_token_price = OraclePrice::new_from_oracle(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        &clock,
+        collateral_custody.oracle,
+        &collateral_custody.oracle_params.get_open_params(),
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
+        false,
+    )?;
+
+    let collateral_token_ema_price = OraclePrice::new_from_oracle(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        &clock,
+        collateral_custody.oracle,
+        &collateral_custody.oracle_params.get_open_params(),
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
+        collateral_custody.pricing.use_ema,
+    )?;
+
+    let min_collateral_price = collateral_token_price
+        .get_min_price(&collateral_token_ema_price, collateral_custody.is_stable)?;
+
+    let position_price = pool.get_entry_price(
+        &token_price,
+        &token_ema_price,
+        position.side,
+        params.size,
+        custody,
+    )?;
+    msg!("Entry price: {}", position_price);
+
+    if position.side == Side::Long {
+        require_gte!(
+            params.price,
+            position_price,
+            PerpetualsError::MaxPriceSlippage
+        );
+    } else {
+        require_gte!(
+            position_price,
+            params.price,
+            PerpetualsError::MaxPriceSlippage
+        );
+    }
+
+    // compute added size
+    let (size_usd, locked_amount, borrow_size_usd, fee_amount, fee_amount_usd) = pool
+        .get_open_position_amounts(
+            position.side,
+            params.size,
+            position_price,
+            &token_ema_price,
+            custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            collateral_custody,
+        )?;
+    let collateral_usd = min_collateral_price
+        .get_asset_amount_usd(params.collateral, collateral_custody.decimals)?;
+    msg!("Collected fee: {}", fee_amount);
+
+    // compute amount to transfer
+    let transfer_amount = math::checked_add(params.collateral, fee_amount)?;
+    msg!("Amount in: {}", transfer_amount);
+
+    // update existing position, interest and funding accrued so far are settled into
+    // the position so that the new snapshots apply to the increased size only from now on
+    msg!("Update existing position");
+    let interest_usd = collateral_custody.get_interest_amount_usd(position, curtime)?;
+    let (funding_profit_usd, funding_loss_usd) =
+        Pool::get_funding_pnl_usd(position, custody, curtime)?;
+    let updated_position = Position {
+        update_time: curtime,
+        price: position.get_average_price(size_usd, position_price)?,
+        size_usd: math::checked_add(position.size_usd, size_usd)?,
+        borrow_size_usd: math::checked_add(position.borrow_size_usd, borrow_size_usd)?,
+        collateral_usd: math::checked_add(position.collateral_usd, collateral_usd)?,
+        unrealized_profit_usd: math::checked_add(
+            position.unrealized_profit_usd,
+            funding_profit_usd,
+        )?,
+        unrealized_loss_usd: math::checked_add(
+            position.unrealized_loss_usd,
+            math::checked_add(interest_usd, funding_loss_usd)?,
+        )?,
+        cumulative_interest_snapshot: collateral_custody.get_cumulative_interest(curtime)?,
+        cumulative_funding_snapshot: custody.get_cumulative_funding(position.side, curtime)?,
+        locked_amount: math::checked_add(position.locked_amount, locked_amount)?,
+        collateral_amount: math::checked_add(position.collateral_amount, params.collateral)?,
+        liquidatable_since: 0,
+        ..(**position).clone()
+    };
+    msg!("New entry price: {}", updated_position.price);
+
+    // check position risk
+    msg!("Check position risks");
+    require!(
+        locked_amount > 0,
+        PerpetualsError::InsufficientAmountReturned
+    );
+    require!(
+        pool.check_leverage(
+            &updated_position,
+            &token_price,
+            &token_ema_price,
+            custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+            true
+        )?,
+        PerpetualsError::MaxLeverage
+    );
+
+    // lock funds for potential profit payoff
+    collateral_custody.lock_funds(locked_amount)?;
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens_from_user(
+        ctx.accounts.funding_account.to_account_info(),
+        ctx.accounts
+            .collateral_custody_token_account
+            .to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        transfer_amount,
+    )?;
+
+    // update custody stats
+    msg!("Update custody stats");
+    collateral_custody.collected_fees.open_position_usd = collateral_custody
+        .collected_fees
+        .open_position_usd
+        .wrapping_add(fee_amount_usd);
+
+    collateral_custody.assets.collateral =
+        math::checked_add(collateral_custody.assets.collateral, params.collateral)?;
+
+    let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
+    collateral_custody.assets.protocol_fees =
+        math::checked_add(collateral_custody.assets.protocol_fees, protocol_fee)?;
+
+    let insurance_fee = Pool::get_fee_amount(custody.fees.insurance_share, fee_amount)?;
+    if insurance_fee > 0 {
+        perpetuals.transfer_tokens(
+            ctx.accounts
+                .collateral_custody_token_account
+                .to_account_info(),
+            ctx.accounts.insurance_fund_token_account.to_account_info(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            insurance_fee,
+        )?;
+    }
+
+    // if custody and collateral_custody accounts are the same, ensure that data is in sync
+    if position.side == Side::Long && !custody.is_virtual {
+        collateral_custody.volume_stats.open_position_usd = collateral_custody
+            .volume_stats
+            .open_position_usd
+            .wrapping_add(size_usd);
+
+        collateral_custody.trade_stats.oi_long_usd =
+            math::checked_add(collateral_custody.trade_stats.oi_long_usd, size_usd)?;
+
+        collateral_custody.remove_position(position, curtime, None)?;
+        collateral_custody.add_position(&updated_position, &token_ema_price, curtime, None)?;
+        collateral_custody.update_borrow_rate(curtime)?;
+        collateral_custody.update_funding_rate(curtime)?;
+        *custody = collateral_custody.clone();
+    } else {
+        custody.volume_stats.open_position_usd = custody
+            .volume_stats
+            .open_position_usd
+            .wrapping_add(size_usd);
+
+        if position.side == Side::Long {
+            custody.trade_stats.oi_long_usd =
+                math::checked_add(custody.trade_stats.oi_long_usd, size_usd)?;
+        } else {
+            custody.trade_stats.oi_short_usd =
+                math::checked_add(custody.trade_stats.oi_short_usd, size_usd)?;
+        }
+
+        custody.remove_position(position, curtime, Some(collateral_custody))?;
+        custody.add_position(
+            &updated_position,
+            &token_ema_price,
+            curtime,
+            Some(collateral_custody),
+        )?;
+        collateral_custody.update_borrow_rate(curtime)?;
+        custody.update_funding_rate(curtime)?;
+    }
+
+    // check open interest limits
+    custody.check_open_interest(position.side)?;
+    pool.add_open_interest(size_usd)?;
+
+    position.set_inner(updated_position);
+
+    emit!(IncreasePositionEvent {
+        owner: position.owner,
+        pool: pool.key(),
+        custody: custody.key(),
+        collateral_custody: collateral_custody.key(),
+        position: position.key(),
+        side: position.side,
+        price: position_price,
+        size_usd,
+        collateral_amount: params.collateral,
+        fee_amount,
+        fee_amount_usd,
+        position_price: position.price,
+        position_size_usd: position.size_usd,
+        position_collateral_usd: position.collateral_usd,
+        update_time: position.update_time,
+    });
+
+    Ok(())
+}
@@ -1,10 +1,10 @@
 //! Init instruction handler
 use {
     crate::{
-        constants::{ADMIN_SEED, PERPETUALS_SEED},
+        constants::PERPETUALS_SEED,
         error::PerpetualsError,
         state::{
-            admin::{Admin, Permissions},
+            multisig::{Multisig, Role},
             perpetuals::Perpetuals,
         },
     },
@@ -21,14 +21,54 @@ pub struct Init<'info> {
     #[account(
         init,
         payer = signer,
-        space = Admin::SIZE,
-        seeds = [
-            ADMIN_SEED.as_bytes(),
-            signer.key().as_ref(),
-        ],
+        space = Multisig::LEN,
+        seeds = [b"multisig".as_ref(), &[Role::PoolAdmin as u8]],
+        bump
+    )]
+    pub pool_admin: AccountLoader<'info, Multisig>,
+
+    #[account(
+        init,
+        payer = signer,
+        space = Multisig::LEN,
+        seeds = [b"multisig".as_ref(), &[Role::RiskAdmin as u8]],
+        bump
+    )]
+    pub risk_admin: AccountLoader<'info, Multisig>,
+
+    #[account(
+        init,
+        payer = signer,
+        space = Multisig::LEN,
+        seeds = [b"multisig".as_ref(), &[Role::FeeWithdrawer as u8]],
+        bump
+    )]
+    pub fee_withdrawer: AccountLoader<'info, Multisig>,
+
+    #[account(
+        init,
+        payer = signer,
+        space = Multisig::LEN,
+        seeds = [b"multisig".as_ref(), &[Role::Pauser as u8]],
+        bump
+    )]
+    pub pauser: AccountLoader<'info, Multisig>,
+
+    #[account(
+        init,
+        payer = signer,
+        space = Multisig::LEN,
+        seeds = [b"multisig".as_ref(), &[Role::OracleAuthority as u8]],
+        bump
+    )]
+    pub oracle_authority: AccountLoader<'info, Multisig>,
+
+    /// CHECK: empty PDA, will be set as authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
         bump
     )]
-    pub superadmin: Account<'info, Admin>,
+    pub transfer_authority: AccountInfo<'info>,
 
     #[account(
         init,
@@ -42,10 +82,12 @@ pub struct Init<'info> {
     pub perpetuals: Box<Account<'info, Perpetuals>>,
     system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
+    // remaining accounts: 1 to Multisig::MAX_SIGNERS admin signers (read-only, unsigned)
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone)]
 pub struct InitParams {
+    pub min_signatures: u8,
     pub allow_swap: bool,
     pub allow_add_liquidity: bool,
     pub allow_remove_liquidity: bool,
@@ -54,16 +96,26 @@ pub struct InitParams {
     pub allow_pnl_withdrawal: bool,
     pub allow_collateral_withdrawal: bool,
     pub allow_size_change: bool,
+    pub proposal_delay: i64,
 }
 
 pub fn init(ctx: Context<Init>, params: &InitParams) -> Result<()> {
-    let signer = &ctx.accounts.signer;
-    let perpetuals = &mut ctx.accounts.perpetuals;
-    let superadmin = &mut ctx.accounts.superadmin;
-
-    superadmin.permissions = Permissions::Superadmin;
-    superadmin.address = signer.key();
+    // initial admin signers get every governance role, roles are reassigned with grant_role
+    // and revoke_role afterwards
+    let role_multisigs = [
+        (&ctx.accounts.pool_admin, "pool_admin"),
+        (&ctx.accounts.risk_admin, "risk_admin"),
+        (&ctx.accounts.fee_withdrawer, "fee_withdrawer"),
+        (&ctx.accounts.pauser, "pauser"),
+        (&ctx.accounts.oracle_authority, "oracle_authority"),
+    ];
+    for (role_multisig, name) in role_multisigs {
+        let mut multisig = role_multisig.load_init()?;
+        multisig.bump = *ctx.bumps.get(name).ok_or(ProgramError::InvalidSeeds)?;
+        multisig.set_signers(ctx.remaining_accounts, params.min_signatures)?;
+    }
 
+    let perpetuals = &mut ctx.accounts.perpetuals;
     perpetuals.permissions.allow_swap = params.allow_swap;
     perpetuals.permissions.allow_add_liquidity = params.allow_add_liquidity;
     perpetuals.permissions.allow_remove_liquidity = params.allow_remove_liquidity;
@@ -72,6 +124,11 @@ pub fn init(ctx: Context<Init>, params: &InitParams) -> Result<()> {
     perpetuals.permissions.allow_pnl_withdrawal = params.allow_pnl_withdrawal;
     perpetuals.permissions.allow_collateral_withdrawal = params.allow_collateral_withdrawal;
     perpetuals.permissions.allow_size_change = params.allow_size_change;
+    perpetuals.proposal_delay = params.proposal_delay;
+    perpetuals.transfer_authority_bump = *ctx
+        .bumps
+        .get("transfer_authority")
+        .ok_or(ProgramError::InvalidSeeds)?;
     perpetuals.perpetuals_bump = *ctx
         .bumps
         .get("perpetuals")
@@ -0,0 +1,67 @@
+//! InitMarginAccount instruction handler
+
+use {
+    crate::{
+        constants::{MARGIN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED},
+        events::InitMarginAccountEvent,
+        state::{margin_account::MarginAccount, perpetuals::Perpetuals, pool::Pool},
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct InitMarginAccount<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [PERPETUALS_SEED.as_bytes()],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [POOL_SEED.as_bytes(),
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = MarginAccount::LEN,
+        seeds = [MARGIN_ACCOUNT_SEED.as_bytes(),
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump
+    )]
+    pub margin_account: Box<Account<'info, MarginAccount>>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct InitMarginAccountParams {}
+
+pub fn init_margin_account(
+    ctx: Context<InitMarginAccount>,
+    _params: &InitMarginAccountParams,
+) -> Result<()> {
+    // record margin account data
+    let margin_account = ctx.accounts.margin_account.as_mut();
+    margin_account.owner = ctx.accounts.owner.key();
+    margin_account.pool = ctx.accounts.pool.key();
+    margin_account.bump = *ctx
+        .bumps
+        .get("margin_account")
+        .ok_or(ProgramError::InvalidSeeds)?;
+
+    emit!(InitMarginAccountEvent {
+        owner: margin_account.owner,
+        pool: margin_account.pool,
+        margin_account: margin_account.key(),
+    });
+
+    Ok(())
+}
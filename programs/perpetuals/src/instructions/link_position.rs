@@ -0,0 +1,182 @@
+//! LinkPosition instruction handler
+
+use {
+    crate::{
+        constants::{MARGIN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED, POSITION_SEED},
+        error::PerpetualsError,
+        events::LinkPositionEvent,
+        helpers::AccountMap,
+        oracle::OraclePrice,
+        state::{
+            custody::Custody, margin_account::MarginAccount, perpetuals::Perpetuals, pool::Pool,
+            position::Position,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct LinkPosition<'info> {
+    #[account()]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [PERPETUALS_SEED.as_bytes()],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [POOL_SEED.as_bytes(),
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MARGIN_ACCOUNT_SEED.as_bytes(),
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump = margin_account.bump
+    )]
+    pub margin_account: Box<Account<'info, MarginAccount>>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [POSITION_SEED.as_bytes(),
+                 owner.key().as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[position.side as u8],
+                 &position.position_id.to_le_bytes()],
+        bump = position.bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(
+        constraint = position.custody == custody.key()
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the position token
+    #[account(
+        constraint = custody_oracle_account.key() == custody.oracle.key()
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        constraint = position.collateral_custody == collateral_custody.key()
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the collateral token
+    #[account(
+        constraint = collateral_custody_oracle_account.key() == collateral_custody.oracle.key()
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+    // remaining accounts:
+    //   positions already linked to the margin account, closed ones are unlinked (read-only, unsigned)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LinkPositionParams {}
+
+pub fn link_position(ctx: Context<LinkPosition>, _params: &LinkPositionParams) -> Result<()> {
+    // validate inputs
+    msg!("Validate inputs");
+    let custody = &ctx.accounts.custody;
+    let collateral_custody = &ctx.accounts.collateral_custody;
+    require_keys_eq!(
+        ctx.accounts.position.margin_account,
+        Pubkey::default(),
+        PerpetualsError::PositionLinkedToMarginAccount
+    );
+    require!(
+        custody.market_status.allow_collateral_deposit(),
+        PerpetualsError::MarketStatusRestricted
+    );
+
+    // check position risk, linking must not make the margin account unhealthy
+    msg!("Check position risks");
+    let curtime = ctx.accounts.perpetuals.get_time()?;
+    let clock = Clock::get()?;
+
+    let token_price = OraclePrice::new_from_oracle(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        &clock,
+        custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
+        false,
+    )?;
+
+    let token_ema_price = OraclePrice::new_from_oracle(
+        &ctx.accounts.custody_oracle_account.to_account_info(),
+        &clock,
+        custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
+        custody.pricing.use_ema,
+    )?;
+
+    let collateral_token_price = OraclePrice::new_from_oracle(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        &clock,
+        collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
+        false,
+    )?;
+
+    let collateral_token_ema_price = OraclePrice::new_from_oracle(
+        &ctx.accounts
+            .collateral_custody_oracle_account
+            .to_account_info(),
+        &clock,
+        collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
+        collateral_custody.pricing.use_ema,
+    )?;
+
+    require!(
+        ctx.accounts.pool.check_leverage(
+            &ctx.accounts.position,
+            &token_price,
+            &token_ema_price,
+            custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+            false
+        )?,
+        PerpetualsError::MaxLeverage
+    );
+
+    // link position
+    msg!("Link position");
+    let margin_account_key = ctx.accounts.margin_account.key();
+    let margin_account = ctx.accounts.margin_account.as_mut();
+    let account_map = AccountMap::from_remaining_accounts(ctx.remaining_accounts);
+    margin_account.prune_positions(&margin_account_key, &account_map);
+
+    let position = ctx.accounts.position.as_mut();
+    margin_account.add_position(&position.key())?;
+    position.margin_account = margin_account_key;
+    position.liquidatable_since = 0;
+
+    emit!(LinkPositionEvent {
+        owner: position.owner,
+        pool: position.pool,
+        margin_account: margin_account_key,
+        position: position.key(),
+    });
+
+    Ok(())
+}
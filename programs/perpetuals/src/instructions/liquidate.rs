@@ -2,12 +2,18 @@
 
 use {
     crate::{
-        constants::{CUSTODY_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED, POSITION_SEED},
+        constants::{
+            CUSTODY_TOKEN_ACCOUNT_SEED, INSURANCE_FUND_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED,
+            POOL_SEED, POSITION_SEED,
+        },
         error::PerpetualsError,
+        events::LiquidateEvent,
+        helpers::AccountMap,
         math,
         oracle::OraclePrice,
         state::{
             custody::Custody,
+            margin_account::MarginAccount,
             perpetuals::Perpetuals,
             pool::Pool,
             position::{Position, Side},
@@ -63,9 +69,9 @@ pub struct Liquidate<'info> {
                  position.owner.as_ref(),
                  pool.key().as_ref(),
                  custody.key().as_ref(),
-                 &[position.side as u8]],
-        bump = position.bump,
-        close = signer
+                 &[position.side as u8],
+                 &position.position_id.to_le_bytes()],
+        bump = position.bump
     )]
     pub position: Box<Account<'info, Position>>,
 
@@ -102,7 +108,20 @@ pub struct Liquidate<'info> {
     )]
     pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
 
+    #[account(
+        mut,
+        seeds = [INSURANCE_FUND_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.insurance_fund_token_account_bump
+    )]
+    pub insurance_fund_token_account: Box<Account<'info, TokenAccount>>,
+
     token_program: Program<'info, Token>,
+    // remaining accounts:
+    //   for positions linked to a margin account: the margin account (writable), its linked
+    //   positions, their custodies and collateral custodies, custodies of the margin collateral
+    //   and oracle accounts of all of these custodies (read-only, unsigned)
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
@@ -115,9 +134,15 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
     let custody = ctx.accounts.custody.as_mut();
     let collateral_custody = ctx.accounts.collateral_custody.as_mut();
     require!(
-        perpetuals.permissions.allow_close_position && custody.permissions.allow_close_position,
+        perpetuals.permissions.allow_close_position
+            && ctx.accounts.pool.permissions.allow_close_position
+            && custody.permissions.allow_close_position,
         PerpetualsError::InstructionNotAllowed
     );
+    require!(
+        custody.market_status.allow_liquidation(),
+        PerpetualsError::MarketStatusRestricted
+    );
 
     let position = ctx.accounts.position.as_mut();
     let pool = ctx.accounts.pool.as_mut();
@@ -131,6 +156,8 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -138,6 +165,8 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         custody.pricing.use_ema,
     )?;
 
@@ -147,6 +176,8 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
             .to_account_info(),
         &clock,
         collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -156,11 +187,31 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
             .to_account_info(),
         &clock,
         collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
         collateral_custody.pricing.use_ema,
     )?;
 
-    require!(
-        !pool.check_leverage(
+    // linked positions are liquidated when the margin account as a whole is unhealthy
+    let account_map = AccountMap::from_remaining_accounts(ctx.remaining_accounts);
+    let mut margin_account = if position.margin_account != Pubkey::default() {
+        Some(Account::<MarginAccount>::try_from(
+            account_map.get_account(&position.margin_account)?,
+        )?)
+    } else {
+        None
+    };
+
+    let healthy = if let Some(margin_account) = &margin_account {
+        pool.check_margin_account_health(
+            &margin_account.key(),
+            margin_account,
+            &account_map,
+            &clock,
+            curtime,
+        )?
+    } else {
+        pool.check_leverage(
             position,
             &token_price,
             &token_ema_price,
@@ -169,24 +220,110 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
             &collateral_token_ema_price,
             collateral_custody,
             curtime,
-            false
-        )?,
-        PerpetualsError::InvalidPositionState
-    );
+            false,
+        )?
+    };
+    require!(!healthy, PerpetualsError::InvalidPositionState);
 
     msg!("Settle position");
-    let (total_amount_out, mut fee_amount, profit_usd, loss_usd) = pool.get_close_amount(
-        position,
-        &token_price,
-        &token_ema_price,
+    let close_size_usd = if margin_account.is_some() {
+        position.size_usd
+    } else {
+        pool.get_liquidation_size(
+            position,
+            &token_price,
+            &token_ema_price,
+            custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+        )?
+    };
+    let (mut closed_position, mut remaining_position) = position.split(close_size_usd)?;
+    let (mut total_amount_out, mut fee_amount, mut profit_usd, mut loss_usd) = pool
+        .get_close_amount(
+            &closed_position,
+            &token_price,
+            &token_ema_price,
+            custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+            true,
+        )?;
+    let mut reward = pool.get_liquidation_reward(
+        total_amount_out,
         custody,
-        &collateral_token_price,
-        &collateral_token_ema_price,
-        collateral_custody,
+        position.liquidatable_since,
         curtime,
-        true,
     )?;
 
+    // on partial liquidation the closed part is settled into the remaining position's
+    // collateral, fall back to full liquidation if that doesn't restore a safe leverage
+    let mut partial_liquidation = remaining_position.size_usd > 0;
+    if partial_liquidation {
+        let retained_amount = math::checked_sub(total_amount_out, reward)?;
+        let min_collateral_price = collateral_token_price
+            .get_min_price(&collateral_token_ema_price, collateral_custody.is_stable)?;
+        remaining_position.collateral_amount =
+            math::checked_add(remaining_position.collateral_amount, retained_amount)?;
+        remaining_position.collateral_usd = math::checked_add(
+            remaining_position.collateral_usd,
+            min_collateral_price
+                .get_asset_amount_usd(retained_amount, collateral_custody.decimals)?,
+        )?;
+
+        // settle interest accrued by the remaining part, it keeps running from the current snapshot
+        let interest_usd =
+            collateral_custody.get_interest_amount_usd(&remaining_position, curtime)?;
+        remaining_position.unrealized_loss_usd =
+            math::checked_add(remaining_position.unrealized_loss_usd, interest_usd)?;
+        remaining_position.cumulative_interest_snapshot =
+            collateral_custody.get_cumulative_interest(curtime)?;
+        remaining_position.update_time = curtime;
+
+        partial_liquidation = pool.check_leverage(
+            &remaining_position,
+            &token_price,
+            &token_ema_price,
+            custody,
+            &collateral_token_price,
+            &collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+            false,
+        )?;
+        if !partial_liquidation {
+            msg!("Partial liquidation is not sufficient, liquidating entirely");
+            (closed_position, remaining_position) = position.split(position.size_usd)?;
+            (total_amount_out, fee_amount, profit_usd, loss_usd) = pool.get_close_amount(
+                &closed_position,
+                &token_price,
+                &token_ema_price,
+                custody,
+                &collateral_token_price,
+                &collateral_token_ema_price,
+                collateral_custody,
+                curtime,
+                true,
+            )?;
+            reward = pool.get_liquidation_reward(
+                total_amount_out,
+                custody,
+                position.liquidatable_since,
+                curtime,
+            )?;
+        } else {
+            msg!(
+                "Partial liquidation: {} / {}",
+                closed_position.size_usd,
+                position.size_usd
+            );
+        }
+    }
+
     let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
     if position.side == Side::Short || custody.is_virtual {
         fee_amount = collateral_token_ema_price
@@ -196,33 +333,97 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
     msg!("Net profit: {}, loss: {}", profit_usd, loss_usd);
     msg!("Collected fee: {}", fee_amount);
 
-    let reward = Pool::get_fee_amount(custody.fees.liquidation, total_amount_out)?;
-    let user_amount = math::checked_sub(total_amount_out, reward)?;
+    // the rest of the closed part stays in the position on partial liquidation
+    let net_amount = math::checked_sub(total_amount_out, reward)?;
+    let (user_amount, retained_amount) = if partial_liquidation {
+        (0, net_amount)
+    } else {
+        (net_amount, 0)
+    };
 
     msg!("Amount out: {}", user_amount);
     msg!("Reward: {}", reward);
 
-    // unlock pool funds
-    collateral_custody.unlock_funds(position.locked_amount)?;
+    // the retained part of the closed collateral stays in the custody with the remaining position
+    collateral_custody.assets.collateral =
+        math::checked_add(collateral_custody.assets.collateral, retained_amount)?;
 
-    // check pool constraints
-    msg!("Check pool constraints");
-    require!(
-        pool.check_available_amount(total_amount_out, collateral_custody)?,
-        PerpetualsError::CustodyAmountLimit
-    );
+    // cover negative equity from the margin account and then from the insurance fund
+    // before LPs take the hit
+    let mut bad_debt = pool.get_bad_debt_amount(
+        &closed_position,
+        loss_usd,
+        &collateral_token_price,
+        &collateral_token_ema_price,
+        collateral_custody,
+    )?;
+
+    let mut margin_amount = 0;
+    if let Some(margin_account) = margin_account.as_mut() {
+        margin_amount = margin_account.cover_bad_debt(
+            &collateral_custody.key(),
+            collateral_custody,
+            bad_debt,
+        )?;
+        if margin_amount > 0 {
+            msg!("Bad debt covered by margin account: {}", margin_amount);
+            bad_debt = math::checked_sub(bad_debt, margin_amount)?;
+        }
+
+        margin_account.remove_position(&position.key());
+        margin_account.exit(&crate::ID)?;
+    }
+
+    let insurance_amount =
+        std::cmp::min(bad_debt, ctx.accounts.insurance_fund_token_account.amount);
+
+    if insurance_amount > 0 {
+        msg!("Bad debt covered by insurance fund: {}", insurance_amount);
+        perpetuals.transfer_tokens(
+            ctx.accounts.insurance_fund_token_account.to_account_info(),
+            ctx.accounts
+                .collateral_custody_token_account
+                .to_account_info(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            insurance_amount,
+        )?;
+
+        collateral_custody.assets.owned =
+            math::checked_add(collateral_custody.assets.owned, insurance_amount)?;
+    }
+
+    // update custody stats
+    msg!("Update custody stats");
+    let insurance_fee = pool.settle_close_position(
+        position,
+        &closed_position,
+        &remaining_position,
+        total_amount_out,
+        fee_amount,
+        fee_amount_usd,
+        profit_usd,
+        loss_usd,
+        &token_ema_price,
+        custody,
+        collateral_custody,
+        curtime,
+        true,
+    )?;
 
     // transfer tokens
     msg!("Transfer tokens");
-    perpetuals.transfer_tokens(
-        ctx.accounts
-            .collateral_custody_token_account
-            .to_account_info(),
-        ctx.accounts.receiving_account.to_account_info(),
-        ctx.accounts.transfer_authority.to_account_info(),
-        ctx.accounts.token_program.to_account_info(),
-        user_amount,
-    )?;
+    if user_amount > 0 {
+        perpetuals.transfer_tokens(
+            ctx.accounts
+                .collateral_custody_token_account
+                .to_account_info(),
+            ctx.accounts.receiving_account.to_account_info(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            user_amount,
+        )?;
+    }
 
     perpetuals.transfer_tokens(
         ctx.accounts
@@ -234,90 +435,54 @@ pub fn liquidate(ctx: Context<Liquidate>, _params: &LiquidateParams) -> Result<(
         reward,
     )?;
 
-    // update custody stats
-    msg!("Update custody stats");
-    collateral_custody.collected_fees.liquidation_usd = collateral_custody
-        .collected_fees
-        .liquidation_usd
-        .wrapping_add(fee_amount_usd);
-
-    if total_amount_out > position.collateral_amount {
-        let amount_lost = total_amount_out.saturating_sub(position.collateral_amount);
-        collateral_custody.assets.owned =
-            math::checked_sub(collateral_custody.assets.owned, amount_lost)?;
-    } else {
-        let amount_gained = position.collateral_amount.saturating_sub(total_amount_out);
-        collateral_custody.assets.owned =
-            math::checked_add(collateral_custody.assets.owned, amount_gained)?;
-    }
-    collateral_custody.assets.collateral = math::checked_sub(
-        collateral_custody.assets.collateral,
-        position.collateral_amount,
-    )?;
-
-    let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
-
-    // Pay protocol_fee from custody if possible, otherwise no protocol_fee
-    if pool.check_available_amount(protocol_fee, collateral_custody)? {
-        collateral_custody.assets.protocol_fees =
-            math::checked_add(collateral_custody.assets.protocol_fees, protocol_fee)?;
-
-        collateral_custody.assets.owned =
-            math::checked_sub(collateral_custody.assets.owned, protocol_fee)?;
-    }
-
-    // if custody and collateral_custody accounts are the same, ensure that data is in sync
-    if position.side == Side::Long && !custody.is_virtual {
-        collateral_custody.volume_stats.liquidation_usd = math::checked_add(
-            collateral_custody.volume_stats.liquidation_usd,
-            position.size_usd,
+    if insurance_fee > 0 {
+        perpetuals.transfer_tokens(
+            ctx.accounts
+                .collateral_custody_token_account
+                .to_account_info(),
+            ctx.accounts.insurance_fund_token_account.to_account_info(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            insurance_fee,
         )?;
+    }
 
-        if position.side == Side::Long {
-            collateral_custody.trade_stats.oi_long_usd = collateral_custody
-                .trade_stats
-                .oi_long_usd
-                .saturating_sub(position.size_usd);
-        } else {
-            collateral_custody.trade_stats.oi_short_usd = collateral_custody
-                .trade_stats
-                .oi_short_usd
-                .saturating_sub(position.size_usd);
-        }
-
-        collateral_custody.trade_stats.profit_usd = collateral_custody
-            .trade_stats
-            .profit_usd
-            .wrapping_add(profit_usd);
-        collateral_custody.trade_stats.loss_usd = collateral_custody
-            .trade_stats
-            .loss_usd
-            .wrapping_add(loss_usd);
-
-        collateral_custody.remove_position(position, curtime, None)?;
-        collateral_custody.update_borrow_rate(curtime)?;
-        *custody = collateral_custody.clone();
+    emit!(LiquidateEvent {
+        signer: ctx.accounts.signer.key(),
+        owner: position.owner,
+        pool: pool.key(),
+        custody: custody.key(),
+        collateral_custody: collateral_custody.key(),
+        position: position.key(),
+        side: position.side,
+        price: pool.get_exit_price(
+            &token_price,
+            &token_ema_price,
+            position.side,
+            closed_position.size_usd,
+            custody,
+        )?,
+        size_usd: closed_position.size_usd,
+        collateral_amount: closed_position.collateral_amount,
+        profit_usd,
+        loss_usd,
+        fee_amount,
+        fee_amount_usd,
+        transfer_amount: user_amount,
+        reward_amount: reward,
+        margin_amount,
+        insurance_amount,
+        remaining_size_usd: remaining_position.size_usd,
+        update_time: curtime,
+    });
+
+    // keep the remaining part open or close the position account
+    if partial_liquidation {
+        position.set_inner(remaining_position);
     } else {
-        custody.volume_stats.liquidation_usd =
-            math::checked_add(custody.volume_stats.liquidation_usd, position.size_usd)?;
-
-        if position.side == Side::Long {
-            custody.trade_stats.oi_long_usd = custody
-                .trade_stats
-                .oi_long_usd
-                .saturating_sub(position.size_usd);
-        } else {
-            custody.trade_stats.oi_short_usd = custody
-                .trade_stats
-                .oi_short_usd
-                .saturating_sub(position.size_usd);
-        }
-
-        custody.trade_stats.profit_usd = custody.trade_stats.profit_usd.wrapping_add(profit_usd);
-        custody.trade_stats.loss_usd = custody.trade_stats.loss_usd.wrapping_add(loss_usd);
-
-        custody.remove_position(position, curtime, Some(collateral_custody))?;
-        collateral_custody.update_borrow_rate(curtime)?;
+        ctx.accounts
+            .position
+            .close(ctx.accounts.signer.to_account_info())?;
     }
 
     Ok(())
@@ -3,16 +3,18 @@
 use {
     crate::{
         constants::{
-            CUSTODY_SEED, CUSTODY_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED, POSITION_SEED,
+            CUSTODY_SEED, CUSTODY_TOKEN_ACCOUNT_SEED, INSURANCE_FUND_TOKEN_ACCOUNT_SEED,
+            PERPETUALS_SEED, POOL_SEED, POSITION_COUNTER_SEED, POSITION_SEED,
         },
         error::PerpetualsError,
+        events::OpenPositionEvent,
         math,
-        oracle::OraclePrice,
         state::{
             custody::Custody,
             perpetuals::Perpetuals,
             pool::Pool,
             position::{Position, Side},
+            position_counter::PositionCounter,
         },
     },
     anchor_lang::prelude::*,
@@ -33,6 +35,13 @@ pub struct OpenPosition<'info> {
     )]
     pub funding_account: Box<Account<'info, TokenAccount>>,
 
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
     #[account(
         seeds = [
             PERPETUALS_SEED.as_bytes()
@@ -45,12 +54,24 @@ pub struct OpenPosition<'info> {
         mut,
         seeds = [
             POOL_SEED.as_bytes(),
-            &params.pool_id.to_le_bytes()
+            pool.name.as_bytes()
         ],
         bump = pool.bump
     )]
     pub pool: Box<Account<'info, Pool>>,
 
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = PositionCounter::LEN,
+        seeds = [
+            POSITION_COUNTER_SEED.as_bytes(),
+            owner.key().as_ref()
+        ],
+        bump
+    )]
+    pub position_counter: Box<Account<'info, PositionCounter>>,
+
     #[account(
         init,
         payer = owner,
@@ -60,7 +81,8 @@ pub struct OpenPosition<'info> {
             owner.key().as_ref(),
             pool.key().as_ref(),
             custody.key().as_ref(),
-            &[params.side as u8]
+            &[params.side as u8],
+            &params.position_id.to_le_bytes()
         ],
         bump
     )]
@@ -129,6 +151,17 @@ pub struct OpenPosition<'info> {
     )]
     pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
 
+    #[account(
+        mut,
+        seeds = [
+            INSURANCE_FUND_TOKEN_ACCOUNT_SEED.as_bytes(),
+            pool.key().as_ref(),
+            collateral_custody.mint.as_ref()
+        ],
+        bump = collateral_custody.insurance_fund_token_account_bump
+    )]
+    pub insurance_fund_token_account: Box<Account<'info, TokenAccount>>,
+
     #[account()]
     pub system_program: Program<'info, System>,
     token_program: Program<'info, Token>,
@@ -136,7 +169,8 @@ pub struct OpenPosition<'info> {
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
 pub struct OpenPositionParams {
-    pub pool_id: u64,
+    // must match the next id of the owner's position counter
+    pub position_id: u64,
     pub price: u64,
     pub collateral: u64,
     pub size: u64,
@@ -151,10 +185,15 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
     let collateral_custody = ctx.accounts.collateral_custody.as_mut();
     require!(
         perpetuals.permissions.allow_open_position
+            && ctx.accounts.pool.permissions.allow_open_position
             && custody.permissions.allow_open_position
             && !custody.is_stable, // can't long/short stablecoins i guess?
         PerpetualsError::InstructionNotAllowed
     );
+    require!(
+        custody.market_status.allow_open_position(),
+        PerpetualsError::MarketStatusRestricted
+    );
 
     // Validate ema oracle
     if custody.needs_ema_oracle() {
@@ -193,6 +232,18 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
     } else {
         require_keys_eq!(custody.key(), collateral_custody.key());
     };
+
+    // reserve position id
+    let position_counter = ctx.accounts.position_counter.as_mut();
+    if position_counter.owner == Pubkey::default() {
+        position_counter.owner = ctx.accounts.owner.key();
+        position_counter.bump = *ctx
+            .bumps
+            .get("position_counter")
+            .ok_or(ProgramError::InvalidSeeds)?;
+    }
+    position_counter.use_position_id(params.position_id)?;
+
     let position = ctx.accounts.position.as_mut();
     let pool = ctx.accounts.pool.as_mut();
 
@@ -204,20 +255,29 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
     let (token_price, token_ema_price) = custody.oracle.extract_prices(
         &ctx.accounts.custody_oracle_account,
         &ctx.accounts.custody_ema_oracle_account,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         clock,
+        &custody.oracle_params.get_open_params(),
     )?;
 
-    let (collateral_price, collateral_ema_price) = custody.oracle.extract_prices(
+    let (collateral_price, collateral_ema_price) = collateral_custody.oracle.extract_prices(
         &ctx.accounts.collateral_custody_oracle_account,
         &ctx.accounts.collateral_custody_ema_oracle_account,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
         clock,
+        &collateral_custody.oracle_params.get_open_params(),
     )?;
 
     let min_collateral_price =
         collateral_price.get_min_price(&collateral_ema_price, collateral_custody.is_stable)?;
 
-    let position_price =
-        pool.get_entry_price(&token_price, &token_ema_price, params.side, custody)?;
+    let position_price = pool.get_entry_price(
+        &token_price,
+        &token_ema_price,
+        params.side,
+        params.size,
+        custody,
+    )?;
 
     if params.side == Side::Long {
         require_gte!(
@@ -234,51 +294,19 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
     }
 
     // compute position parameters
-    let position_oracle_price = OraclePrice {
-        price: position_price,
-        exponent: -(Perpetuals::PRICE_DECIMALS as i32),
-    };
-    let size_usd = position_oracle_price.get_asset_amount_usd(params.size, custody.decimals)?;
+    let (size_usd, locked_amount, borrow_size_usd, fee_amount, fee_amount_usd) = pool
+        .get_open_position_amounts(
+            params.side,
+            params.size,
+            position_price,
+            &token_ema_price,
+            custody,
+            &collateral_price,
+            &collateral_ema_price,
+            collateral_custody,
+        )?;
     let collateral_usd = min_collateral_price
         .get_asset_amount_usd(params.collateral, collateral_custody.decimals)?;
-
-    let locked_amount = if use_collateral_custody {
-        custody.get_locked_amount(
-            min_collateral_price.get_token_amount(size_usd, collateral_custody.decimals)?,
-            params.side,
-        )?
-    } else {
-        custody.get_locked_amount(params.size, params.side)?
-    };
-
-    // Sort out the max_payoff
-    let borrow_size_usd = if custody.pricing.max_payoff_mult as u128 != Perpetuals::BPS_POWER {
-        if use_collateral_custody {
-            let max_collateral_price = if collateral_price < collateral_ema_price {
-                collateral_ema_price
-            } else {
-                collateral_price
-            };
-            max_collateral_price.get_asset_amount_usd(locked_amount, collateral_custody.decimals)?
-        } else {
-            position_oracle_price.get_asset_amount_usd(locked_amount, custody.decimals)?
-        }
-    } else {
-        size_usd
-    };
-
-    // compute fee
-    let mut fee_amount = pool.get_entry_fee(
-        custody.fees.open_position,
-        params.size,
-        locked_amount,
-        collateral_custody,
-    )?;
-    let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
-    if use_collateral_custody {
-        fee_amount =
-            collateral_ema_price.get_token_amount(fee_amount_usd, collateral_custody.decimals)?;
-    }
     msg!("Collected fee: {}", fee_amount);
 
     // compute amount to transfer
@@ -291,6 +319,7 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
     position.pool = pool.key();
     position.custody = custody.key();
     position.collateral_custody = collateral_custody.key();
+    position.position_id = params.position_id;
     position.open_time = perpetuals.get_time()?;
     position.update_time = 0;
     position.side = params.side;
@@ -301,6 +330,7 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
     position.unrealized_profit_usd = 0;
     position.unrealized_loss_usd = 0;
     position.cumulative_interest_snapshot = collateral_custody.get_cumulative_interest(curtime)?;
+    position.cumulative_funding_snapshot = custody.get_cumulative_funding(params.side, curtime)?;
     position.locked_amount = locked_amount;
     position.collateral_amount = params.collateral;
     position.bump = *ctx
@@ -329,9 +359,6 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
         PerpetualsError::MaxLeverage
     );
 
-    // lock funds for potential profit payoff
-    collateral_custody.lock_funds(position.locked_amount)?;
-
     // transfer tokens
     msg!("Transfer tokens");
     perpetuals.transfer_tokens_from_user(
@@ -346,58 +373,44 @@ pub fn open_position(ctx: Context<OpenPosition>, params: &OpenPositionParams) ->
 
     // update custody stats
     msg!("Update custody stats");
-    collateral_custody.collected_fees.open_position_usd = collateral_custody
-        .collected_fees
-        .open_position_usd
-        .wrapping_add(fee_amount_usd);
-
-    collateral_custody.assets.collateral =
-        math::checked_add(collateral_custody.assets.collateral, params.collateral)?;
-
-    let protocol_fee = Pool::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
-    collateral_custody.assets.protocol_fees =
-        math::checked_add(collateral_custody.assets.protocol_fees, protocol_fee)?;
-
-    // if custody and collateral_custody accounts are the same, ensure that data is in sync
-    if position.side == Side::Long && !custody.is_virtual {
-        collateral_custody.volume_stats.open_position_usd = collateral_custody
-            .volume_stats
-            .open_position_usd
-            .wrapping_add(size_usd);
-
-        if params.side == Side::Long {
-            collateral_custody.trade_stats.oi_long_usd =
-                math::checked_add(collateral_custody.trade_stats.oi_long_usd, size_usd)?;
-        } else {
-            collateral_custody.trade_stats.oi_short_usd =
-                math::checked_add(collateral_custody.trade_stats.oi_short_usd, size_usd)?;
-        }
-
-        collateral_custody.add_position(position, &token_ema_price, curtime, None)?;
-        collateral_custody.update_borrow_rate(curtime)?;
-        *custody = collateral_custody.clone();
-    } else {
-        custody.volume_stats.open_position_usd = custody
-            .volume_stats
-            .open_position_usd
-            .wrapping_add(size_usd);
-
-        if params.side == Side::Long {
-            custody.trade_stats.oi_long_usd =
-                math::checked_add(custody.trade_stats.oi_long_usd, size_usd)?;
-        } else {
-            custody.trade_stats.oi_short_usd =
-                math::checked_add(custody.trade_stats.oi_short_usd, size_usd)?;
-        }
-
-        custody.add_position(
-            position,
-            &token_ema_price,
-            curtime,
-            Some(collateral_custody),
+    let insurance_fee = pool.settle_open_position(
+        position,
+        fee_amount,
+        fee_amount_usd,
+        &token_ema_price,
+        custody,
+        collateral_custody,
+        curtime,
+    )?;
+    if insurance_fee > 0 {
+        perpetuals.transfer_tokens(
+            ctx.accounts
+                .collateral_custody_token_account
+                .to_account_info(),
+            ctx.accounts.insurance_fund_token_account.to_account_info(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            insurance_fee,
         )?;
-        collateral_custody.update_borrow_rate(curtime)?;
     }
 
+    emit!(OpenPositionEvent {
+        owner: position.owner,
+        pool: pool.key(),
+        custody: custody.key(),
+        collateral_custody: collateral_custody.key(),
+        position: position.key(),
+        position_id: position.position_id,
+        side: position.side,
+        price: position.price,
+        size_usd: position.size_usd,
+        collateral_usd: position.collateral_usd,
+        collateral_amount: position.collateral_amount,
+        locked_amount: position.locked_amount,
+        fee_amount,
+        fee_amount_usd,
+        open_time: position.open_time,
+    });
+
     Ok(())
 }
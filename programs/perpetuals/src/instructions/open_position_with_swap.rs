@@ -0,0 +1,488 @@
+//! OpenPositionWithSwap instruction handler
+
+use {
+    crate::{
+        constants::{
+            CUSTODY_SEED, CUSTODY_TOKEN_ACCOUNT_SEED, INSURANCE_FUND_TOKEN_ACCOUNT_SEED,
+            PERPETUALS_SEED, POOL_SEED, POSITION_COUNTER_SEED, POSITION_SEED,
+        },
+        error::PerpetualsError,
+        events::{OpenPositionEvent, SwapEvent},
+        math,
+        state::{
+            custody::Custody,
+            perpetuals::Perpetuals,
+            pool::Pool,
+            position::{Position, Side},
+            position_counter::PositionCounter,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+#[instruction(params: OpenPositionWithSwapParams)]
+pub struct OpenPositionWithSwap<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = funding_account.mint == receiving_custody.mint,
+        has_one = owner
+    )]
+    pub funding_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [PERPETUALS_SEED.as_bytes()],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        mut,
+        seeds = [POOL_SEED.as_bytes(),
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = PositionCounter::LEN,
+        seeds = [POSITION_COUNTER_SEED.as_bytes(),
+                 owner.key().as_ref()],
+        bump
+    )]
+    pub position_counter: Box<Account<'info, PositionCounter>>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Position::LEN,
+        seeds = [POSITION_SEED.as_bytes(),
+                 owner.key().as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &[params.side as u8],
+                 &params.position_id.to_le_bytes()],
+        bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    // custody of the token provided by the user, swapped into the collateral token
+    #[account(
+        mut,
+        seeds = [CUSTODY_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 receiving_custody.mint.as_ref()],
+        bump = receiving_custody.bump
+    )]
+    pub receiving_custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the provided token
+    #[account(
+        address = receiving_custody.oracle.key()
+    )]
+    pub receiving_custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = receiving_custody.ema_oracle.is_none() || Some(receiving_custody_ema_oracle_account.key()) == receiving_custody.ema_oracle.map(|o| o.key()) @ PerpetualsError::InvalidEmaOracle
+    )]
+    pub receiving_custody_ema_oracle_account: Option<AccountInfo<'info>>,
+
+    #[account(
+        mut,
+        seeds = [CUSTODY_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 receiving_custody.mint.as_ref()],
+        bump = receiving_custody.token_account_bump
+    )]
+    pub receiving_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [CUSTODY_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the position token
+    #[account(
+        address = custody.oracle.key()
+    )]
+    pub custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = custody.ema_oracle.is_none() || Some(custody_ema_oracle_account.key()) == custody.ema_oracle.map(|o| o.key()) @ PerpetualsError::InvalidEmaOracle
+    )]
+    pub custody_ema_oracle_account: Option<AccountInfo<'info>>,
+
+    #[account(
+        mut,
+        seeds = [CUSTODY_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.bump
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    /// CHECK: oracle account for the collateral token
+    #[account(
+        address = collateral_custody.oracle.key()
+    )]
+    pub collateral_custody_oracle_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = collateral_custody.ema_oracle.is_none() || Some(collateral_custody_ema_oracle_account.key()) == collateral_custody.ema_oracle.map(|o| o.key()) @ PerpetualsError::InvalidEmaOracle
+    )]
+    pub collateral_custody_ema_oracle_account: Option<AccountInfo<'info>>,
+
+    #[account(
+        mut,
+        seeds = [CUSTODY_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.token_account_bump
+    )]
+    pub collateral_custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [INSURANCE_FUND_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.insurance_fund_token_account_bump
+    )]
+    pub insurance_fund_token_account: Box<Account<'info, TokenAccount>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct OpenPositionWithSwapParams {
+    // must match the next id of the owner's position counter
+    pub position_id: u64,
+    pub price: u64,
+    // amount of the receiving custody token, swapped into collateral and open fee
+    pub amount_in: u64,
+    pub size: u64,
+    pub side: Side,
+}
+
+pub fn open_position_with_swap(
+    ctx: Context<OpenPositionWithSwap>,
+    params: &OpenPositionWithSwapParams,
+) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let receiving_custody = ctx.accounts.receiving_custody.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    require!(
+        perpetuals.permissions.allow_open_position
+            && ctx.accounts.pool.permissions.allow_open_position
+            && custody.permissions.allow_open_position
+            && !custody.is_stable,
+        PerpetualsError::InstructionNotAllowed
+    );
+    require!(
+        custody.market_status.allow_open_position(),
+        PerpetualsError::MarketStatusRestricted
+    );
+    require!(
+        perpetuals.permissions.allow_swap
+            && ctx.accounts.pool.permissions.allow_swap
+            && receiving_custody.permissions.allow_swap
+            && collateral_custody.permissions.allow_swap
+            && !receiving_custody.is_virtual,
+        PerpetualsError::InstructionNotAllowed
+    );
+
+    // validate ema oracles
+    if receiving_custody.needs_ema_oracle() {
+        require!(
+            ctx.accounts.receiving_custody_ema_oracle_account.is_some(),
+            PerpetualsError::EmaOracleRequired
+        );
+    }
+    if custody.needs_ema_oracle() {
+        require!(
+            ctx.accounts.custody_ema_oracle_account.is_some(),
+            PerpetualsError::EmaOracleRequired
+        );
+    }
+
+    // validate inputs
+    msg!("Validate inputs");
+    if params.price == 0 || params.amount_in == 0 || params.size == 0 || params.side == Side::None {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    // same collateral rules as in open_position
+    let use_collateral_custody = params.side == Side::Short || custody.is_virtual;
+    if use_collateral_custody {
+        require_keys_neq!(custody.key(), collateral_custody.key());
+        require!(
+            collateral_custody.is_stable && !collateral_custody.is_virtual,
+            PerpetualsError::InvalidCollateralCustody
+        );
+    } else {
+        require_keys_eq!(custody.key(), collateral_custody.key());
+    };
+    require_keys_neq!(receiving_custody.key(), collateral_custody.key());
+    // the provided token can be the traded token itself if collateral is a stablecoin
+    let same_receiving_custody = receiving_custody.key() == custody.key();
+
+    // reserve position id
+    let position_counter = ctx.accounts.position_counter.as_mut();
+    if position_counter.owner == Pubkey::default() {
+        position_counter.owner = ctx.accounts.owner.key();
+        position_counter.bump = *ctx
+            .bumps
+            .get("position_counter")
+            .ok_or(ProgramError::InvalidSeeds)?;
+    }
+    position_counter.use_position_id(params.position_id)?;
+
+    let position = ctx.accounts.position.as_mut();
+    let pool = ctx.accounts.pool.as_mut();
+
+    // read prices
+    let curtime = perpetuals.get_time()?;
+
+    let clock = &Clock::get()?;
+
+    let (received_token_price, received_token_ema_price) =
+        receiving_custody.oracle.extract_prices(
+            &ctx.accounts.receiving_custody_oracle_account,
+            &ctx.accounts.receiving_custody_ema_oracle_account,
+            receiving_custody.get_secondary_oracle(ctx.remaining_accounts),
+            clock,
+            &receiving_custody.oracle_params.get_open_params(),
+        )?;
+
+    let (token_price, token_ema_price) = custody.oracle.extract_prices(
+        &ctx.accounts.custody_oracle_account,
+        &ctx.accounts.custody_ema_oracle_account,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
+        clock,
+        &custody.oracle_params.get_open_params(),
+    )?;
+
+    let (collateral_price, collateral_ema_price) = collateral_custody.oracle.extract_prices(
+        &ctx.accounts.collateral_custody_oracle_account,
+        &ctx.accounts.collateral_custody_ema_oracle_account,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
+        clock,
+        &collateral_custody.oracle_params.get_open_params(),
+    )?;
+
+    // swap provided tokens into the collateral token, same as swap
+    msg!("Compute swap amount");
+    let token_id_in = pool.get_token_id(&receiving_custody.key())?;
+    let token_id_out = pool.get_token_id(&collateral_custody.key())?;
+
+    let (swapped_amount, swap_fees) = pool.settle_swap(
+        token_id_in,
+        token_id_out,
+        params.amount_in,
+        &received_token_price,
+        &received_token_ema_price,
+        receiving_custody,
+        &collateral_price,
+        &collateral_ema_price,
+        collateral_custody,
+        curtime,
+    )?;
+    msg!("Collected swap fees: {} {}", swap_fees.0, swap_fees.1);
+    msg!("Swapped amount: {}", swapped_amount);
+
+    // transfer tokens, swapped collateral stays in the collateral custody token account
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens_from_user(
+        ctx.accounts.funding_account.to_account_info(),
+        ctx.accounts
+            .receiving_custody_token_account
+            .to_account_info(),
+        ctx.accounts.owner.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.amount_in,
+    )?;
+
+    // if receiving custody and custody accounts are the same, ensure that data is in sync
+    if same_receiving_custody {
+        *custody = receiving_custody.clone();
+    }
+
+    // compute position price
+    let min_collateral_price =
+        collateral_price.get_min_price(&collateral_ema_price, collateral_custody.is_stable)?;
+
+    let position_price = pool.get_entry_price(
+        &token_price,
+        &token_ema_price,
+        params.side,
+        params.size,
+        custody,
+    )?;
+
+    if params.side == Side::Long {
+        require_gte!(
+            params.price,
+            position_price,
+            PerpetualsError::MaxPriceSlippage
+        );
+    } else {
+        require_gte!(
+            position_price,
+            params.price,
+            PerpetualsError::MaxPriceSlippage
+        );
+    }
+
+    // compute position parameters
+    let (size_usd, locked_amount, borrow_size_usd, fee_amount, fee_amount_usd) = pool
+        .get_open_position_amounts(
+            params.side,
+            params.size,
+            position_price,
+            &token_ema_price,
+            custody,
+            &collateral_price,
+            &collateral_ema_price,
+            collateral_custody,
+        )?;
+    msg!("Collected fee: {}", fee_amount);
+
+    // swapped tokens pay the fee, the rest becomes position collateral
+    require!(
+        swapped_amount > fee_amount,
+        PerpetualsError::InsufficientAmountReturned
+    );
+    let collateral = math::checked_sub(swapped_amount, fee_amount)?;
+    msg!("Collateral: {}", collateral);
+    let collateral_usd =
+        min_collateral_price.get_asset_amount_usd(collateral, collateral_custody.decimals)?;
+
+    // init new position
+    msg!("Initialize new position");
+    position.owner = ctx.accounts.owner.key();
+    position.pool = pool.key();
+    position.custody = custody.key();
+    position.collateral_custody = collateral_custody.key();
+    position.position_id = params.position_id;
+    position.open_time = perpetuals.get_time()?;
+    position.update_time = 0;
+    position.side = params.side;
+    position.price = position_price;
+    position.size_usd = size_usd;
+    position.borrow_size_usd = borrow_size_usd;
+    position.collateral_usd = collateral_usd;
+    position.unrealized_profit_usd = 0;
+    position.unrealized_loss_usd = 0;
+    position.cumulative_interest_snapshot = collateral_custody.get_cumulative_interest(curtime)?;
+    position.cumulative_funding_snapshot = custody.get_cumulative_funding(params.side, curtime)?;
+    position.locked_amount = locked_amount;
+    position.collateral_amount = collateral;
+    position.bump = *ctx
+        .bumps
+        .get("position")
+        .ok_or(ProgramError::InvalidSeeds)?;
+
+    // check position risk
+    msg!("Check position risks");
+    require!(
+        position.locked_amount > 0,
+        PerpetualsError::InsufficientAmountReturned
+    );
+    require!(
+        pool.check_leverage(
+            position,
+            &token_price,
+            &token_ema_price,
+            custody,
+            &collateral_price,
+            &collateral_ema_price,
+            collateral_custody,
+            curtime,
+            true
+        )?,
+        PerpetualsError::MaxLeverage
+    );
+
+    // update custody stats of the position
+    let insurance_fee = pool.settle_open_position(
+        position,
+        fee_amount,
+        fee_amount_usd,
+        &token_ema_price,
+        custody,
+        collateral_custody,
+        curtime,
+    )?;
+    if insurance_fee > 0 {
+        perpetuals.transfer_tokens(
+            ctx.accounts
+                .collateral_custody_token_account
+                .to_account_info(),
+            ctx.accounts.insurance_fund_token_account.to_account_info(),
+            ctx.accounts.transfer_authority.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            insurance_fee,
+        )?;
+    }
+
+    if same_receiving_custody {
+        *receiving_custody = custody.clone();
+    }
+
+    emit!(SwapEvent {
+        owner: position.owner,
+        pool: pool.key(),
+        receiving_custody: receiving_custody.key(),
+        dispensing_custody: collateral_custody.key(),
+        amount_in: params.amount_in,
+        fee_in: swap_fees.0,
+        amount_out: swapped_amount,
+        fee_out: swap_fees.1,
+        receiving_custody_owned: receiving_custody.assets.owned,
+        dispensing_custody_owned: collateral_custody.assets.owned,
+    });
+
+    emit!(OpenPositionEvent {
+        owner: position.owner,
+        pool: pool.key(),
+        custody: custody.key(),
+        collateral_custody: collateral_custody.key(),
+        position: position.key(),
+        position_id: position.position_id,
+        side: position.side,
+        price: position.price,
+        size_usd: position.size_usd,
+        collateral_usd: position.collateral_usd,
+        collateral_amount: position.collateral_amount,
+        locked_amount: position.locked_amount,
+        fee_amount,
+        fee_amount_usd,
+        open_time: position.open_time,
+    });
+
+    Ok(())
+}
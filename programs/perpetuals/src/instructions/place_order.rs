@@ -0,0 +1,236 @@
+//! PlaceOrder instruction handler
+
+use {
+    crate::{
+        constants::{
+            CUSTODY_SEED, ORDER_SEED, ORDER_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED,
+            POSITION_COUNTER_SEED,
+        },
+        error::PerpetualsError,
+        events::PlaceOrderEvent,
+        state::{
+            custody::Custody,
+            order::{Order, OrderType},
+            perpetuals::Perpetuals,
+            pool::Pool,
+            position::Side,
+            position_counter::PositionCounter,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Mint, Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+#[instruction(params: PlaceOrderParams)]
+pub struct PlaceOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = funding_account.mint == collateral_custody.mint,
+        has_one = owner
+    )]
+    pub funding_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [PERPETUALS_SEED.as_bytes()],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [POOL_SEED.as_bytes(),
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = PositionCounter::LEN,
+        seeds = [POSITION_COUNTER_SEED.as_bytes(),
+                 owner.key().as_ref()],
+        bump
+    )]
+    pub position_counter: Box<Account<'info, PositionCounter>>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = Order::LEN,
+        seeds = [ORDER_SEED.as_bytes(),
+                 owner.key().as_ref(),
+                 pool.key().as_ref(),
+                 custody.key().as_ref(),
+                 &params.order_id.to_le_bytes()],
+        bump
+    )]
+    pub order: Box<Account<'info, Order>>,
+
+    #[account(
+        init,
+        payer = owner,
+        token::mint = collateral_custody_token_mint,
+        token::authority = transfer_authority,
+        seeds = [ORDER_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 order.key().as_ref()],
+        bump
+    )]
+    pub order_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [CUSTODY_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    #[account(
+        seeds = [CUSTODY_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 collateral_custody.mint.as_ref()],
+        bump = collateral_custody.bump
+    )]
+    pub collateral_custody: Box<Account<'info, Custody>>,
+
+    #[account(
+        constraint = collateral_custody_token_mint.key() == collateral_custody.mint
+    )]
+    pub collateral_custody_token_mint: Box<Account<'info, Mint>>,
+
+    system_program: Program<'info, System>,
+    token_program: Program<'info, Token>,
+    rent: Sysvar<'info, Rent>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct PlaceOrderParams {
+    pub order_id: u64,
+    // limit orders: next id of the owner's position counter, reserved for the new position
+    // stop-loss and take-profit: id of the position to close
+    pub position_id: u64,
+    pub order_type: OrderType,
+    pub side: Side,
+    pub trigger_price: u64,
+    // limit orders: position size in tokens, stop-loss and take-profit: size_usd to close
+    pub size: u64,
+    // limit orders only, escrowed until the order is executed or cancelled
+    pub collateral: u64,
+}
+
+pub fn place_order(ctx: Context<PlaceOrder>, params: &PlaceOrderParams) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    let collateral_custody = ctx.accounts.collateral_custody.as_mut();
+    if params.order_type == OrderType::Limit {
+        require!(
+            perpetuals.permissions.allow_open_position
+                && ctx.accounts.pool.permissions.allow_open_position
+                && custody.permissions.allow_open_position
+                && !custody.is_stable,
+            PerpetualsError::InstructionNotAllowed
+        );
+        require!(
+            custody.market_status.allow_open_position(),
+            PerpetualsError::MarketStatusRestricted
+        );
+    } else {
+        require!(
+            perpetuals.permissions.allow_close_position
+                && ctx.accounts.pool.permissions.allow_close_position
+                && custody.permissions.allow_close_position,
+            PerpetualsError::InstructionNotAllowed
+        );
+    }
+
+    // validate inputs
+    msg!("Validate inputs");
+    let order = ctx.accounts.order.as_mut();
+    order.owner = ctx.accounts.owner.key();
+    order.pool = ctx.accounts.pool.key();
+    order.custody = custody.key();
+    order.collateral_custody = collateral_custody.key();
+    order.order_id = params.order_id;
+    order.position_id = params.position_id;
+    order.order_type = params.order_type;
+    order.side = params.side;
+    order.trigger_price = params.trigger_price;
+    order.size = params.size;
+    order.collateral_amount = params.collateral;
+    order.create_time = perpetuals.get_time()?;
+    order.bump = *ctx.bumps.get("order").ok_or(ProgramError::InvalidSeeds)?;
+    order.token_account_bump = *ctx
+        .bumps
+        .get("order_token_account")
+        .ok_or(ProgramError::InvalidSeeds)?;
+
+    if !order.validate() {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    // same collateral rules as in open_position, stop-loss and take-profit orders
+    // are matched against the position's collateral custody on execution
+    if params.order_type == OrderType::Limit {
+        if params.side == Side::Short || custody.is_virtual {
+            require_keys_neq!(custody.key(), collateral_custody.key());
+            require!(
+                collateral_custody.is_stable && !collateral_custody.is_virtual,
+                PerpetualsError::InvalidCollateralCustody
+            );
+        } else {
+            require_keys_eq!(custody.key(), collateral_custody.key());
+        }
+
+        // reserve position id
+        let position_counter = ctx.accounts.position_counter.as_mut();
+        if position_counter.owner == Pubkey::default() {
+            position_counter.owner = ctx.accounts.owner.key();
+            position_counter.bump = *ctx
+                .bumps
+                .get("position_counter")
+                .ok_or(ProgramError::InvalidSeeds)?;
+        }
+        position_counter.use_position_id(params.position_id)?;
+
+        // transfer tokens
+        msg!("Transfer tokens");
+        perpetuals.transfer_tokens_from_user(
+            ctx.accounts.funding_account.to_account_info(),
+            ctx.accounts.order_token_account.to_account_info(),
+            ctx.accounts.owner.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            params.collateral,
+        )?;
+    }
+
+    emit!(PlaceOrderEvent {
+        owner: order.owner,
+        pool: order.pool,
+        custody: order.custody,
+        collateral_custody: order.collateral_custody,
+        order: order.key(),
+        order_id: order.order_id,
+        position_id: order.position_id,
+        order_type: order.order_type,
+        side: order.side,
+        trigger_price: order.trigger_price,
+        size: order.size,
+        collateral_amount: order.collateral_amount,
+    });
+
+    Ok(())
+}
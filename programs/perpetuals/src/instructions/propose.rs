@@ -0,0 +1,164 @@
+//! Propose instruction handler
+
+use {
+    crate::{
+        constants::{PERPETUALS_SEED, PROPOSAL_SEED},
+        error::PerpetualsError,
+        events::ProposeEvent,
+        instructions::{SetMarketStatusParams, SetPermissionsParams, SetPoolPermissionsParams},
+        math,
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, Multisig, Role},
+            perpetuals::Perpetuals,
+            pool::Pool,
+            proposal::{Proposal, ProposalStatus},
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+#[instruction(params: ProposeParams)]
+pub struct Propose<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    // proposals are approved by the role that owns the proposed instruction
+    #[account(
+        mut,
+        seeds = [b"multisig", &[params.role as u8]],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    #[account(
+        seeds = [PERPETUALS_SEED.as_bytes()],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    // instruction accounts and data are approved in the same multisig round
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = Proposal::LEN + params.instruction_data.len(),
+        seeds = [PROPOSAL_SEED.as_bytes(),
+                 &params.proposal_id.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+
+    system_program: Program<'info, System>,
+    // remaining accounts:
+    //   accounts of the proposed instruction in the order expected by execute_proposal
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ProposeParams {
+    pub role: Role,
+    pub proposal_id: u64,
+    // see Multisig::get_instruction_data
+    pub instruction_data: Vec<u8>,
+}
+
+pub fn propose<'info>(
+    ctx: Context<'_, '_, '_, 'info, Propose<'info>>,
+    params: &ProposeParams,
+) -> Result<u8> {
+    // validate signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::Propose, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // validate inputs
+    let proposal = ctx.accounts.proposal.as_mut();
+    require!(
+        proposal.instruction_data.is_empty(),
+        PerpetualsError::InvalidProposalState
+    );
+
+    let instruction_type = Proposal::get_instruction_type(&params.instruction_data)?;
+    require!(
+        Proposal::get_role(instruction_type)? == params.role,
+        PerpetualsError::MultisigAccountNotAuthorized
+    );
+
+    // risk-reducing changes are exempt from the delay
+    let perpetuals = ctx.accounts.perpetuals.as_ref();
+    let curtime = perpetuals.get_time()?;
+    let params_data = &params.instruction_data[..params.instruction_data.len() - 1];
+    let risk_reducing = is_risk_reducing_proposal(
+        perpetuals,
+        instruction_type,
+        params_data,
+        ctx.remaining_accounts,
+    )?;
+    let eta = if risk_reducing {
+        curtime
+    } else {
+        math::checked_add(curtime, perpetuals.proposal_delay)?
+    };
+
+    // record proposal
+    proposal.proposal_id = params.proposal_id;
+    proposal.instruction_hash =
+        Multisig::get_instruction_hash(ctx.remaining_accounts, &params.instruction_data);
+    proposal.instruction_data = params.instruction_data.clone();
+    proposal.eta = eta;
+    proposal.risk_reducing = risk_reducing;
+    proposal.status = ProposalStatus::Queued;
+    proposal.bump = *ctx
+        .bumps
+        .get("proposal")
+        .ok_or(ProgramError::InvalidSeeds)?;
+
+    emit!(ProposeEvent {
+        proposal: ctx.accounts.proposal.key(),
+        proposal_id: params.proposal_id,
+        role: params.role,
+        instruction_type,
+        instruction_hash: ctx.accounts.proposal.instruction_hash,
+        eta,
+    });
+
+    Ok(0)
+}
+
+/// Returns true if the proposed instruction only reduces risk given the current state,
+/// also called by execute_proposal to check that the exemption from the delay still holds.
+pub fn is_risk_reducing_proposal(
+    perpetuals: &Perpetuals,
+    instruction_type: u8,
+    params_data: &[u8],
+    accounts: &[AccountInfo],
+) -> Result<bool> {
+    let risk_reducing = if instruction_type == AdminInstruction::SetPermissions as u8 {
+        SetPermissionsParams::try_from_slice(params_data)?.is_risk_reducing(&perpetuals.permissions)
+    } else if instruction_type == AdminInstruction::SetPoolPermissions as u8 {
+        let pool =
+            Account::<Pool>::try_from(accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?)?;
+        SetPoolPermissionsParams::try_from_slice(params_data)?
+            .permissions
+            .is_risk_reducing(&pool.permissions)
+    } else if instruction_type == AdminInstruction::SetMarketStatus as u8 {
+        let custody = Account::<Custody>::try_from(
+            accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?,
+        )?;
+        SetMarketStatusParams::try_from_slice(params_data)?.market_status >= custody.market_status
+    } else {
+        false
+    };
+    Ok(risk_reducing)
+}
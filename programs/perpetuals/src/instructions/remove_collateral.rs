@@ -4,6 +4,7 @@ use {
     crate::{
         constants::{CUSTODY_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED, POSITION_SEED},
         error::PerpetualsError,
+        events::RemoveCollateralEvent,
         math,
         oracle::OraclePrice,
         state::{
@@ -59,7 +60,8 @@ pub struct RemoveCollateral<'info> {
                  owner.key().as_ref(),
                  pool.key().as_ref(),
                  custody.key().as_ref(),
-                 &[position.side as u8]],
+                 &[position.side as u8],
+                 &position.position_id.to_le_bytes()],
         bump = position.bump
     )]
     pub position: Box<Account<'info, Position>>,
@@ -116,9 +118,14 @@ pub fn remove_collateral(
     let collateral_custody = ctx.accounts.collateral_custody.as_mut();
     require!(
         perpetuals.permissions.allow_collateral_withdrawal
+            && ctx.accounts.pool.permissions.allow_collateral_withdrawal
             && custody.permissions.allow_collateral_withdrawal,
         PerpetualsError::InstructionNotAllowed
     );
+    require!(
+        custody.market_status.allow_collateral_withdrawal(),
+        PerpetualsError::MarketStatusRestricted
+    );
 
     // validate inputs
     msg!("Validate inputs");
@@ -126,6 +133,12 @@ pub fn remove_collateral(
     if params.collateral_usd == 0 || params.collateral_usd >= position.collateral_usd {
         return Err(ProgramError::InvalidArgument.into());
     }
+    // collateral of linked positions is managed through the margin account
+    require_keys_eq!(
+        position.margin_account,
+        Pubkey::default(),
+        PerpetualsError::PositionLinkedToMarginAccount
+    );
     let pool = ctx.accounts.pool.as_mut();
 
     // compute position price
@@ -136,6 +149,8 @@ pub fn remove_collateral(
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -143,6 +158,8 @@ pub fn remove_collateral(
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         custody.pricing.use_ema,
     )?;
 
@@ -152,6 +169,8 @@ pub fn remove_collateral(
             .to_account_info(),
         &clock,
         collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -161,6 +180,8 @@ pub fn remove_collateral(
             .to_account_info(),
         &clock,
         collateral_custody.oracle,
+        &collateral_custody.oracle_params,
+        collateral_custody.get_secondary_oracle(ctx.remaining_accounts),
         collateral_custody.pricing.use_ema,
     )?;
 
@@ -200,6 +221,7 @@ pub fn remove_collateral(
         )?,
         PerpetualsError::MaxLeverage
     );
+    position.liquidatable_since = 0;
 
     // transfer tokens
     msg!("Transfer tokens");
@@ -220,8 +242,24 @@ pub fn remove_collateral(
 
     // if custody and collateral_custody accounts are the same, ensure that data is in sync
     if position.side == Side::Long && !custody.is_virtual {
+        collateral_custody.remove_position_collateral(position.side, params.collateral_usd);
         *custody = collateral_custody.clone();
+    } else {
+        custody.remove_position_collateral(position.side, params.collateral_usd);
     }
 
+    emit!(RemoveCollateralEvent {
+        owner: position.owner,
+        pool: pool.key(),
+        custody: custody.key(),
+        collateral_custody: collateral_custody.key(),
+        position: position.key(),
+        collateral_amount: collateral,
+        collateral_usd: params.collateral_usd,
+        position_collateral_amount: position.collateral_amount,
+        position_collateral_usd: position.collateral_usd,
+        update_time: position.update_time,
+    });
+
     Ok(())
 }
@@ -4,9 +4,10 @@ use {
     crate::{
         constants::{CUSTODY_SEED, CUSTODY_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED},
         error::PerpetualsError,
+        events::RemoveCustodyEvent,
         state::{
             custody::Custody,
-            multisig::{AdminInstruction, Multisig},
+            multisig::{AdminInstruction, Multisig, Role},
             perpetuals::Perpetuals,
             pool::{Pool, TokenRatios},
         },
@@ -22,7 +23,7 @@ pub struct RemoveCustody<'info> {
 
     #[account(
         mut,
-        seeds = [b"multisig"],
+        seeds = [b"multisig", &[Role::PoolAdmin as u8]],
         bump = multisig.load()?.bump
     )]
     pub multisig: AccountLoader<'info, Multisig>,
@@ -133,5 +134,10 @@ pub fn remove_custody<'info>(
         ]],
     )?;
 
+    emit!(RemoveCustodyEvent {
+        pool: ctx.accounts.pool.key(),
+        custody: ctx.accounts.custody.key(),
+    });
+
     Ok(0)
 }
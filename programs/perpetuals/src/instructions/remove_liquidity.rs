@@ -7,6 +7,7 @@ use {
             POOL_SEED,
         },
         error::PerpetualsError,
+        events::RemoveLiquidityEvent,
         helpers::AccountMap,
         math,
         oracle::OraclePrice,
@@ -116,6 +117,7 @@ pub fn remove_liquidity(
     let custody = ctx.accounts.custody.as_mut();
     require!(
         perpetuals.permissions.allow_remove_liquidity
+            && ctx.accounts.pool.permissions.allow_remove_liquidity
             && custody.permissions.allow_remove_liquidity
             && !custody.is_virtual,
         PerpetualsError::InstructionNotAllowed
@@ -142,6 +144,8 @@ pub fn remove_liquidity(
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -149,6 +153,8 @@ pub fn remove_liquidity(
         &ctx.accounts.custody_oracle_account.to_account_info(),
         &clock,
         custody.oracle,
+        &custody.oracle_params,
+        custody.get_secondary_oracle(ctx.remaining_accounts),
         custody.pricing.use_ema,
     )?;
 
@@ -239,5 +245,16 @@ pub fn remove_liquidity(
     custody.exit(&crate::ID)?;
     pool.aum_usd = pool.get_assets_under_management_usd(AumCalcMode::EMA, &accounts_map, &clock)?;
 
+    emit!(RemoveLiquidityEvent {
+        owner: ctx.accounts.owner.key(),
+        pool: pool.key(),
+        custody: custody.key(),
+        lp_amount: params.lp_amount_in,
+        fee_amount,
+        amount_out: transfer_amount,
+        custody_owned: custody.assets.owned,
+        pool_aum_usd: pool.aum_usd,
+    });
+
     Ok(())
 }
@@ -0,0 +1,173 @@
+//! RemoveMarginCollateral instruction handler
+
+use {
+    crate::{
+        constants::{
+            CUSTODY_SEED, CUSTODY_TOKEN_ACCOUNT_SEED, MARGIN_ACCOUNT_SEED, PERPETUALS_SEED,
+            POOL_SEED,
+        },
+        error::PerpetualsError,
+        events::RemoveMarginCollateralEvent,
+        helpers::AccountMap,
+        math,
+        state::{
+            custody::Custody, margin_account::MarginAccount, perpetuals::Perpetuals, pool::Pool,
+            position::Position,
+        },
+    },
+    anchor_lang::prelude::*,
+    anchor_spl::token::{Token, TokenAccount},
+    solana_program::program_error::ProgramError,
+};
+
+#[derive(Accounts)]
+pub struct RemoveMarginCollateral<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = receiving_account.mint == custody.mint,
+        has_one = owner
+    )]
+    pub receiving_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
+    )]
+    pub transfer_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [PERPETUALS_SEED.as_bytes()],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [POOL_SEED.as_bytes(),
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        has_one = owner,
+        seeds = [MARGIN_ACCOUNT_SEED.as_bytes(),
+                 owner.key().as_ref(),
+                 pool.key().as_ref()],
+        bump = margin_account.bump
+    )]
+    pub margin_account: Box<Account<'info, MarginAccount>>,
+
+    #[account(
+        mut,
+        seeds = [CUSTODY_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    #[account(
+        mut,
+        seeds = [CUSTODY_TOKEN_ACCOUNT_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.token_account_bump
+    )]
+    pub custody_token_account: Box<Account<'info, TokenAccount>>,
+
+    token_program: Program<'info, Token>,
+    // remaining accounts:
+    //   linked positions, their custodies and collateral custodies, custodies of the
+    //   margin collateral and oracle accounts of all of these custodies (read-only, unsigned)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RemoveMarginCollateralParams {
+    pub amount: u64,
+}
+
+pub fn remove_margin_collateral(
+    ctx: Context<RemoveMarginCollateral>,
+    params: &RemoveMarginCollateralParams,
+) -> Result<()> {
+    // check permissions
+    msg!("Check permissions");
+    let perpetuals = ctx.accounts.perpetuals.as_mut();
+    let custody = ctx.accounts.custody.as_mut();
+    require!(
+        perpetuals.permissions.allow_collateral_withdrawal
+            && ctx.accounts.pool.permissions.allow_collateral_withdrawal
+            && custody.permissions.allow_collateral_withdrawal,
+        PerpetualsError::InstructionNotAllowed
+    );
+
+    // validate inputs
+    msg!("Validate inputs");
+    if params.amount == 0 {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+
+    // update margin account
+    msg!("Update margin account");
+    let margin_account_key = ctx.accounts.margin_account.key();
+    let margin_account = ctx.accounts.margin_account.as_mut();
+    margin_account.remove_collateral(&custody.key(), params.amount)?;
+
+    // check margin account risk
+    msg!("Check margin account risks");
+    let curtime = perpetuals.get_time()?;
+    let clock = Clock::get()?;
+    let account_map = AccountMap::from_remaining_accounts(ctx.remaining_accounts);
+    margin_account.prune_positions(&margin_account_key, &account_map);
+
+    // margin collateral stays locked while any linked market is restricted
+    for position_key in margin_account.positions.iter() {
+        let position = Account::<Position>::try_from(account_map.get_account(position_key)?)?;
+        let position_custody =
+            Account::<Custody>::try_from(account_map.get_account(&position.custody)?)?;
+        require!(
+            position_custody.market_status.allow_collateral_withdrawal(),
+            PerpetualsError::MarketStatusRestricted
+        );
+    }
+    require!(
+        ctx.accounts.pool.check_margin_account_health(
+            &margin_account_key,
+            margin_account,
+            &account_map,
+            &clock,
+            curtime,
+        )?,
+        PerpetualsError::MaxLeverage
+    );
+
+    // transfer tokens
+    msg!("Transfer tokens");
+    perpetuals.transfer_tokens(
+        ctx.accounts.custody_token_account.to_account_info(),
+        ctx.accounts.receiving_account.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        params.amount,
+    )?;
+
+    // update custody stats
+    msg!("Update custody stats");
+    custody.assets.collateral = math::checked_sub(custody.assets.collateral, params.amount)?;
+
+    emit!(RemoveMarginCollateralEvent {
+        owner: margin_account.owner,
+        pool: margin_account.pool,
+        margin_account: margin_account_key,
+        custody: custody.key(),
+        amount: params.amount,
+        margin_amount: margin_account.get_collateral_amount(&custody.key()),
+    });
+
+    Ok(())
+}
@@ -4,8 +4,9 @@ use {
     crate::{
         constants::{PERPETUALS_SEED, POOL_SEED},
         error::PerpetualsError,
+        events::RemovePoolEvent,
         state::{
-            multisig::{AdminInstruction, Multisig},
+            multisig::{AdminInstruction, Multisig, Role},
             perpetuals::Perpetuals,
             pool::Pool,
         },
@@ -20,7 +21,7 @@ pub struct RemovePool<'info> {
 
     #[account(
         mut,
-        seeds = [b"multisig"],
+        seeds = [b"multisig", &[Role::PoolAdmin as u8]],
         bump = multisig.load()?.bump
     )]
     pub multisig: AccountLoader<'info, Multisig>,
@@ -87,5 +88,9 @@ pub fn remove_pool<'info>(
     let perpetuals = ctx.accounts.perpetuals.as_mut();
     perpetuals.pools -= 1;
 
+    emit!(RemovePoolEvent {
+        pool: ctx.accounts.pool.key(),
+    });
+
     Ok(0)
 }
@@ -0,0 +1,67 @@
+//! RevokeRole instruction handler
+
+use {
+    crate::{
+        events::RevokeRoleEvent,
+        state::multisig::{AdminInstruction, Multisig, Role},
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+#[instruction(params: RevokeRoleParams)]
+pub struct RevokeRole<'info> {
+    #[account()]
+    pub admin: Signer<'info>,
+
+    // roles are managed by their own members
+    #[account(
+        mut,
+        seeds = [b"multisig", &[params.role as u8]],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: member of the role to remove
+    #[account()]
+    pub member: AccountInfo<'info>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RevokeRoleParams {
+    pub role: Role,
+    // number of signatures required by the role afterwards
+    pub min_signatures: u8,
+}
+
+pub fn revoke_role<'info>(
+    ctx: Context<'_, '_, '_, 'info, RevokeRole<'info>>,
+    params: &RevokeRoleParams,
+) -> Result<u8> {
+    // validate signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::RevokeRole, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // remove role member
+    multisig.remove_signer(ctx.accounts.member.key, params.min_signatures)?;
+
+    emit!(RevokeRoleEvent {
+        role: params.role,
+        member: ctx.accounts.member.key(),
+        min_signatures: params.min_signatures,
+    });
+
+    Ok(0)
+}
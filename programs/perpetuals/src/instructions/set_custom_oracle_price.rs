@@ -0,0 +1,124 @@
+//! SetCustomOraclePrice instruction handler
+
+use {
+    crate::{
+        constants::{CUSTODY_SEED, CUSTOM_ORACLE_SEED, PERPETUALS_SEED, POOL_SEED},
+        error::PerpetualsError,
+        events::SetCustomOraclePriceEvent,
+        state::{
+            custody::{Custody, Oracle},
+            multisig::{AdminInstruction, Multisig, Role},
+            oracle::CustomOracle,
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct SetCustomOraclePrice<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig", &[Role::OracleAuthority as u8]],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    #[account(
+        seeds = [PERPETUALS_SEED.as_bytes()],
+        bump = perpetuals.perpetuals_bump
+    )]
+    pub perpetuals: Box<Account<'info, Perpetuals>>,
+
+    #[account(
+        seeds = [POOL_SEED.as_bytes(),
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        seeds = [CUSTODY_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump,
+        constraint = custody.oracle == Oracle::Custom(oracle_account.key()) @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = CustomOracle::LEN,
+        seeds = [CUSTOM_ORACLE_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump
+    )]
+    pub oracle_account: Box<Account<'info, CustomOracle>>,
+
+    system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone)]
+pub struct SetCustomOraclePriceParams {
+    pub price: u64,
+    pub expo: i32,
+    pub conf: u64,
+    pub ema: u64,
+    pub publish_time: i64,
+}
+
+pub fn set_custom_oracle_price<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetCustomOraclePrice<'info>>,
+    params: &SetCustomOraclePriceParams,
+) -> Result<u8> {
+    // validate signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetCustomOraclePrice, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // validate inputs
+    if params.price == 0 || params.ema == 0 {
+        return err!(PerpetualsError::InvalidOraclePrice);
+    }
+    CustomOracle::validate_publish_time(params.publish_time, Clock::get()?.unix_timestamp)?;
+
+    // update oracle data
+    ctx.accounts.oracle_account.set(
+        params.price,
+        params.expo,
+        params.conf,
+        params.ema,
+        params.publish_time,
+    );
+
+    emit!(SetCustomOraclePriceEvent {
+        pool: ctx.accounts.pool.key(),
+        custody: ctx.accounts.custody.key(),
+        oracle_account: ctx.accounts.oracle_account.key(),
+        price: params.price,
+        expo: params.expo,
+        conf: params.conf,
+        ema: params.ema,
+        publish_time: params.publish_time,
+        permissionless: false,
+    });
+
+    Ok(0)
+}
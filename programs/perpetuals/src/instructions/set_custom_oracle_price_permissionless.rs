@@ -0,0 +1,131 @@
+//! SetCustomOraclePricePermissionless instruction handler
+
+use {
+    crate::{
+        constants::{CUSTODY_SEED, CUSTOM_ORACLE_SEED, POOL_SEED},
+        error::PerpetualsError,
+        events::SetCustomOraclePriceEvent,
+        helpers::validate_ed25519_signature_instruction,
+        state::{
+            custody::{Custody, Oracle},
+            oracle::CustomOracle,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+    solana_program::sysvar::instructions::{
+        load_current_index_checked, load_instruction_at_checked,
+    },
+};
+
+#[derive(Accounts)]
+pub struct SetCustomOraclePricePermissionless<'info> {
+    #[account(
+        seeds = [POOL_SEED.as_bytes(),
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        seeds = [CUSTODY_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump,
+        constraint = custody.oracle == Oracle::Custom(oracle_account.key()) @ PerpetualsError::InvalidOracleAccount
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+
+    #[account(
+        mut,
+        seeds = [CUSTOM_ORACLE_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump
+    )]
+    pub oracle_account: Box<Account<'info, CustomOracle>>,
+
+    /// CHECK: instructions sysvar, used to read the Ed25519 signature instruction
+    #[account(
+        address = solana_program::sysvar::instructions::ID
+    )]
+    pub ix_sysvar: AccountInfo<'info>,
+}
+
+// Serialized params are the message signed by the custody's oracle authority
+#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone)]
+pub struct SetCustomOraclePricePermissionlessParams {
+    pub custody_account: Pubkey,
+    pub price: u64,
+    pub expo: i32,
+    pub conf: u64,
+    pub ema: u64,
+    pub publish_time: i64,
+}
+
+pub fn set_custom_oracle_price_permissionless(
+    ctx: Context<SetCustomOraclePricePermissionless>,
+    params: &SetCustomOraclePricePermissionlessParams,
+) -> Result<()> {
+    // validate inputs
+    msg!("Validate inputs");
+    let custody = ctx.accounts.custody.as_ref();
+    require_keys_eq!(
+        params.custody_account,
+        custody.key(),
+        PerpetualsError::PermissionlessOracleMessageMismatch
+    );
+    require_keys_neq!(
+        custody.oracle_params.oracle_authority,
+        Pubkey::default(),
+        PerpetualsError::InstructionNotAllowed
+    );
+    if params.price == 0 || params.ema == 0 {
+        return err!(PerpetualsError::InvalidOraclePrice);
+    }
+    require_gt!(
+        params.publish_time,
+        ctx.accounts.oracle_account.publish_time,
+        PerpetualsError::StaleOraclePrice
+    );
+    CustomOracle::validate_publish_time(params.publish_time, Clock::get()?.unix_timestamp)?;
+
+    // validate signature, the Ed25519 program instruction must precede this one
+    msg!("Validate signature");
+    let ix_sysvar = &ctx.accounts.ix_sysvar;
+    let current_index = load_current_index_checked(ix_sysvar)?;
+    if current_index == 0 {
+        return err!(PerpetualsError::PermissionlessOracleMissingSignature);
+    }
+    let signature_ix = load_instruction_at_checked((current_index - 1) as usize, ix_sysvar)?;
+
+    validate_ed25519_signature_instruction(
+        &signature_ix,
+        &custody.oracle_params.oracle_authority,
+        &params.try_to_vec()?,
+    )?;
+
+    // update oracle data
+    msg!("Update oracle price");
+    ctx.accounts.oracle_account.set(
+        params.price,
+        params.expo,
+        params.conf,
+        params.ema,
+        params.publish_time,
+    );
+
+    emit!(SetCustomOraclePriceEvent {
+        pool: ctx.accounts.pool.key(),
+        custody: ctx.accounts.custody.key(),
+        oracle_account: ctx.accounts.oracle_account.key(),
+        price: params.price,
+        expo: params.expo,
+        conf: params.conf,
+        ema: params.ema,
+        publish_time: params.publish_time,
+        permissionless: true,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! SetMarketStatus instruction handler
+
+use {
+    crate::{
+        constants::{CUSTODY_SEED, POOL_SEED},
+        error::PerpetualsError,
+        events::SetMarketStatusEvent,
+        state::{
+            custody::{Custody, MarketStatus},
+            multisig::{AdminInstruction, Multisig, Role},
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct SetMarketStatus<'info> {
+    #[account()]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig", &[Role::Pauser as u8]],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    #[account(
+        seeds = [POOL_SEED.as_bytes(),
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        seeds = [CUSTODY_SEED.as_bytes(),
+                 pool.key().as_ref(),
+                 custody.mint.as_ref()],
+        bump = custody.bump
+    )]
+    pub custody: Box<Account<'info, Custody>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetMarketStatusParams {
+    pub market_status: MarketStatus,
+}
+
+pub fn set_market_status<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetMarketStatus<'info>>,
+    params: &SetMarketStatusParams,
+) -> Result<u8> {
+    // validate signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetMarketStatus, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // markets can be restricted right away, lifting restrictions requires a proposal
+    let custody = ctx.accounts.custody.as_mut();
+    require!(
+        params.market_status >= custody.market_status,
+        PerpetualsError::ProposalRequired
+    );
+
+    apply_market_status(custody, params)?;
+
+    Ok(0)
+}
+
+/// Updates custody market status, also called by execute_proposal for SetMarketStatus proposals.
+/// Proposal accounts: custody (writable).
+pub fn apply_market_status(
+    custody: &mut Account<Custody>,
+    params: &SetMarketStatusParams,
+) -> Result<()> {
+    custody.market_status = params.market_status;
+
+    emit!(SetMarketStatusEvent {
+        pool: custody.pool,
+        custody: custody.key(),
+        market_status: custody.market_status,
+    });
+
+    Ok(())
+}
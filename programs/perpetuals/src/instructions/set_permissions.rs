@@ -4,9 +4,10 @@ use {
     crate::{
         constants::PERPETUALS_SEED,
         error::PerpetualsError,
+        events::SetPermissionsEvent,
         state::{
-            admin::{Admin, Permissions},
-            perpetuals::Perpetuals,
+            multisig::{AdminInstruction, Multisig, Role},
+            perpetuals::{Permissions, Perpetuals},
         },
     },
     anchor_lang::prelude::*,
@@ -15,12 +16,14 @@ use {
 #[derive(Accounts)]
 pub struct SetPermissions<'info> {
     #[account()]
-    pub signer: Signer<'info>,
+    pub admin: Signer<'info>,
 
     #[account(
-        constraint = admin.has_permissions(Permissions::ChangePermissions)
+        mut,
+        seeds = [b"multisig", &[Role::Pauser as u8]],
+        bump = multisig.load()?.bump
     )]
-    pub admin: Account<'info, Admin>,
+    pub multisig: AccountLoader<'info, Multisig>,
 
     #[account(
         mut,
@@ -44,12 +47,58 @@ pub struct SetPermissionsParams {
     pub allow_size_change: bool,
 }
 
+impl SetPermissionsParams {
+    /// Returns true if the new permissions don't allow anything that is currently disallowed.
+    /// Such changes take effect immediately, others go through a timelocked proposal.
+    pub fn is_risk_reducing(&self, permissions: &Permissions) -> bool {
+        Permissions {
+            allow_swap: self.allow_swap,
+            allow_add_liquidity: self.allow_add_liquidity,
+            allow_remove_liquidity: self.allow_remove_liquidity,
+            allow_open_position: self.allow_open_position,
+            allow_close_position: self.allow_close_position,
+            allow_pnl_withdrawal: self.allow_pnl_withdrawal,
+            allow_collateral_withdrawal: self.allow_collateral_withdrawal,
+            allow_size_change: self.allow_size_change,
+        }
+        .is_risk_reducing(permissions)
+    }
+}
+
 pub fn set_permissions<'info>(
     ctx: Context<'_, '_, '_, 'info, SetPermissions<'info>>,
     params: &SetPermissionsParams,
 ) -> Result<u8> {
-    // update permissions
+    // validate signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetPermissions, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // permissions can be lowered right away, anything else requires a proposal
     let perpetuals = ctx.accounts.perpetuals.as_mut();
+    require!(
+        params.is_risk_reducing(&perpetuals.permissions),
+        PerpetualsError::ProposalRequired
+    );
+
+    apply_permissions(perpetuals, params)?;
+
+    Ok(0)
+}
+
+/// Updates protocol permissions, also called by execute_proposal for SetPermissions proposals
+pub fn apply_permissions(perpetuals: &mut Perpetuals, params: &SetPermissionsParams) -> Result<()> {
     perpetuals.permissions.allow_swap = params.allow_swap;
     perpetuals.permissions.allow_add_liquidity = params.allow_add_liquidity;
     perpetuals.permissions.allow_remove_liquidity = params.allow_remove_liquidity;
@@ -60,8 +109,12 @@ pub fn set_permissions<'info>(
     perpetuals.permissions.allow_size_change = params.allow_size_change;
 
     if !perpetuals.validate() {
-        err!(PerpetualsError::InvalidPerpetualsConfig)
-    } else {
-        Ok(0)
+        return err!(PerpetualsError::InvalidPerpetualsConfig);
     }
+
+    emit!(SetPermissionsEvent {
+        permissions: perpetuals.permissions,
+    });
+
+    Ok(())
 }
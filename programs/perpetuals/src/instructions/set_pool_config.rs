@@ -0,0 +1,29 @@
+//! SetPoolConfig proposal handler
+
+use {
+    crate::{error::PerpetualsError, events::SetPoolConfigEvent, state::pool::Pool},
+    anchor_lang::prelude::*,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SetPoolConfigParams {
+    pub max_oi_usd: u64,
+}
+
+/// Applies a SetPoolConfig proposal, called by execute_proposal once the timelock has expired.
+/// Proposal accounts: pool (writable).
+pub fn set_pool_config(pool: &mut Account<Pool>, params: &SetPoolConfigParams) -> Result<()> {
+    // update pool data
+    pool.max_oi_usd = params.max_oi_usd;
+
+    if !pool.validate() {
+        return err!(PerpetualsError::InvalidPoolConfig);
+    }
+
+    emit!(SetPoolConfigEvent {
+        pool: pool.key(),
+        max_oi_usd: pool.max_oi_usd,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+//! SetPoolPermissions instruction handler
+
+use {
+    crate::{
+        constants::POOL_SEED,
+        error::PerpetualsError,
+        events::SetPoolPermissionsEvent,
+        state::{
+            multisig::{AdminInstruction, Multisig, Role},
+            perpetuals::Permissions,
+            pool::Pool,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Accounts)]
+pub struct SetPoolPermissions<'info> {
+    #[account()]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig", &[Role::Pauser as u8]],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    #[account(
+        mut,
+        seeds = [POOL_SEED.as_bytes(),
+                 pool.name.as_bytes()],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetPoolPermissionsParams {
+    pub permissions: Permissions,
+}
+
+pub fn set_pool_permissions<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetPoolPermissions<'info>>,
+    params: &SetPoolPermissionsParams,
+) -> Result<u8> {
+    // validate signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::SetPoolPermissions, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // permissions can be lowered right away, anything else requires a proposal
+    let pool = ctx.accounts.pool.as_mut();
+    require!(
+        params.permissions.is_risk_reducing(&pool.permissions),
+        PerpetualsError::ProposalRequired
+    );
+
+    apply_pool_permissions(pool, params)?;
+
+    Ok(0)
+}
+
+/// Updates pool permissions, also called by execute_proposal for SetPoolPermissions proposals.
+/// Proposal accounts: pool (writable).
+pub fn apply_pool_permissions(
+    pool: &mut Account<Pool>,
+    params: &SetPoolPermissionsParams,
+) -> Result<()> {
+    pool.permissions = params.permissions;
+
+    emit!(SetPoolPermissionsEvent {
+        pool: pool.key(),
+        permissions: pool.permissions,
+    });
+
+    Ok(())
+}
@@ -5,7 +5,7 @@ use {
         constants::PERPETUALS_SEED,
         error::PerpetualsError,
         state::{
-            multisig::{AdminInstruction, Multisig},
+            multisig::{AdminInstruction, Multisig, Role},
             perpetuals::Perpetuals,
         },
     },
@@ -19,7 +19,7 @@ pub struct SetTestTime<'info> {
 
     #[account(
         mut,
-        seeds = [b"multisig"],
+        seeds = [b"multisig", &[Role::PoolAdmin as u8]],
         bump = multisig.load()?.bump
     )]
     pub multisig: AccountLoader<'info, Multisig>,
@@ -4,7 +4,7 @@ use {
     crate::{
         constants::{CUSTODY_SEED, CUSTODY_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED},
         error::PerpetualsError,
-        math,
+        events::SwapEvent,
         oracle::OraclePrice,
         state::{custody::Custody, perpetuals::Perpetuals, pool::Pool},
     },
@@ -119,6 +119,7 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
     let dispensing_custody = ctx.accounts.dispensing_custody.as_mut();
     require!(
         perpetuals.permissions.allow_swap
+            && ctx.accounts.pool.permissions.allow_swap
             && receiving_custody.permissions.allow_swap
             && dispensing_custody.permissions.allow_swap
             && !receiving_custody.is_virtual
@@ -146,6 +147,8 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
             .to_account_info(),
         &clock,
         receiving_custody.oracle,
+        &receiving_custody.oracle_params,
+        receiving_custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -155,6 +158,8 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
             .to_account_info(),
         &clock,
         receiving_custody.oracle,
+        &receiving_custody.oracle_params,
+        receiving_custody.get_secondary_oracle(ctx.remaining_accounts),
         receiving_custody.pricing.use_ema,
     )?;
 
@@ -164,6 +169,8 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
             .to_account_info(),
         &clock,
         dispensing_custody.oracle,
+        &dispensing_custody.oracle_params,
+        dispensing_custody.get_secondary_oracle(ctx.remaining_accounts),
         false,
     )?;
 
@@ -173,35 +180,27 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
             .to_account_info(),
         &clock,
         dispensing_custody.oracle,
+        &dispensing_custody.oracle_params,
+        dispensing_custody.get_secondary_oracle(ctx.remaining_accounts),
         dispensing_custody.pricing.use_ema,
     )?;
 
     msg!("Compute swap amount");
-    let amount_out = pool.get_swap_amount(
-        &received_token_price,
-        &received_token_ema_price,
-        &dispensed_token_price,
-        &dispensed_token_ema_price,
-        receiving_custody,
-        dispensing_custody,
-        params.amount_in,
-    )?;
-
-    // calculate fee
-    let fees = pool.get_swap_fees(
+    let (no_fee_amount, fees) = pool.settle_swap(
         token_id_in,
         token_id_out,
         params.amount_in,
-        amount_out,
-        receiving_custody,
         &received_token_price,
-        dispensing_custody,
+        &received_token_ema_price,
+        receiving_custody,
         &dispensed_token_price,
+        &dispensed_token_ema_price,
+        dispensing_custody,
+        curtime,
     )?;
     msg!("Collected fees: {} {}", fees.0, fees.1);
 
     // check returned amount
-    let no_fee_amount = math::checked_sub(amount_out, fees.1)?;
     msg!("Amount out: {}", no_fee_amount);
     require_gte!(
         no_fee_amount,
@@ -209,37 +208,6 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
         PerpetualsError::InsufficientAmountReturned
     );
 
-    // check pool constraints
-    msg!("Check pool constraints");
-    let protocol_fee_in = Pool::get_fee_amount(receiving_custody.fees.protocol_share, fees.0)?;
-    let protocol_fee_out = Pool::get_fee_amount(dispensing_custody.fees.protocol_share, fees.1)?;
-    let deposit_amount = math::checked_sub(params.amount_in, protocol_fee_in)?;
-    let withdrawal_amount = math::checked_add(no_fee_amount, protocol_fee_out)?;
-
-    require!(
-        pool.check_token_ratio(
-            token_id_in,
-            deposit_amount,
-            0,
-            receiving_custody,
-            &received_token_price
-        )? && pool.check_token_ratio(
-            token_id_out,
-            0,
-            withdrawal_amount,
-            dispensing_custody,
-            &dispensed_token_price
-        )?,
-        PerpetualsError::TokenRatioOutOfRange
-    );
-    require!(
-        math::checked_sub(
-            dispensing_custody.assets.owned,
-            dispensing_custody.assets.locked
-        )? >= withdrawal_amount,
-        PerpetualsError::CustodyAmountLimit
-    );
-
     // transfer tokens
     msg!("Transfer tokens");
     perpetuals.transfer_tokens_from_user(
@@ -262,41 +230,18 @@ pub fn swap(ctx: Context<Swap>, params: &SwapParams) -> Result<()> {
         no_fee_amount,
     )?;
 
-    // update custody stats
-    msg!("Update custody stats");
-    receiving_custody.volume_stats.swap_usd = receiving_custody.volume_stats.swap_usd.wrapping_add(
-        received_token_price.get_asset_amount_usd(params.amount_in, receiving_custody.decimals)?,
-    );
-
-    receiving_custody.collected_fees.swap_usd =
-        receiving_custody.collected_fees.swap_usd.wrapping_add(
-            received_token_price.get_asset_amount_usd(fees.0, receiving_custody.decimals)?,
-        );
-
-    receiving_custody.assets.owned =
-        math::checked_add(receiving_custody.assets.owned, deposit_amount)?;
-
-    receiving_custody.assets.protocol_fees =
-        math::checked_add(receiving_custody.assets.protocol_fees, protocol_fee_in)?;
-
-    dispensing_custody.collected_fees.swap_usd =
-        dispensing_custody.collected_fees.swap_usd.wrapping_add(
-            dispensed_token_price.get_asset_amount_usd(fees.1, dispensing_custody.decimals)?,
-        );
-
-    dispensing_custody.volume_stats.swap_usd =
-        dispensing_custody.volume_stats.swap_usd.wrapping_add(
-            dispensed_token_price.get_asset_amount_usd(amount_out, dispensing_custody.decimals)?,
-        );
-
-    dispensing_custody.assets.protocol_fees =
-        math::checked_add(dispensing_custody.assets.protocol_fees, protocol_fee_out)?;
-
-    dispensing_custody.assets.owned =
-        math::checked_sub(dispensing_custody.assets.owned, withdrawal_amount)?;
-
-    receiving_custody.update_borrow_rate(curtime)?;
-    dispensing_custody.update_borrow_rate(curtime)?;
+    emit!(SwapEvent {
+        owner: ctx.accounts.owner.key(),
+        pool: pool.key(),
+        receiving_custody: receiving_custody.key(),
+        dispensing_custody: dispensing_custody.key(),
+        amount_in: params.amount_in,
+        fee_in: fees.0,
+        amount_out: no_fee_amount,
+        fee_out: fees.1,
+        receiving_custody_owned: receiving_custody.assets.owned,
+        dispensing_custody_owned: dispensing_custody.assets.owned,
+    });
 
     Ok(())
 }
@@ -0,0 +1,164 @@
+//! UpgradePool instruction handler
+
+use {
+    crate::{
+        constants::POOL_SEED,
+        error::PerpetualsError,
+        events::UpgradePoolEvent,
+        helpers::AccountMap,
+        math,
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, Multisig, Role},
+            perpetuals::{Permissions, Perpetuals},
+            pool::{DeprecatedPool, Pool, TokenRatios},
+        },
+    },
+    anchor_lang::{prelude::*, Discriminator},
+};
+
+#[derive(Accounts)]
+pub struct UpgradePool<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"multisig", &[Role::PoolAdmin as u8]],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: pool account in the deprecated layout, validated in the handler
+    #[account(mut)]
+    pub pool: AccountInfo<'info>,
+
+    system_program: Program<'info, System>,
+    // remaining accounts:
+    //   pool.custodies.len() custody accounts (read-only, unsigned)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UpgradePoolParams {}
+
+pub fn upgrade_pool<'info>(
+    ctx: Context<'_, '_, '_, 'info, UpgradePool<'info>>,
+    params: &UpgradePoolParams,
+) -> Result<u8> {
+    // validate signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::UpgradePool, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
+    // load deprecated pool data, new fields are appended to the old layout
+    // so the old fields are read the same way from upgraded pools
+    msg!("Load deprecated pool");
+    let pool_account = &ctx.accounts.pool;
+    if pool_account.owner != &crate::ID {
+        return Err(ProgramError::IllegalOwner.into());
+    }
+    if pool_account.try_borrow_data()?.get(..8) != Some(&Pool::discriminator()[..]) {
+        return Err(ProgramError::InvalidAccountData.into());
+    }
+    // pools in the current layout carry a version, deprecated pools read as version zero
+    let upgraded = Pool::try_deserialize(&mut &pool_account.try_borrow_data()?[..]);
+    if matches!(upgraded, Ok(pool) if pool.version == Pool::VERSION) {
+        msg!("Error: Pool has already been upgraded");
+        return err!(PerpetualsError::InvalidPoolState);
+    }
+    let deprecated_pool = Account::<DeprecatedPool>::try_from_unchecked(pool_account)?;
+    let expected_pool_key = Pubkey::create_program_address(
+        &[
+            POOL_SEED.as_bytes(),
+            deprecated_pool.name.as_bytes(),
+            &[deprecated_pool.bump],
+        ],
+        &crate::ID,
+    )
+    .map_err(|_| ProgramError::InvalidSeeds)?;
+    require_keys_eq!(
+        pool_account.key(),
+        expected_pool_key,
+        PerpetualsError::InvalidPoolState
+    );
+
+    // seed pool open interest from the custodies, passed as remaining accounts,
+    // the limit stays disabled until it is set with a SetPoolConfig proposal
+    msg!("Compute pool open interest");
+    let account_map = AccountMap::from_remaining_accounts(ctx.remaining_accounts);
+    let mut oi_usd: u64 = 0;
+    for custody in deprecated_pool.custodies.iter() {
+        let custody = Account::<Custody>::try_from(account_map.get_account(custody)?)?;
+        oi_usd = math::checked_add(
+            oi_usd,
+            math::checked_add(
+                custody.trade_stats.oi_long_usd,
+                custody.trade_stats.oi_short_usd,
+            )?,
+        )?;
+    }
+
+    let pool_data = Pool {
+        name: deprecated_pool.name.clone(),
+        custodies: deprecated_pool.custodies.clone(),
+        ratios: deprecated_pool.ratios.clone(),
+        aum_usd: deprecated_pool.aum_usd,
+        bump: deprecated_pool.bump,
+        lp_token_bump: deprecated_pool.lp_token_bump,
+        inception_time: deprecated_pool.inception_time,
+        oi_usd,
+        max_oi_usd: 0,
+        // pools had no permissions of their own, protocol and custody permissions
+        // keep applying, changes go through set_pool_permissions
+        permissions: Permissions {
+            allow_swap: true,
+            allow_add_liquidity: true,
+            allow_remove_liquidity: true,
+            allow_open_position: true,
+            allow_close_position: true,
+            allow_pnl_withdrawal: true,
+            allow_collateral_withdrawal: true,
+            allow_size_change: true,
+        },
+        version: Pool::VERSION,
+    };
+    if !pool_data.validate() {
+        return err!(PerpetualsError::InvalidPoolConfig);
+    }
+
+    // resize pool account, same size as pools created with add_pool and add_custody
+    msg!("Resize pool account");
+    let new_len = Pool::LEN + deprecated_pool.ratios.len() * std::mem::size_of::<TokenRatios>();
+    Perpetuals::realloc(
+        ctx.accounts.admin.to_account_info(),
+        pool_account.clone(),
+        ctx.accounts.system_program.to_account_info(),
+        new_len,
+        false,
+    )?;
+
+    // save new pool data
+    msg!("Re-initialize pool");
+    let mut data = pool_account.try_borrow_mut_data()?;
+    let dst: &mut [u8] = &mut data;
+    let mut cursor = std::io::Cursor::new(dst);
+    pool_data.try_serialize(&mut cursor)?;
+
+    emit!(UpgradePoolEvent {
+        pool: pool_account.key(),
+        permissions: pool_data.permissions,
+    });
+
+    Ok(0)
+}
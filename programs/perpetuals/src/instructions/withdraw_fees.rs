@@ -2,32 +2,37 @@
 
 use {
     crate::{
-        constants::{
-            ADMIN_SEED, CUSTODY_SEED, CUSTODY_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED,
+        constants::{CUSTODY_SEED, CUSTODY_TOKEN_ACCOUNT_SEED, PERPETUALS_SEED, POOL_SEED},
+        events::WithdrawFeesEvent,
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, Multisig, Role},
+            perpetuals::Perpetuals,
+            pool::Pool,
         },
-        state::{admin::Admin, custody::Custody, perpetuals::Perpetuals, pool::Pool},
     },
     anchor_lang::prelude::*,
     anchor_spl::token::{Token, TokenAccount},
 };
 
 #[derive(Accounts)]
-#[instruction(
-    args: WithdrawFeesParams
-)]
 pub struct WithdrawFees<'info> {
     #[account()]
-    pub signer: Signer<'info>,
+    pub admin: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [
-            ADMIN_SEED.as_bytes(),
-            signer.key().as_ref()
-        ],
-        bump = admin.bump,
+        seeds = [b"multisig", &[Role::FeeWithdrawer as u8]],
+        bump = multisig.load()?.bump
+    )]
+    pub multisig: AccountLoader<'info, Multisig>,
+
+    /// CHECK: empty PDA, authority for token accounts
+    #[account(
+        seeds = [b"transfer_authority"],
+        bump = perpetuals.transfer_authority_bump
     )]
-    pub admin: Account<'info, Admin>,
+    pub transfer_authority: AccountInfo<'info>,
 
     #[account(
         seeds = [
@@ -41,7 +46,7 @@ pub struct WithdrawFees<'info> {
         mut,
         seeds = [
             POOL_SEED.as_bytes(),
-           & args.pool_id.to_le_bytes()
+            pool.name.as_bytes()
         ],
         bump = pool.bump
     )]
@@ -80,29 +85,43 @@ pub struct WithdrawFees<'info> {
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
-pub struct WithdrawFeesParams {
-    pub pool_id: u64,
-}
+pub struct WithdrawFeesParams {}
 
 pub fn withdraw_fees<'info>(
     ctx: Context<'_, '_, '_, 'info, WithdrawFees<'info>>,
-    _params: &WithdrawFeesParams,
+    params: &WithdrawFeesParams,
 ) -> Result<u8> {
+    // validate signatures
+    let mut multisig = ctx.accounts.multisig.load_mut()?;
+
+    let signatures_left = multisig.sign_multisig(
+        &ctx.accounts.admin,
+        &Multisig::get_account_infos(&ctx)[1..],
+        &Multisig::get_instruction_data(AdminInstruction::WithdrawFees, params)?,
+    )?;
+    if signatures_left > 0 {
+        msg!(
+            "Instruction has been signed but more signatures are required: {}",
+            signatures_left
+        );
+        return Ok(signatures_left);
+    }
+
     // transfer token fees from the custody to the receiver
-    ctx.accounts.custody.withdraw_fees(
+    ctx.accounts.perpetuals.transfer_tokens(
         ctx.accounts.custody_token_account.to_account_info(),
         ctx.accounts.receiving_token_account.to_account_info(),
-        ctx.accounts.custody.to_account_info(),
+        ctx.accounts.transfer_authority.to_account_info(),
         ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.custody.assets.protocol_fees,
     )?;
 
-    // ctx.accounts.perpetuals.transfer_tokens(
-    //     ctx.accounts.custody_token_account.to_account_info(),
-    //     ctx.accounts.receiving_token_account.to_account_info(),
-    //     ctx.accounts.transfer_authority.to_account_info(),
-    //     ctx.accounts.token_program.to_account_info(),
-    //     custody.assets.protocol_fees,
-    // )?;
+    emit!(WithdrawFeesEvent {
+        pool: ctx.accounts.pool.key(),
+        custody: ctx.accounts.custody.key(),
+        receiving_account: ctx.accounts.receiving_token_account.key(),
+        amount: ctx.accounts.custody.assets.protocol_fees,
+    });
 
     Ok(0)
 }
@@ -3,9 +3,10 @@
 use {
     crate::{
         constants::PERPETUALS_SEED,
+        events::WithdrawSolFeesEvent,
         math,
         state::{
-            multisig::{AdminInstruction, Multisig},
+            multisig::{AdminInstruction, Multisig, Role},
             perpetuals::Perpetuals,
         },
     },
@@ -20,7 +21,7 @@ pub struct WithdrawSolFees<'info> {
 
     #[account(
         mut,
-        seeds = [b"multisig"],
+        seeds = [b"multisig", &[Role::FeeWithdrawer as u8]],
         bump = multisig.load()?.bump
     )]
     pub multisig: AccountLoader<'info, Multisig>,
@@ -101,5 +102,10 @@ pub fn withdraw_sol_fees<'info>(
         params.amount,
     )?;
 
+    emit!(WithdrawSolFeesEvent {
+        receiving_account: ctx.accounts.receiving_account.key(),
+        amount: params.amount,
+    });
+
     Ok(0)
 }
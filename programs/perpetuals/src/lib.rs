@@ -4,6 +4,7 @@
 
 pub mod constants;
 mod error;
+pub mod events;
 pub mod helpers;
 pub mod instructions;
 pub mod math;
@@ -60,11 +61,11 @@ pub mod perpetuals {
         instructions::add_custody(ctx, &params)
     }
 
-    pub fn set_custody_config<'info>(
-        ctx: Context<'_, '_, '_, 'info, SetCustodyConfig<'info>>,
-        params: SetCustodyConfigParams,
+    pub fn set_custom_oracle_price<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetCustomOraclePrice<'info>>,
+        params: SetCustomOraclePriceParams,
     ) -> Result<u8> {
-        instructions::set_custody_config(ctx, &params)
+        instructions::set_custom_oracle_price(ctx, &params)
     }
 
     pub fn set_permissions<'info>(
@@ -74,6 +75,27 @@ pub mod perpetuals {
         instructions::set_permissions(ctx, &params)
     }
 
+    pub fn set_market_status<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetMarketStatus<'info>>,
+        params: SetMarketStatusParams,
+    ) -> Result<u8> {
+        instructions::set_market_status(ctx, &params)
+    }
+
+    pub fn set_pool_permissions<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetPoolPermissions<'info>>,
+        params: SetPoolPermissionsParams,
+    ) -> Result<u8> {
+        instructions::set_pool_permissions(ctx, &params)
+    }
+
+    pub fn upgrade_pool<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpgradePool<'info>>,
+        params: UpgradePoolParams,
+    ) -> Result<u8> {
+        instructions::upgrade_pool(ctx, &params)
+    }
+
     pub fn withdraw_fees<'info>(
         ctx: Context<'_, '_, '_, 'info, WithdrawFees<'info>>,
         params: WithdrawFeesParams,
@@ -88,6 +110,34 @@ pub mod perpetuals {
         instructions::withdraw_sol_fees(ctx, &params)
     }
 
+    pub fn grant_role<'info>(
+        ctx: Context<'_, '_, '_, 'info, GrantRole<'info>>,
+        params: GrantRoleParams,
+    ) -> Result<u8> {
+        instructions::grant_role(ctx, &params)
+    }
+
+    pub fn revoke_role<'info>(
+        ctx: Context<'_, '_, '_, 'info, RevokeRole<'info>>,
+        params: RevokeRoleParams,
+    ) -> Result<u8> {
+        instructions::revoke_role(ctx, &params)
+    }
+
+    pub fn propose<'info>(
+        ctx: Context<'_, '_, '_, 'info, Propose<'info>>,
+        params: ProposeParams,
+    ) -> Result<u8> {
+        instructions::propose(ctx, &params)
+    }
+
+    pub fn cancel_proposal<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelProposal<'info>>,
+        params: CancelProposalParams,
+    ) -> Result<u8> {
+        instructions::cancel_proposal(ctx, &params)
+    }
+
     // test instructions
 
     pub fn set_test_time<'info>(
@@ -119,10 +169,24 @@ pub mod perpetuals {
         instructions::open_position(ctx, &params)
     }
 
+    pub fn open_position_with_swap(
+        ctx: Context<OpenPositionWithSwap>,
+        params: OpenPositionWithSwapParams,
+    ) -> Result<()> {
+        instructions::open_position_with_swap(ctx, &params)
+    }
+
     pub fn add_collateral(ctx: Context<AddCollateral>, params: AddCollateralParams) -> Result<()> {
         instructions::add_collateral(ctx, &params)
     }
 
+    pub fn increase_position(
+        ctx: Context<IncreasePosition>,
+        params: IncreasePositionParams,
+    ) -> Result<()> {
+        instructions::increase_position(ctx, &params)
+    }
+
     pub fn remove_collateral(
         ctx: Context<RemoveCollateral>,
         params: RemoveCollateralParams,
@@ -130,14 +194,86 @@ pub mod perpetuals {
         instructions::remove_collateral(ctx, &params)
     }
 
+    pub fn init_margin_account(
+        ctx: Context<InitMarginAccount>,
+        params: InitMarginAccountParams,
+    ) -> Result<()> {
+        instructions::init_margin_account(ctx, &params)
+    }
+
+    pub fn add_margin_collateral(
+        ctx: Context<AddMarginCollateral>,
+        params: AddMarginCollateralParams,
+    ) -> Result<()> {
+        instructions::add_margin_collateral(ctx, &params)
+    }
+
+    pub fn remove_margin_collateral(
+        ctx: Context<RemoveMarginCollateral>,
+        params: RemoveMarginCollateralParams,
+    ) -> Result<()> {
+        instructions::remove_margin_collateral(ctx, &params)
+    }
+
+    pub fn link_position(ctx: Context<LinkPosition>, params: LinkPositionParams) -> Result<()> {
+        instructions::link_position(ctx, &params)
+    }
+
+    pub fn unlink_position(
+        ctx: Context<UnlinkPosition>,
+        params: UnlinkPositionParams,
+    ) -> Result<()> {
+        instructions::unlink_position(ctx, &params)
+    }
+
     pub fn close_position(ctx: Context<ClosePosition>, params: ClosePositionParams) -> Result<()> {
         instructions::close_position(ctx, &params)
     }
 
+    pub fn place_order(ctx: Context<PlaceOrder>, params: PlaceOrderParams) -> Result<()> {
+        instructions::place_order(ctx, &params)
+    }
+
+    pub fn cancel_order(ctx: Context<CancelOrder>, params: CancelOrderParams) -> Result<()> {
+        instructions::cancel_order(ctx, &params)
+    }
+
+    pub fn execute_order(ctx: Context<ExecuteOrder>, params: ExecuteOrderParams) -> Result<()> {
+        instructions::execute_order(ctx, &params)
+    }
+
+    pub fn execute_proposal<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteProposal<'info>>,
+        params: ExecuteProposalParams,
+    ) -> Result<()> {
+        instructions::execute_proposal(ctx, &params)
+    }
+
     pub fn liquidate(ctx: Context<Liquidate>, params: LiquidateParams) -> Result<()> {
         instructions::liquidate(ctx, &params)
     }
 
+    pub fn flag_liquidatable(
+        ctx: Context<FlagLiquidatable>,
+        params: FlagLiquidatableParams,
+    ) -> Result<()> {
+        instructions::flag_liquidatable(ctx, &params)
+    }
+
+    pub fn auto_deleverage(
+        ctx: Context<AutoDeleverage>,
+        params: AutoDeleverageParams,
+    ) -> Result<()> {
+        instructions::auto_deleverage(ctx, &params)
+    }
+
+    pub fn set_custom_oracle_price_permissionless(
+        ctx: Context<SetCustomOraclePricePermissionless>,
+        params: SetCustomOraclePricePermissionlessParams,
+    ) -> Result<()> {
+        instructions::set_custom_oracle_price_permissionless(ctx, &params)
+    }
+
     pub fn update_pool_aum(ctx: Context<UpdatePoolAum>) -> Result<u128> {
         instructions::update_pool_aum(ctx)
     }
@@ -208,4 +344,18 @@ pub mod perpetuals {
     ) -> Result<u64> {
         instructions::get_lp_token_price(ctx, &params)
     }
+
+    pub fn get_insurance_fund_balance(
+        ctx: Context<GetInsuranceFundBalance>,
+        params: GetInsuranceFundBalanceParams,
+    ) -> Result<u64> {
+        instructions::get_insurance_fund_balance(ctx, &params)
+    }
+
+    pub fn get_insurance_fund_coverage(
+        ctx: Context<GetInsuranceFundCoverage>,
+        params: GetInsuranceFundCoverageParams,
+    ) -> Result<u64> {
+        instructions::get_insurance_fund_coverage(ctx, &params)
+    }
 }
@@ -340,6 +340,19 @@ where
     }
 }
 
+pub fn checked_as_i64<T>(arg: T) -> Result<i64>
+where
+    T: Display + num_traits::ToPrimitive + Clone,
+{
+    let option: Option<i64> = num_traits::NumCast::from(arg.clone());
+    if let Some(res) = option {
+        Ok(res)
+    } else {
+        msg!("Error: Overflow in {} as i64", arg);
+        err!(PerpetualsError::MathOverflow)
+    }
+}
+
 pub fn checked_as_u128<T>(arg: T) -> Result<u128>
 where
     T: Display + num_traits::ToPrimitive + Clone,
@@ -0,0 +1,172 @@
+use {
+    super::OraclePrice,
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::{
+            oracle::{OracleAggregation, OracleParams},
+            perpetuals::Perpetuals,
+        },
+    },
+    anchor_lang::prelude::*,
+};
+
+// Combines primary and secondary oracle reads according to the aggregation policy.
+// Secondary price is None if the custody has no secondary oracle or its account wasn't passed.
+pub fn aggregate_prices(
+    primary_price: Result<OraclePrice>,
+    secondary_price: Option<Result<OraclePrice>>,
+    oracle_params: &OracleParams,
+) -> Result<OraclePrice> {
+    match oracle_params.aggregation {
+        OracleAggregation::PrimaryOnly => primary_price,
+        OracleAggregation::Fallback => match (primary_price, secondary_price) {
+            (Err(err), Some(secondary_price))
+                if err == Error::from(PerpetualsError::StaleOraclePrice) =>
+            {
+                msg!("Primary oracle price is stale, using secondary oracle");
+                secondary_price
+            }
+            (primary_price, _) => primary_price,
+        },
+        OracleAggregation::Median => {
+            let secondary_price = secondary_price.ok_or(PerpetualsError::AccountMapMissingEntry)?;
+            match (primary_price, secondary_price) {
+                (Ok(primary_price), Ok(secondary_price)) => {
+                    get_median_price(&primary_price, &secondary_price)
+                }
+                (Ok(price), Err(_)) | (Err(_), Ok(price)) => Ok(price),
+                (Err(err), Err(_)) => Err(err),
+            }
+        }
+        OracleAggregation::RejectOnDivergence => {
+            let primary_price = primary_price?;
+            let secondary_price =
+                secondary_price.ok_or(PerpetualsError::AccountMapMissingEntry)??;
+            let divergence = get_price_divergence(&primary_price, &secondary_price)?;
+            if divergence > oracle_params.max_divergence as u128 {
+                msg!("Error: Oracle prices diverge by {} BPS", divergence);
+                return err!(PerpetualsError::OraclePriceDivergence);
+            }
+            Ok(primary_price)
+        }
+    }
+}
+
+// Median of two prices is their mean, confidence interval is the widest of the two
+fn get_median_price(
+    primary_price: &OraclePrice,
+    secondary_price: &OraclePrice,
+) -> Result<OraclePrice> {
+    let secondary_price = secondary_price.scale_to_exponent(primary_price.exponent)?;
+    Ok(OraclePrice {
+        price: math::checked_div(
+            math::checked_add(primary_price.price, secondary_price.price)?,
+            2,
+        )?,
+        exponent: primary_price.exponent,
+        conf: std::cmp::max(primary_price.conf, secondary_price.conf),
+    })
+}
+
+// Difference between two prices relative to the lower one, in BPS
+fn get_price_divergence(
+    primary_price: &OraclePrice,
+    secondary_price: &OraclePrice,
+) -> Result<u128> {
+    let secondary_price = secondary_price.scale_to_exponent(primary_price.exponent)?;
+    let min_price = std::cmp::min(primary_price.price, secondary_price.price);
+    if min_price == 0 {
+        return err!(PerpetualsError::InvalidOraclePrice);
+    }
+    let max_price = std::cmp::max(primary_price.price, secondary_price.price);
+    math::checked_div(
+        math::checked_mul(
+            math::checked_sub(max_price, min_price)? as u128,
+            Perpetuals::BPS_POWER,
+        )?,
+        min_price as u128,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_fixture(aggregation: OracleAggregation) -> (OracleParams, OraclePrice, OraclePrice) {
+        let oracle_params = OracleParams {
+            max_price_error: 100,
+            max_price_age_sec: 60,
+            max_open_price_age_sec: 30,
+            aggregation,
+            max_divergence: 100,
+            ..OracleParams::default()
+        };
+        let primary_price = OraclePrice::new(25_000_000, -3);
+        let secondary_price = OraclePrice::new(2_520_000, -2);
+
+        (oracle_params, primary_price, secondary_price)
+    }
+
+    #[test]
+    fn test_aggregate_prices() {
+        // fallback
+        let (oracle_params, primary_price, secondary_price) =
+            get_fixture(OracleAggregation::Fallback);
+        assert_eq!(
+            primary_price,
+            aggregate_prices(Ok(primary_price), Some(Ok(secondary_price)), &oracle_params).unwrap()
+        );
+        assert_eq!(
+            secondary_price,
+            aggregate_prices(
+                err!(PerpetualsError::StaleOraclePrice),
+                Some(Ok(secondary_price)),
+                &oracle_params
+            )
+            .unwrap()
+        );
+        assert!(aggregate_prices(
+            err!(PerpetualsError::InvalidOraclePrice),
+            Some(Ok(secondary_price)),
+            &oracle_params
+        )
+        .is_err());
+        assert!(aggregate_prices(
+            err!(PerpetualsError::StaleOraclePrice),
+            None,
+            &oracle_params
+        )
+        .is_err());
+
+        // median
+        let (oracle_params, primary_price, secondary_price) =
+            get_fixture(OracleAggregation::Median);
+        assert_eq!(
+            OraclePrice::new(25_100_000, -3),
+            aggregate_prices(Ok(primary_price), Some(Ok(secondary_price)), &oracle_params).unwrap()
+        );
+        assert_eq!(
+            secondary_price,
+            aggregate_prices(
+                err!(PerpetualsError::StaleOraclePrice),
+                Some(Ok(secondary_price)),
+                &oracle_params
+            )
+            .unwrap()
+        );
+        assert!(aggregate_prices(Ok(primary_price), None, &oracle_params).is_err());
+
+        // reject on divergence, 80 BPS difference
+        let (mut oracle_params, primary_price, secondary_price) =
+            get_fixture(OracleAggregation::RejectOnDivergence);
+        assert_eq!(
+            primary_price,
+            aggregate_prices(Ok(primary_price), Some(Ok(secondary_price)), &oracle_params).unwrap()
+        );
+        oracle_params.max_divergence = 50;
+        assert!(
+            aggregate_prices(Ok(primary_price), Some(Ok(secondary_price)), &oracle_params).is_err()
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use {
+    super::OraclePrice,
+    crate::{
+        error::PerpetualsError,
+        math,
+        state::oracle::{CustomOracle, OracleParams},
+    },
+    anchor_lang::prelude::*,
+};
+
+#[inline(never)]
+pub fn get_price_from_custom_oracle(
+    oracle_account: &AccountInfo,
+    clock: &Clock,
+    oracle_params: &OracleParams,
+    use_ema: bool,
+) -> Result<OraclePrice> {
+    let oracle = Account::<CustomOracle>::try_from(oracle_account)
+        .map_err(|_| PerpetualsError::PriceError)?;
+
+    let last_update_age_sec = math::checked_sub(clock.unix_timestamp, oracle.publish_time)?;
+    if last_update_age_sec < 0 {
+        msg!("Error: Custom oracle publish time is in the future");
+        return err!(PerpetualsError::OraclePublishTimeInFuture);
+    }
+    if last_update_age_sec > oracle_params.max_price_age_sec as i64 {
+        msg!("Error: Custom oracle price is stale");
+        return err!(PerpetualsError::StaleOraclePrice);
+    }
+
+    let price = if use_ema { oracle.ema } else { oracle.price };
+    if price == 0 {
+        return err!(PerpetualsError::InvalidOraclePrice);
+    }
+
+    let price = OraclePrice {
+        price,
+        exponent: oracle.expo,
+        conf: oracle.conf,
+    };
+    price.validate_confidence(oracle_params.max_price_error)?;
+
+    Ok(price)
+}
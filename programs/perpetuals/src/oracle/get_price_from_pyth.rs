@@ -1,24 +1,15 @@
 use {
     super::OraclePrice,
-    crate::{constants::*, error::PerpetualsError},
+    crate::{error::PerpetualsError, state::oracle::OracleParams},
     anchor_lang::prelude::*,
     pyth_solana_receiver_sdk::price_update::PriceUpdateV2,
 };
 
-pub fn get_prices_from_pyth(
-    oracle_account: &AccountInfo,
-    clock: &Clock,
-) -> Result<(OraclePrice, OraclePrice)> {
-    Ok((
-        get_price_from_pyth(oracle_account, clock, false)?,
-        get_price_from_pyth(oracle_account, clock, true)?,
-    ))
-}
-
 #[inline(never)]
 pub fn get_price_from_pyth(
     oracle_account: &AccountInfo,
     clock: &Clock,
+    oracle_params: &OracleParams,
     use_ema: bool,
 ) -> Result<OraclePrice> {
     let oracle_account_data = oracle_account.try_borrow_mut_data()?;
@@ -26,25 +17,51 @@ pub fn get_price_from_pyth(
     let oracle: PriceUpdateV2 = PriceUpdateV2::deserialize(&mut oracle_account_data.as_ref())
         .map_err(|_| PerpetualsError::PriceError)?;
 
+    let last_update_age_sec = clock
+        .unix_timestamp
+        .saturating_sub(oracle.price_message.publish_time);
+    if last_update_age_sec > oracle_params.max_price_age_sec as i64 {
+        msg!("Error: Pyth oracle price is stale");
+        return err!(PerpetualsError::StaleOraclePrice);
+    }
+
     let price = oracle
-        .get_price_no_older_than(clock, ORACLE_MAXIMUM_AGE, &oracle.price_message.feed_id)
+        .get_price_no_older_than(
+            clock,
+            oracle_params.max_price_age_sec as u64,
+            &oracle.price_message.feed_id,
+        )
         .map_err(|_| PerpetualsError::PriceError)?;
 
     // if above succeeds, ema should work too
     let ema_price = oracle.price_message.ema_price;
+    let ema_conf = oracle.price_message.ema_conf;
 
     let exponent = price.exponent;
 
-    let price = if use_ema {
-        ema_price
-            .try_into()
-            .map_err(|_| PerpetualsError::PriceError)?
+    let (price, conf) = if use_ema {
+        (
+            ema_price
+                .try_into()
+                .map_err(|_| PerpetualsError::PriceError)?,
+            ema_conf,
+        )
     } else {
-        price
-            .price
-            .try_into()
-            .map_err(|_| PerpetualsError::PriceError)?
+        (
+            price
+                .price
+                .try_into()
+                .map_err(|_| PerpetualsError::PriceError)?,
+            price.conf,
+        )
+    };
+
+    let price = OraclePrice {
+        price,
+        exponent,
+        conf,
     };
+    price.validate_confidence(oracle_params.max_price_error)?;
 
-    Ok(OraclePrice { price, exponent })
+    Ok(price)
 }
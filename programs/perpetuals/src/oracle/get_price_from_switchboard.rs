@@ -1,31 +1,56 @@
 use {
     super::OraclePrice,
-    crate::{constants::ORACLE_MAXIMUM_AGE, error::PerpetualsError},
+    crate::{error::PerpetualsError, math, state::oracle::OracleParams},
     anchor_lang::prelude::*,
     std::ops::Mul,
     switchboard_solana::AggregatorAccountData,
 };
 
 #[inline(never)]
-pub fn get_price_from_switchboard(account: &AccountInfo, clock: &Clock) -> Result<OraclePrice> {
+pub fn get_price_from_switchboard(
+    account: &AccountInfo,
+    clock: &Clock,
+    oracle_params: &OracleParams,
+) -> Result<OraclePrice> {
     let account_data = account.try_borrow_mut_data()?;
     let oracle_data = AggregatorAccountData::new_from_bytes(&account_data)
         .map_err(|_| PerpetualsError::PriceError)?;
 
-    let unix_timestamp = clock.unix_timestamp;
-
-    oracle_data
-        .check_staleness(unix_timestamp, ORACLE_MAXIMUM_AGE as i64)
-        .map_err(|_| PerpetualsError::PriceError)?;
+    let last_update_age_sec = clock
+        .unix_timestamp
+        .saturating_sub(oracle_data.latest_confirmed_round.round_open_timestamp);
+    if last_update_age_sec > oracle_params.max_price_age_sec as i64 {
+        msg!("Error: Switchboard oracle price is stale");
+        return err!(PerpetualsError::StaleOraclePrice);
+    }
 
     let result = oracle_data
         .get_result()
         .map_err(|_| PerpetualsError::PriceError)?;
 
-    Ok(OraclePrice {
+    // standard deviation of the latest round is used as the confidence interval,
+    // rescaled to the result's decimal places
+    let std_deviation = oracle_data.latest_confirmed_round.std_deviation;
+    let conf = if std_deviation.scale > result.scale {
+        math::checked_div(
+            std_deviation.mantissa,
+            math::checked_pow(10i128, (std_deviation.scale - result.scale) as usize)?,
+        )?
+    } else {
+        math::checked_mul(
+            std_deviation.mantissa,
+            math::checked_pow(10i128, (result.scale - std_deviation.scale) as usize)?,
+        )?
+    };
+
+    let price = OraclePrice {
         price: result.try_into().map_err(|_| PerpetualsError::PriceError)?,
         // result.scale is always decimal places to move to the **LEFT** to yield the actual value
         // since pyth can return both negative or positive scales, we have to add negative sign here
         exponent: (result.scale as i32).mul(-1),
-    })
+        conf: conf.try_into().map_err(|_| PerpetualsError::PriceError)?,
+    };
+    price.validate_confidence(oracle_params.max_price_error)?;
+
+    Ok(price)
 }
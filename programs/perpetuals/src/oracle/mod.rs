@@ -6,3 +6,9 @@ pub use get_price_from_pyth::*;
 
 pub mod get_price_from_switchboard;
 pub use get_price_from_switchboard::*;
+
+pub mod get_price_from_custom_oracle;
+pub use get_price_from_custom_oracle::*;
+
+pub mod aggregate_prices;
+pub use aggregate_prices::*;
@@ -1,8 +1,8 @@
 use {
-    super::{get_price_from_pyth, get_price_from_switchboard},
     crate::{
+        error::PerpetualsError,
         math,
-        state::{custody::Oracle, perpetuals::Perpetuals},
+        state::{custody::Oracle, oracle::OracleParams, perpetuals::Perpetuals},
     },
     anchor_lang::prelude::*,
 };
@@ -15,6 +15,8 @@ const ORACLE_MAX_PRICE: u64 = (1 << 28) - 1;
 pub struct OraclePrice {
     pub price: u64,
     pub exponent: i32,
+    // confidence interval, uses the same exponent as price
+    pub conf: u64,
 }
 
 impl Ord for OraclePrice {
@@ -57,13 +59,18 @@ impl Eq for OraclePrice {}
 #[allow(dead_code)]
 impl OraclePrice {
     pub fn new(price: u64, exponent: i32) -> Self {
-        Self { price, exponent }
+        Self {
+            price,
+            exponent,
+            conf: 0,
+        }
     }
 
     pub fn new_from_token(amount_and_decimals: (u64, u8)) -> Self {
         Self {
             price: amount_and_decimals.0,
             exponent: -(amount_and_decimals.1 as i32),
+            conf: 0,
         }
     }
 
@@ -71,12 +78,37 @@ impl OraclePrice {
         oracle_account: &AccountInfo,
         clock: &Clock,
         oracle_type: Oracle,
+        oracle_params: &OracleParams,
+        secondary_oracle: Option<(Oracle, &AccountInfo)>,
         use_ema: bool,
     ) -> Result<Self> {
-        match oracle_type {
-            Oracle::Pyth(_) => get_price_from_pyth(oracle_account, clock, use_ema),
-            Oracle::Switchboard(_) => get_price_from_switchboard(oracle_account, clock),
+        oracle_type.get_price(
+            oracle_account,
+            None,
+            secondary_oracle,
+            clock,
+            oracle_params,
+            use_ema,
+        )
+    }
+
+    // Fails if the confidence interval is wider than max_price_error (BPS) of the price
+    pub fn validate_confidence(&self, max_price_error: u64) -> Result<()> {
+        if self.price == 0 {
+            return err!(PerpetualsError::InvalidOraclePrice);
+        }
+        let price_error = math::checked_div(
+            math::checked_mul(self.conf as u128, Perpetuals::BPS_POWER)?,
+            self.price as u128,
+        )?;
+        if price_error > max_price_error as u128 {
+            msg!(
+                "Error: Oracle confidence interval is too wide: {} BPS",
+                price_error
+            );
+            return err!(PerpetualsError::InvalidOraclePrice);
         }
+        Ok(())
     }
 
     // Converts token amount to USD with implied USD_DECIMALS decimals using oracle price
@@ -110,16 +142,19 @@ impl OraclePrice {
     /// Returns price with mantissa normalized to be less than ORACLE_MAX_PRICE
     pub fn normalize(&self) -> Result<OraclePrice> {
         let mut p = self.price;
+        let mut c = self.conf;
         let mut e = self.exponent;
 
         while p > ORACLE_MAX_PRICE {
             p = math::checked_div(p, 10)?;
+            c = math::checked_div(c, 10)?;
             e = math::checked_add(e, 1)?;
         }
 
         Ok(OraclePrice {
             price: p,
             exponent: e,
+            conf: c,
         })
     }
 
@@ -136,6 +171,7 @@ impl OraclePrice {
                 math::checked_add(base.exponent, ORACLE_EXPONENT_SCALE)?,
                 other.exponent,
             )?,
+            conf: 0,
         })
     }
 
@@ -143,6 +179,7 @@ impl OraclePrice {
         Ok(OraclePrice {
             price: math::checked_mul(self.price, other.price)?,
             exponent: math::checked_add(self.exponent, other.exponent)?,
+            conf: 0,
         })
     }
 
@@ -152,14 +189,18 @@ impl OraclePrice {
         }
         let delta = math::checked_sub(target_exponent, self.exponent)?;
         if delta > 0 {
+            let scale = math::checked_pow(10, delta as usize)?;
             Ok(OraclePrice {
-                price: math::checked_div(self.price, math::checked_pow(10, delta as usize)?)?,
+                price: math::checked_div(self.price, scale)?,
                 exponent: target_exponent,
+                conf: math::checked_div(self.conf, scale)?,
             })
         } else {
+            let scale = math::checked_pow(10, (-delta) as usize)?;
             Ok(OraclePrice {
-                price: math::checked_mul(self.price, math::checked_pow(10, (-delta) as usize)?)?,
+                price: math::checked_mul(self.price, scale)?,
                 exponent: target_exponent,
+                conf: math::checked_mul(self.conf, scale)?,
             })
         }
     }
@@ -181,6 +222,7 @@ impl OraclePrice {
                     return Ok(OraclePrice {
                         price: 1000000u64,
                         exponent: -6,
+                        conf: 0,
                     });
                 }
             }
@@ -189,6 +231,7 @@ impl OraclePrice {
                 Ok(OraclePrice {
                     price: one_usd,
                     exponent: min_price.exponent,
+                    conf: min_price.conf,
                 })
             } else {
                 Ok(*min_price)
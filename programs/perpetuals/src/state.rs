@@ -1,6 +1,10 @@
-pub mod admin;
 pub mod custody;
+pub mod margin_account;
 pub mod multisig;
+pub mod oracle;
+pub mod order;
 pub mod perpetuals;
 pub mod pool;
 pub mod position;
+pub mod position_counter;
+pub mod proposal;
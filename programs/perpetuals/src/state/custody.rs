@@ -1,29 +1,47 @@
 use {
     crate::{
-        constants::CUSTODY_SEED,
         error::PerpetualsError,
         math,
         oracle::{
-            get_price_from_pyth, get_price_from_switchboard, get_prices_from_pyth, OraclePrice,
+            aggregate_prices, get_price_from_custom_oracle, get_price_from_pyth,
+            get_price_from_switchboard, OraclePrice,
         },
         state::{
+            oracle::{OracleAggregation, OracleParams},
             perpetuals::{Permissions, Perpetuals},
             position::{Position, Side},
         },
     },
     anchor_lang::prelude::*,
-    anchor_spl::token::{transfer, Transfer},
     pyth_solana_receiver_sdk::ID as PYTH_PROGRAM_ID,
     switchboard_solana::ID as SWITCHBOARD_PROGRAM_ID,
 };
 
-#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub enum FeesMode {
     Fixed,
+    #[default]
     Linear,
     Optimal,
 }
 
+/// Trading status of the custody market, enforced by position handlers on top of permissions.
+/// Statuses are ordered from the least to the most restrictive.
+#[derive(
+    Copy, Clone, PartialEq, PartialOrd, AnchorSerialize, AnchorDeserialize, Default, Debug,
+)]
+pub enum MarketStatus {
+    // no restrictions
+    #[default]
+    Active,
+    // positions can only be reduced: closes, liquidations and collateral deposits
+    ReduceOnly,
+    // positions can only be closed in full or liquidated
+    CloseOnly,
+    // market is wound down, positions can only be closed in full
+    Settled,
+}
+
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub struct Fees {
     pub mode: FeesMode,
@@ -39,7 +57,15 @@ pub struct Fees {
     pub open_position: u64,
     pub close_position: u64,
     pub liquidation: u64,
+    // liquidator reward rises from min to max over the window (in seconds) since the position
+    // has been flagged liquidatable, flat liquidation fee is paid if the window is zero
+    pub liquidation_reward_min: u64,
+    pub liquidation_reward_max: u64,
+    pub liquidation_reward_window_sec: u64,
+    pub execute_order: u64,
     pub protocol_share: u64,
+    // share of open, close and liquidation fees sent to the insurance fund
+    pub insurance_share: u64,
     // configs for optimal fee mode
     pub fee_max: u64,
     pub fee_optimal: u64,
@@ -95,15 +121,34 @@ pub struct PricingParams {
     pub trade_spread_long: u64,
     pub trade_spread_short: u64,
     pub swap_spread: u64,
+    // trade spreads grow by skew_spread_mult scaled by the long/short imbalance for trades on
+    // the crowded side and shrink by as much, down to trade_spread_min, for the other side
+    pub trade_spread_min: u64,
+    pub skew_spread_mult: u64,
+    // price impact added to the trade spread, price_impact_mult applies to a trade as large
+    // as the custody's owned assets and scales linearly, zero disables price impact
+    pub price_impact_mult: u64,
+    pub price_impact_max: u64,
     pub min_initial_leverage: u64,
     pub max_initial_leverage: u64,
     pub max_leverage: u64,
+    // liquidations close just enough size to bring leverage down to this target,
+    // zero disables partial liquidations
+    pub liquidation_target_leverage: u64,
+    // positions with equity below this share of their size are liquidated entirely
+    pub maintenance_margin: u64,
     // max_user_profit = position_size * max_payoff_mult
     pub max_payoff_mult: u64,
     pub max_utilization: u64,
+    // auto-deleveraging is allowed once owned assets net of pending trader profits
+    // fall below this share of owned assets, zero disables it
+    pub adl_threshold: u64,
     // USD denominated values always have implied USD_DECIMALS decimals
     pub max_position_locked_usd: u64,
     pub max_total_locked_usd: u64,
+    // caps on custody open interest by side, zero means no limit
+    pub max_oi_long_usd: u64,
+    pub max_oi_short_usd: u64,
 }
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
@@ -123,6 +168,24 @@ pub struct BorrowRateState {
     pub last_update: i64,
 }
 
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct FundingRateParams {
+    // funding rate params have implied RATE_DECIMALS decimals
+    // hourly rate paid by the heavier side if all open interest is on one side
+    pub max_rate: u64,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct FundingRateState {
+    // funding rates have implied RATE_DECIMALS decimals,
+    // positive values are paid by the side, negative values are received
+    pub current_rate_long: i64,
+    pub current_rate_short: i64,
+    pub cumulative_funding_long: i128,
+    pub cumulative_funding_short: i128,
+    pub last_update: i64,
+}
+
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
 pub struct PositionStats {
     pub open_positions: u64,
@@ -134,22 +197,32 @@ pub struct PositionStats {
     pub total_quantity: u128,
     pub cumulative_interest_usd: u64,
     pub cumulative_interest_snapshot: u128,
+    // sum of position funding snapshots weighted by size_usd
+    pub weighted_funding_snapshot: i128,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, AnchorDeserialize, AnchorSerialize)]
 pub enum Oracle {
     Pyth(Pubkey),
     Switchboard(Pubkey),
+    Custom(Pubkey),
 }
 
 impl Oracle {
-    pub fn from_account_info(account: &AccountInfo, clock: &Clock) -> Result<Self> {
+    pub fn from_account_info(
+        account: &AccountInfo,
+        clock: &Clock,
+        oracle_params: &OracleParams,
+    ) -> Result<Self> {
         if account.owner.eq(&PYTH_PROGRAM_ID) {
-            get_price_from_pyth(account, clock, false)?;
+            get_price_from_pyth(account, clock, oracle_params, false)?;
             Ok(Oracle::Pyth(account.key()))
         } else if account.owner.eq(&SWITCHBOARD_PROGRAM_ID) {
-            get_price_from_switchboard(account, clock)?;
+            get_price_from_switchboard(account, clock, oracle_params)?;
             Ok(Oracle::Switchboard(account.key()))
+        } else if account.owner.eq(&crate::ID) {
+            get_price_from_custom_oracle(account, clock, oracle_params, false)?;
+            Ok(Oracle::Custom(account.key()))
         } else {
             Err(PerpetualsError::InvalidOracleAccount.into())
         }
@@ -157,7 +230,7 @@ impl Oracle {
 
     pub fn key(&self) -> Pubkey {
         match self {
-            Oracle::Pyth(key) | Oracle::Switchboard(key) => *key,
+            Oracle::Pyth(key) | Oracle::Switchboard(key) | Oracle::Custom(key) => *key,
         }
     }
 
@@ -169,29 +242,92 @@ impl Oracle {
         &self,
         custody_oracle: &AccountInfo,
         custody_ema_oracle: &Option<AccountInfo>,
+        secondary_oracle: Option<(Oracle, &AccountInfo)>,
         clock: &Clock,
+        oracle_params: &OracleParams,
     ) -> Result<(OraclePrice, OraclePrice)> {
-        Ok(match *self {
-            Oracle::Pyth(_) => {
-                // Both base and ema prices are in the same account
-                get_prices_from_pyth(custody_oracle, clock)?
-            }
-            Oracle::Switchboard(_) => {
-                let ema_oracle = custody_ema_oracle
-                    .as_ref()
-                    .ok_or(PerpetualsError::EmaOracleRequired)?;
-                (
-                    // Base and ema in separate accounts in case of switchboard
-                    get_price_from_switchboard(custody_oracle, clock)?,
-                    get_price_from_switchboard(ema_oracle, clock)?,
-                )
+        // Base and ema in separate accounts in case of switchboard
+        if let Oracle::Switchboard(_) = self {
+            require!(
+                custody_ema_oracle.is_some(),
+                PerpetualsError::EmaOracleRequired
+            );
+        }
+
+        Ok((
+            self.get_price(
+                custody_oracle,
+                custody_ema_oracle.as_ref(),
+                secondary_oracle,
+                clock,
+                oracle_params,
+                false,
+            )?,
+            self.get_price(
+                custody_oracle,
+                custody_ema_oracle.as_ref(),
+                secondary_oracle,
+                clock,
+                oracle_params,
+                true,
+            )?,
+        ))
+    }
+
+    // Reads the price from this oracle and aggregates it with the secondary
+    // oracle according to the custody's aggregation policy
+    pub fn get_price(
+        &self,
+        oracle_account: &AccountInfo,
+        ema_oracle_account: Option<&AccountInfo>,
+        secondary_oracle: Option<(Oracle, &AccountInfo)>,
+        clock: &Clock,
+        oracle_params: &OracleParams,
+        use_ema: bool,
+    ) -> Result<OraclePrice> {
+        let primary_price = self.read_price(
+            oracle_account,
+            ema_oracle_account,
+            clock,
+            oracle_params,
+            use_ema,
+        );
+        if oracle_params.aggregation == OracleAggregation::PrimaryOnly {
+            return primary_price;
+        }
+
+        let secondary_price = secondary_oracle.map(|(oracle, account)| {
+            oracle.read_price(account, None, clock, oracle_params, use_ema)
+        });
+
+        aggregate_prices(primary_price, secondary_price, oracle_params)
+    }
+
+    fn read_price(
+        &self,
+        oracle_account: &AccountInfo,
+        ema_oracle_account: Option<&AccountInfo>,
+        clock: &Clock,
+        oracle_params: &OracleParams,
+        use_ema: bool,
+    ) -> Result<OraclePrice> {
+        match self {
+            Oracle::Pyth(_) => get_price_from_pyth(oracle_account, clock, oracle_params, use_ema),
+            Oracle::Switchboard(_) => match ema_oracle_account {
+                Some(ema_oracle_account) if use_ema => {
+                    get_price_from_switchboard(ema_oracle_account, clock, oracle_params)
+                }
+                _ => get_price_from_switchboard(oracle_account, clock, oracle_params),
+            },
+            Oracle::Custom(_) => {
+                get_price_from_custom_oracle(oracle_account, clock, oracle_params, use_ema)
             }
-        })
+        }
     }
 }
 
 #[account]
-#[derive(Debug, PartialEq)]
+#[derive(Default, Debug, PartialEq)]
 pub struct Custody {
     // static parameters
     pub pool: Pubkey,
@@ -202,10 +338,13 @@ pub struct Custody {
     pub is_virtual: bool,
     pub oracle: Oracle,
     pub ema_oracle: Option<Oracle>, // if present, always switchboard
+    pub secondary_oracle: Option<Oracle>, // if present, never switchboard
+    pub oracle_params: OracleParams,
     pub pricing: PricingParams,
     pub permissions: Permissions,
     pub fees: Fees,
     pub borrow_rate: BorrowRateParams,
+    pub funding_rate: FundingRateParams,
 
     // dynamic variables
     pub assets: Assets,
@@ -215,15 +354,46 @@ pub struct Custody {
     pub long_positions: PositionStats,
     pub short_positions: PositionStats,
     pub borrow_rate_state: BorrowRateState,
+    pub funding_rate_state: FundingRateState,
 
     // bumps for address validation
     pub bump: u8,
     pub token_account_bump: u8,
+    pub insurance_fund_token_account_bump: u8,
+
+    // appended last, custodies created before it was added read as Active
+    pub market_status: MarketStatus,
 }
 
-impl Default for FeesMode {
+impl Default for Oracle {
     fn default() -> Self {
-        Self::Linear
+        Self::Custom(Pubkey::default())
+    }
+}
+
+impl MarketStatus {
+    pub fn allow_open_position(&self) -> bool {
+        *self == MarketStatus::Active
+    }
+
+    pub fn allow_size_increase(&self) -> bool {
+        *self == MarketStatus::Active
+    }
+
+    pub fn allow_collateral_deposit(&self) -> bool {
+        *self <= MarketStatus::ReduceOnly
+    }
+
+    pub fn allow_collateral_withdrawal(&self) -> bool {
+        *self == MarketStatus::Active
+    }
+
+    pub fn allow_partial_close(&self) -> bool {
+        *self <= MarketStatus::ReduceOnly
+    }
+
+    pub fn allow_liquidation(&self) -> bool {
+        *self <= MarketStatus::CloseOnly
     }
 }
 
@@ -238,7 +408,11 @@ impl Fees {
             && self.open_position as u128 <= Perpetuals::BPS_POWER
             && self.close_position as u128 <= Perpetuals::BPS_POWER
             && self.liquidation as u128 <= Perpetuals::BPS_POWER
+            && self.liquidation_reward_min <= self.liquidation_reward_max
+            && self.liquidation_reward_max as u128 <= Perpetuals::BPS_POWER
+            && self.execute_order as u128 <= Perpetuals::BPS_POWER
             && self.protocol_share as u128 <= Perpetuals::BPS_POWER
+            && (self.protocol_share as u128 + self.insurance_share as u128) <= Perpetuals::BPS_POWER
             && self.fee_max as u128 <= Perpetuals::BPS_POWER
             && self.fee_optimal as u128 <= Perpetuals::BPS_POWER
     }
@@ -249,10 +423,25 @@ impl PricingParams {
         (self.min_initial_leverage as u128) >= Perpetuals::BPS_POWER
             && self.min_initial_leverage <= self.max_initial_leverage
             && self.max_initial_leverage <= self.max_leverage
+            && (self.liquidation_target_leverage == 0
+                || ((self.liquidation_target_leverage as u128) >= Perpetuals::BPS_POWER
+                    && self.liquidation_target_leverage < self.max_leverage))
+            && (self.maintenance_margin as u128) < Perpetuals::BPS_POWER
             && (self.trade_spread_long as u128) < Perpetuals::BPS_POWER
             && (self.trade_spread_short as u128) < Perpetuals::BPS_POWER
             && (self.swap_spread as u128) < Perpetuals::BPS_POWER
+            && self.trade_spread_min <= self.trade_spread_long
+            && self.trade_spread_min <= self.trade_spread_short
+            && (self.trade_spread_long as u128
+                + self.skew_spread_mult as u128
+                + self.price_impact_max as u128)
+                < Perpetuals::BPS_POWER
+            && (self.trade_spread_short as u128
+                + self.skew_spread_mult as u128
+                + self.price_impact_max as u128)
+                < Perpetuals::BPS_POWER
             && (self.max_utilization as u128) <= Perpetuals::BPS_POWER
+            && (self.adl_threshold as u128) <= Perpetuals::BPS_POWER
             && self.max_position_locked_usd <= self.max_total_locked_usd
     }
 }
@@ -263,6 +452,12 @@ impl BorrowRateParams {
     }
 }
 
+impl FundingRateParams {
+    pub fn validate(&self) -> bool {
+        (self.max_rate as u128) <= Perpetuals::RATE_POWER
+    }
+}
+
 impl Custody {
     pub const LEN: usize = 8 + std::mem::size_of::<Custody>();
 
@@ -271,9 +466,18 @@ impl Custody {
             && self.token_account != Pubkey::default()
             && self.mint != Pubkey::default()
             && self.oracle.validate()
+            && self.oracle_params.validate()
+            && match self.secondary_oracle {
+                Some(secondary_oracle) => {
+                    secondary_oracle.key() != self.oracle.key()
+                        && !matches!(secondary_oracle, Oracle::Switchboard(_))
+                }
+                None => self.oracle_params.aggregation == OracleAggregation::PrimaryOnly,
+            }
             && self.pricing.validate()
             && self.fees.validate()
             && self.borrow_rate.validate()
+            && self.funding_rate.validate()
     }
 
     pub fn lock_funds(&mut self, amount: u64) -> Result<()> {
@@ -422,6 +626,103 @@ impl Custody {
         Ok(())
     }
 
+    pub fn get_funding_amount_usd(&self, position: &Position, curtime: i64) -> Result<i64> {
+        if position.size_usd == 0 {
+            return Ok(0);
+        }
+
+        let cumulative_funding = self.get_cumulative_funding(position.side, curtime)?;
+        let position_funding =
+            math::checked_sub(cumulative_funding, position.cumulative_funding_snapshot)?;
+
+        math::checked_as_i64(math::checked_div(
+            math::checked_mul(position_funding, position.size_usd as i128)?,
+            Perpetuals::RATE_POWER as i128,
+        )?)
+    }
+
+    pub fn get_cumulative_funding(&self, side: Side, curtime: i64) -> Result<i128> {
+        let (cumulative_funding, current_rate) = if side == Side::Long {
+            (
+                self.funding_rate_state.cumulative_funding_long,
+                self.funding_rate_state.current_rate_long,
+            )
+        } else {
+            (
+                self.funding_rate_state.cumulative_funding_short,
+                self.funding_rate_state.current_rate_short,
+            )
+        };
+
+        if curtime > self.funding_rate_state.last_update {
+            let funding = math::checked_div(
+                math::checked_mul(
+                    math::checked_sub(curtime, self.funding_rate_state.last_update)? as i128,
+                    current_rate as i128,
+                )?,
+                3600,
+            )?;
+            math::checked_add(cumulative_funding, funding)
+        } else {
+            Ok(cumulative_funding)
+        }
+    }
+
+    pub fn update_funding_rate(&mut self, curtime: i64) -> Result<()> {
+        // heavy_rate = max_rate * |oi_long - oi_short| / (oi_long + oi_short)
+        // light_rate = -heavy_rate * oi_heavy / oi_light
+        // i.e. whatever the heavier side pays is received by the lighter side
+
+        if curtime > self.funding_rate_state.last_update {
+            // compute funding accumulated since previous update
+            self.funding_rate_state.cumulative_funding_long =
+                self.get_cumulative_funding(Side::Long, curtime)?;
+            self.funding_rate_state.cumulative_funding_short =
+                self.get_cumulative_funding(Side::Short, curtime)?;
+            self.funding_rate_state.last_update = curtime;
+        }
+
+        let oi_long = self.trade_stats.oi_long_usd as u128;
+        let oi_short = self.trade_stats.oi_short_usd as u128;
+        let (oi_heavy, oi_light) = if oi_long > oi_short {
+            (oi_long, oi_short)
+        } else {
+            (oi_short, oi_long)
+        };
+
+        if oi_heavy == oi_light || self.funding_rate.max_rate == 0 {
+            self.funding_rate_state.current_rate_long = 0;
+            self.funding_rate_state.current_rate_short = 0;
+            return Ok(());
+        }
+
+        // compute and save new funding rates
+        let heavy_rate = math::checked_div(
+            math::checked_mul(
+                self.funding_rate.max_rate as u128,
+                math::checked_sub(oi_heavy, oi_light)?,
+            )?,
+            math::checked_add(oi_heavy, oi_light)?,
+        )?;
+        let light_rate = if oi_light > 0 {
+            math::checked_div(math::checked_mul(heavy_rate, oi_heavy)?, oi_light)?
+        } else {
+            0
+        };
+
+        let heavy_rate = math::checked_as_i64(heavy_rate)?;
+        let light_rate = -math::checked_as_i64(light_rate)?;
+        if oi_long > oi_short {
+            self.funding_rate_state.current_rate_long = heavy_rate;
+            self.funding_rate_state.current_rate_short = light_rate;
+        } else {
+            self.funding_rate_state.current_rate_long = light_rate;
+            self.funding_rate_state.current_rate_short = heavy_rate;
+        }
+
+        Ok(())
+    }
+
     pub fn get_collective_position(&self, side: Side) -> Result<Position> {
         let stats = if side == Side::Long {
             &self.long_positions
@@ -443,6 +744,11 @@ impl Custody {
                 borrow_size_usd: stats.borrow_size_usd,
                 unrealized_loss_usd: stats.cumulative_interest_usd,
                 cumulative_interest_snapshot: stats.cumulative_interest_snapshot,
+                cumulative_funding_snapshot: if stats.size_usd > 0 {
+                    math::checked_div(stats.weighted_funding_snapshot, stats.size_usd as i128)?
+                } else {
+                    0
+                },
                 locked_amount: stats.locked_amount,
                 ..Position::default()
             })
@@ -451,6 +757,23 @@ impl Custody {
         }
     }
 
+    pub fn check_open_interest(&self, side: Side) -> Result<()> {
+        if side == Side::Long {
+            if self.pricing.max_oi_long_usd > 0 {
+                require!(
+                    self.trade_stats.oi_long_usd <= self.pricing.max_oi_long_usd,
+                    PerpetualsError::MaxOpenInterestLong
+                );
+            }
+        } else if self.pricing.max_oi_short_usd > 0 {
+            require!(
+                self.trade_stats.oi_short_usd <= self.pricing.max_oi_short_usd,
+                PerpetualsError::MaxOpenInterestShort
+            );
+        }
+        Ok(())
+    }
+
     pub fn add_position(
         &mut self,
         position: &Position,
@@ -470,6 +793,7 @@ impl Custody {
         };
 
         stats.open_positions = math::checked_add(stats.open_positions, 1)?;
+        stats.collateral_usd = math::checked_add(stats.collateral_usd, position.collateral_usd)?;
         stats.size_usd = math::checked_add(stats.size_usd, position.size_usd)?;
         stats.locked_amount = math::checked_add(stats.locked_amount, position.locked_amount)?;
 
@@ -496,6 +820,13 @@ impl Custody {
             math::checked_mul(position.price as u128, quantity)?,
         )?;
         stats.total_quantity = math::checked_add(stats.total_quantity, quantity)?;
+        stats.weighted_funding_snapshot = math::checked_add(
+            stats.weighted_funding_snapshot,
+            math::checked_mul(
+                position.cumulative_funding_snapshot,
+                position.size_usd as i128,
+            )?,
+        )?;
 
         // check limits
         if self.pricing.max_position_locked_usd > 0 {
@@ -576,6 +907,7 @@ impl Custody {
         }
 
         stats.open_positions = math::checked_sub(stats.open_positions, 1)?;
+        stats.collateral_usd = stats.collateral_usd.saturating_sub(position.collateral_usd);
         stats.size_usd = math::checked_sub(stats.size_usd, position.size_usd)?;
         stats.locked_amount = math::checked_sub(stats.locked_amount, position.locked_amount)?;
 
@@ -593,6 +925,13 @@ impl Custody {
             math::checked_mul(position.price as u128, quantity)?,
         )?;
         stats.total_quantity = math::checked_sub(stats.total_quantity, quantity)?;
+        stats.weighted_funding_snapshot = math::checked_sub(
+            stats.weighted_funding_snapshot,
+            math::checked_mul(
+                position.cumulative_funding_snapshot,
+                position.size_usd as i128,
+            )?,
+        )?;
 
         // update collateral custody for interest tracking
         if let Some(custody) = collateral_custody {
@@ -626,45 +965,47 @@ impl Custody {
         Ok(())
     }
 
-    // Tbh this should be aggregated across entire protocol
-    // to be able to withdraw in one instruction.
-    // TODO: Aggregate fees on protocol-level.
-    pub fn withdraw_fees<'info>(
-        &self,
-        from: AccountInfo<'info>,
-        to: AccountInfo<'info>,
-        authority: AccountInfo<'info>,
-        token_program: AccountInfo<'info>,
-    ) -> Result<()> {
-        let seeds = &[
-            CUSTODY_SEED.as_bytes(),
-            self.pool.as_ref(),
-            self.mint.as_ref(),
-            &[self.bump],
-        ];
-
-        transfer(
-            CpiContext::new_with_signer(
-                token_program.to_account_info(),
-                Transfer {
-                    from: from.to_account_info(),
-                    authority: authority.to_account_info(),
-                    to: to.to_account_info(),
-                },
-                &[seeds],
-            ),
-            self.assets.protocol_fees,
-        )?;
+    pub fn add_position_collateral(&mut self, side: Side, collateral_usd: u64) -> Result<()> {
+        let stats = if side == Side::Long {
+            &mut self.long_positions
+        } else {
+            &mut self.short_positions
+        };
+        stats.collateral_usd = math::checked_add(stats.collateral_usd, collateral_usd)?;
 
         Ok(())
     }
 
+    pub fn remove_position_collateral(&mut self, side: Side, collateral_usd: u64) {
+        let stats = if side == Side::Long {
+            &mut self.long_positions
+        } else {
+            &mut self.short_positions
+        };
+        stats.collateral_usd = stats.collateral_usd.saturating_sub(collateral_usd);
+    }
+
+    // Tbh this should be aggregated across entire protocol
+    // to be able to withdraw in one instruction.
+    // TODO: Aggregate fees on protocol-level.
     pub fn needs_ema_oracle(&self) -> bool {
         match self.oracle {
-            Oracle::Pyth(_) => false,
+            Oracle::Pyth(_) | Oracle::Custom(_) => false,
             Oracle::Switchboard(_) => true,
         }
     }
+
+    // Secondary oracle and its account, if registered and passed in remaining accounts
+    pub fn get_secondary_oracle<'a, 'info>(
+        &self,
+        remaining_accounts: &'a [AccountInfo<'info>],
+    ) -> Option<(Oracle, &'a AccountInfo<'info>)> {
+        let secondary_oracle = self.secondary_oracle?;
+        remaining_accounts
+            .iter()
+            .find(|account| account.key() == secondary_oracle.key())
+            .map(|account| (secondary_oracle, account))
+    }
 }
 
 #[cfg(test)]
@@ -787,4 +1128,132 @@ mod test {
         custody.update_borrow_rate(3600).unwrap();
         assert_eq!(custody.borrow_rate_state.current_rate, 199400);
     }
+
+    #[test]
+    fn test_update_funding_rate() {
+        let mut custody = Custody {
+            funding_rate: FundingRateParams { max_rate: 100_000 },
+            trade_stats: TradeStats {
+                oi_long_usd: 300,
+                oi_short_usd: 100,
+                ..TradeStats::default()
+            },
+            ..get_fixture()
+        };
+        custody.update_funding_rate(3600).unwrap();
+        assert_eq!(
+            custody.funding_rate_state,
+            FundingRateState {
+                current_rate_long: 50_000,
+                current_rate_short: -150_000,
+                cumulative_funding_long: 0,
+                cumulative_funding_short: 0,
+                last_update: 3600
+            }
+        );
+
+        custody.update_funding_rate(7200).unwrap();
+        assert_eq!(custody.funding_rate_state.cumulative_funding_long, 50_000);
+        assert_eq!(
+            custody.funding_rate_state.cumulative_funding_short,
+            -150_000
+        );
+
+        // longs pay what shorts receive
+        let long_position = Position {
+            side: Side::Long,
+            size_usd: 3_000_000_000,
+            ..Position::default()
+        };
+        let short_position = Position {
+            side: Side::Short,
+            size_usd: 1_000_000_000,
+            ..Position::default()
+        };
+        assert_eq!(
+            custody
+                .get_funding_amount_usd(&long_position, 7200)
+                .unwrap(),
+            150_000
+        );
+        assert_eq!(
+            custody
+                .get_funding_amount_usd(&short_position, 7200)
+                .unwrap(),
+            -150_000
+        );
+        assert_eq!(
+            custody
+                .get_funding_amount_usd(&long_position, 9000)
+                .unwrap(),
+            225_000
+        );
+
+        // balanced open interest
+        custody.trade_stats.oi_short_usd = 300;
+        custody.update_funding_rate(10800).unwrap();
+        assert_eq!(custody.funding_rate_state.current_rate_long, 0);
+        assert_eq!(custody.funding_rate_state.current_rate_short, 0);
+        assert_eq!(custody.funding_rate_state.cumulative_funding_long, 100_000);
+        assert_eq!(
+            custody.funding_rate_state.cumulative_funding_short,
+            -300_000
+        );
+
+        // nobody on the other side
+        custody.trade_stats.oi_long_usd = 0;
+        custody.update_funding_rate(14400).unwrap();
+        assert_eq!(custody.funding_rate_state.current_rate_long, 0);
+        assert_eq!(custody.funding_rate_state.current_rate_short, 100_000);
+    }
+
+    #[test]
+    fn test_check_open_interest() {
+        let mut custody = get_fixture();
+        custody.trade_stats.oi_long_usd = 1_000;
+        custody.trade_stats.oi_short_usd = 500;
+
+        // no limits
+        assert!(custody.check_open_interest(Side::Long).is_ok());
+        assert!(custody.check_open_interest(Side::Short).is_ok());
+
+        custody.pricing.max_oi_long_usd = 1_000;
+        custody.pricing.max_oi_short_usd = 400;
+        assert!(custody.check_open_interest(Side::Long).is_ok());
+        assert!(custody.check_open_interest(Side::Short).is_err());
+
+        custody.trade_stats.oi_long_usd = 1_001;
+        assert!(custody.check_open_interest(Side::Long).is_err());
+    }
+
+    #[test]
+    fn test_market_status() {
+        let active = MarketStatus::Active;
+        assert!(active.allow_open_position());
+        assert!(active.allow_size_increase());
+        assert!(active.allow_collateral_deposit());
+        assert!(active.allow_collateral_withdrawal());
+        assert!(active.allow_partial_close());
+        assert!(active.allow_liquidation());
+
+        let reduce_only = MarketStatus::ReduceOnly;
+        assert!(!reduce_only.allow_open_position());
+        assert!(!reduce_only.allow_size_increase());
+        assert!(reduce_only.allow_collateral_deposit());
+        assert!(!reduce_only.allow_collateral_withdrawal());
+        assert!(reduce_only.allow_partial_close());
+        assert!(reduce_only.allow_liquidation());
+
+        let close_only = MarketStatus::CloseOnly;
+        assert!(!close_only.allow_collateral_deposit());
+        assert!(!close_only.allow_partial_close());
+        assert!(close_only.allow_liquidation());
+
+        let settled = MarketStatus::Settled;
+        assert!(!settled.allow_collateral_deposit());
+        assert!(!settled.allow_partial_close());
+        assert!(!settled.allow_liquidation());
+
+        assert!(active < reduce_only && reduce_only < close_only && close_only < settled);
+    }
 }
@@ -0,0 +1,226 @@
+use {
+    crate::{
+        error::PerpetualsError,
+        helpers::AccountMap,
+        math,
+        state::{custody::Custody, position::Position},
+    },
+    anchor_lang::prelude::*,
+};
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct MarginCollateral {
+    pub custody: Pubkey,
+    pub amount: u64,
+}
+
+/// Cross-margin account, its collateral backs all linked positions of the owner in the pool.
+/// Collateral tokens are held in the custody token accounts and tracked as custody collateral.
+#[account]
+#[derive(Default, Debug)]
+pub struct MarginAccount {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub collaterals: Vec<MarginCollateral>,
+    pub positions: Vec<Pubkey>,
+
+    pub bump: u8,
+}
+
+impl MarginAccount {
+    pub const MAX_COLLATERALS: usize = 4;
+    pub const MAX_POSITIONS: usize = 16;
+    pub const LEN: usize = 8
+        + 32
+        + 32
+        + 4
+        + MarginAccount::MAX_COLLATERALS * std::mem::size_of::<MarginCollateral>()
+        + 4
+        + MarginAccount::MAX_POSITIONS * 32
+        + 1;
+
+    pub fn get_collateral_amount(&self, custody: &Pubkey) -> u64 {
+        self.collaterals
+            .iter()
+            .find(|collateral| collateral.custody == *custody)
+            .map_or(0, |collateral| collateral.amount)
+    }
+
+    pub fn add_collateral(&mut self, custody: &Pubkey, amount: u64) -> Result<()> {
+        if let Some(collateral) = self
+            .collaterals
+            .iter_mut()
+            .find(|collateral| collateral.custody == *custody)
+        {
+            collateral.amount = math::checked_add(collateral.amount, amount)?;
+            return Ok(());
+        }
+
+        require!(
+            self.collaterals.len() < MarginAccount::MAX_COLLATERALS,
+            PerpetualsError::MarginAccountFull
+        );
+        self.collaterals.push(MarginCollateral {
+            custody: *custody,
+            amount,
+        });
+        Ok(())
+    }
+
+    pub fn remove_collateral(&mut self, custody: &Pubkey, amount: u64) -> Result<()> {
+        let collateral = self
+            .collaterals
+            .iter_mut()
+            .find(|collateral| collateral.custody == *custody)
+            .ok_or(ProgramError::InvalidArgument)?;
+        collateral.amount = math::checked_sub(collateral.amount, amount)?;
+
+        self.collaterals.retain(|collateral| collateral.amount > 0);
+        Ok(())
+    }
+
+    /// Covers bad debt of a linked position from the margin collateral held in the position's
+    /// collateral custody, returns the amount of collateral used.
+    pub fn cover_bad_debt(
+        &mut self,
+        collateral_custody_key: &Pubkey,
+        collateral_custody: &mut Custody,
+        bad_debt: u64,
+    ) -> Result<u64> {
+        // margin collateral is already held in the custody token account, only collateral
+        // of the position's collateral custody can cover its bad debt
+        let amount = std::cmp::min(bad_debt, self.get_collateral_amount(collateral_custody_key));
+        if amount > 0 {
+            self.remove_collateral(collateral_custody_key, amount)?;
+            collateral_custody.assets.collateral =
+                math::checked_sub(collateral_custody.assets.collateral, amount)?;
+            collateral_custody.assets.owned =
+                math::checked_add(collateral_custody.assets.owned, amount)?;
+        }
+        Ok(amount)
+    }
+
+    pub fn add_position(&mut self, position: &Pubkey) -> Result<()> {
+        if self.positions.contains(position) {
+            return Ok(());
+        }
+        require!(
+            self.positions.len() < MarginAccount::MAX_POSITIONS,
+            PerpetualsError::MarginAccountFull
+        );
+        self.positions.push(*position);
+        Ok(())
+    }
+
+    pub fn remove_position(&mut self, position: &Pubkey) {
+        self.positions.retain(|key| key != position);
+    }
+
+    /// Drops positions that have been closed or are no longer linked to this account.
+    /// Positions missing from the account map are kept.
+    pub fn prune_positions(&mut self, margin_account_key: &Pubkey, accounts: &AccountMap) {
+        self.positions
+            .retain(|key| match accounts.get_account(key) {
+                Ok(account) => matches!(
+                    Account::<Position>::try_from(account),
+                    Ok(position) if position.margin_account == *margin_account_key
+                ),
+                Err(_) => true,
+            });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_collateral_bookkeeping() {
+        let mut margin_account = MarginAccount::default();
+        let usdc = Pubkey::new_unique();
+        let usdt = Pubkey::new_unique();
+
+        margin_account.add_collateral(&usdc, 100).unwrap();
+        margin_account.add_collateral(&usdc, 50).unwrap();
+        margin_account.add_collateral(&usdt, 10).unwrap();
+        assert_eq!(150, margin_account.get_collateral_amount(&usdc));
+        assert_eq!(10, margin_account.get_collateral_amount(&usdt));
+        assert_eq!(2, margin_account.collaterals.len());
+
+        margin_account.remove_collateral(&usdt, 10).unwrap();
+        assert_eq!(0, margin_account.get_collateral_amount(&usdt));
+        assert_eq!(1, margin_account.collaterals.len());
+
+        assert!(margin_account.remove_collateral(&usdc, 151).is_err());
+        assert!(margin_account.remove_collateral(&usdt, 1).is_err());
+
+        for _ in 1..MarginAccount::MAX_COLLATERALS {
+            margin_account
+                .add_collateral(&Pubkey::new_unique(), 1)
+                .unwrap();
+        }
+        assert!(margin_account
+            .add_collateral(&Pubkey::new_unique(), 1)
+            .is_err());
+    }
+
+    #[test]
+    fn test_position_bookkeeping() {
+        let mut margin_account = MarginAccount::default();
+        let position = Pubkey::new_unique();
+
+        margin_account.add_position(&position).unwrap();
+        margin_account.add_position(&position).unwrap();
+        assert_eq!(vec![position], margin_account.positions);
+
+        margin_account.remove_position(&position);
+        assert!(margin_account.positions.is_empty());
+
+        for _ in 0..MarginAccount::MAX_POSITIONS {
+            margin_account.add_position(&Pubkey::new_unique()).unwrap();
+        }
+        assert!(margin_account.add_position(&Pubkey::new_unique()).is_err());
+    }
+
+    #[test]
+    fn test_cover_bad_debt() {
+        let mut margin_account = MarginAccount::default();
+        let mut collateral_custody = Custody::default();
+        let collateral_custody_key = Pubkey::new_unique();
+        collateral_custody.assets.owned = 1_000;
+        collateral_custody.assets.collateral = 100;
+        margin_account
+            .add_collateral(&collateral_custody_key, 100)
+            .unwrap();
+        margin_account
+            .add_collateral(&Pubkey::new_unique(), 100)
+            .unwrap();
+
+        assert_eq!(
+            0,
+            margin_account
+                .cover_bad_debt(&collateral_custody_key, &mut collateral_custody, 0)
+                .unwrap()
+        );
+
+        assert_eq!(
+            60,
+            margin_account
+                .cover_bad_debt(&collateral_custody_key, &mut collateral_custody, 60)
+                .unwrap()
+        );
+        assert_eq!(1_060, collateral_custody.assets.owned);
+        assert_eq!(40, collateral_custody.assets.collateral);
+
+        // collateral of other custodies is left untouched
+        assert_eq!(
+            40,
+            margin_account
+                .cover_bad_debt(&collateral_custody_key, &mut collateral_custody, 60)
+                .unwrap()
+        );
+        assert_eq!(1_100, collateral_custody.assets.owned);
+        assert_eq!(0, collateral_custody.assets.collateral);
+        assert_eq!(1, margin_account.collaterals.len());
+    }
+}
@@ -22,12 +22,28 @@ pub struct Multisig {
     pub bump: u8,
 }
 
+/// Governance roles, each role is a separate multisig PDA at seeds `[b"multisig", role]`
+/// with its own signers and number of required signatures
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+pub enum Role {
+    // adds and removes pools and custodies
+    PoolAdmin,
+    // sets custody and pool risk parameters
+    RiskAdmin,
+    // withdraws protocol fees
+    FeeWithdrawer,
+    // sets protocol permissions
+    Pauser,
+    // sets custom oracle prices
+    OracleAuthority,
+}
+
 pub enum AdminInstruction {
     AddPool,
     RemovePool,
     AddCustody,
     RemoveCustody,
-    SetAdminSigners,
+    GrantRole,
     SetCustodyConfig,
     SetPermissions,
     SetBorrowRate,
@@ -36,6 +52,13 @@ pub enum AdminInstruction {
     SetCustomOraclePrice,
     SetTestTime,
     UpgradeCustody,
+    SetPoolConfig,
+    RevokeRole,
+    Propose,
+    CancelProposal,
+    SetPoolPermissions,
+    UpgradePool,
+    SetMarketStatus,
 }
 
 impl Multisig {
@@ -129,6 +152,71 @@ impl Multisig {
         Ok(())
     }
 
+    /// Adds a new signer and updates the number of required signatures,
+    /// pending signatures are discarded
+    pub fn add_signer(&mut self, signer: &Pubkey, min_signatures: u8) -> Result<()> {
+        if self.is_signer(signer)? {
+            msg!("Error: Duplicate signer {}", signer);
+            return Err(ProgramError::InvalidArgument.into());
+        }
+        if self.num_signers as usize >= Multisig::MAX_SIGNERS {
+            msg!(
+                "Error: Number of signers exceeded max ({})",
+                Multisig::MAX_SIGNERS
+            );
+            return Err(ProgramError::InvalidArgument.into());
+        }
+        let num_signers = math::checked_add(self.num_signers, 1)?;
+        Multisig::validate_min_signatures(min_signatures, num_signers)?;
+
+        let mut signers = self.signers;
+        signers[self.num_signers as usize] = *signer;
+        self.signers = signers;
+        self.num_signers = num_signers;
+        self.min_signatures = min_signatures;
+        self.reset_signatures();
+
+        Ok(())
+    }
+
+    /// Removes the signer and updates the number of required signatures,
+    /// pending signatures are discarded
+    pub fn remove_signer(&mut self, signer: &Pubkey, min_signatures: u8) -> Result<()> {
+        let signer_idx = self.get_signer_index(signer)?;
+        let num_signers = math::checked_sub(self.num_signers, 1)?;
+        Multisig::validate_min_signatures(min_signatures, num_signers)?;
+
+        let mut signers = self.signers;
+        signers[signer_idx] = signers[num_signers as usize];
+        signers[num_signers as usize] = Pubkey::default();
+        self.signers = signers;
+        self.num_signers = num_signers;
+        self.min_signatures = min_signatures;
+        self.reset_signatures();
+
+        Ok(())
+    }
+
+    fn validate_min_signatures(min_signatures: u8, num_signers: u8) -> Result<()> {
+        if min_signatures == 0 || min_signatures > num_signers {
+            msg!(
+                "Error: Number of min signatures ({}) must be between 1 and number of signers ({})",
+                min_signatures,
+                num_signers,
+            );
+            return Err(ProgramError::InvalidArgument.into());
+        }
+        Ok(())
+    }
+
+    fn reset_signatures(&mut self) {
+        self.num_signed = 0;
+        self.instruction_accounts_len = 0;
+        self.instruction_data_len = 0;
+        self.instruction_hash = 0;
+        self.signed = Default::default();
+    }
+
     /// Signs multisig and returns Ok(0) if there are enough signatures to continue or Ok(signatures_left) otherwise.
     /// If Err() is returned then signature was not recognized and transaction must be aborted.
     pub fn sign_multisig(
@@ -233,3 +321,126 @@ impl Multisig {
         Ok(self.get_signer_index(key).is_ok())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use {super::*, crate::instructions::SetPermissionsParams};
+
+    #[test]
+    fn test_add_remove_signer() {
+        let mut multisig = Multisig::default();
+        let signer1 = Pubkey::new_unique();
+        let signer2 = Pubkey::new_unique();
+        let signer3 = Pubkey::new_unique();
+
+        multisig.add_signer(&signer1, 1).unwrap();
+        multisig.add_signer(&signer2, 2).unwrap();
+        multisig.add_signer(&signer3, 2).unwrap();
+        assert_eq!(3, { multisig.num_signers });
+        assert_eq!(2, { multisig.min_signatures });
+
+        assert!(multisig.add_signer(&signer3, 2).is_err());
+        assert!(multisig.add_signer(&Pubkey::new_unique(), 5).is_err());
+        assert!(multisig.add_signer(&Pubkey::new_unique(), 0).is_err());
+
+        multisig.remove_signer(&signer1, 1).unwrap();
+        assert_eq!(2, { multisig.num_signers });
+        assert!(!multisig.is_signer(&signer1).unwrap());
+        assert!(multisig.is_signer(&signer2).unwrap());
+        assert!(multisig.is_signer(&signer3).unwrap());
+
+        assert!(multisig.remove_signer(&signer1, 1).is_err());
+        assert!(multisig.remove_signer(&signer2, 2).is_err());
+    }
+
+    #[test]
+    fn test_sign_round() {
+        let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let owner = Pubkey::new_unique();
+        let mut lamports = vec![0u64; keys.len()];
+        let mut data: Vec<Vec<u8>> = vec![vec![]; keys.len()];
+
+        let mut account_infos: Vec<AccountInfo> = Vec::new();
+        for ((key, lamports), data) in keys.iter().zip(lamports.iter_mut()).zip(data.iter_mut()) {
+            account_infos.push(AccountInfo::new(
+                key, true, false, lamports, data, &owner, false, 0,
+            ));
+        }
+        let (signers, outsider) = (&account_infos[..3], &account_infos[3]);
+
+        let mut multisig = Multisig::default();
+        multisig.set_signers(signers, 2).unwrap();
+
+        let params = SetPermissionsParams {
+            allow_swap: false,
+            allow_add_liquidity: true,
+            allow_remove_liquidity: true,
+            allow_open_position: true,
+            allow_close_position: true,
+            allow_pnl_withdrawal: true,
+            allow_collateral_withdrawal: true,
+            allow_size_change: true,
+        };
+        let instruction_data =
+            Multisig::get_instruction_data(AdminInstruction::SetPermissions, &params).unwrap();
+        let instruction_accounts = &account_infos[..1];
+
+        // first signature starts the round
+        assert_eq!(
+            1,
+            multisig
+                .sign_multisig(&signers[0], instruction_accounts, &instruction_data)
+                .unwrap()
+        );
+
+        // signing twice is rejected
+        assert_eq!(
+            multisig
+                .sign_multisig(&signers[0], instruction_accounts, &instruction_data)
+                .unwrap_err(),
+            PerpetualsError::MultisigAlreadySigned.into()
+        );
+
+        // signature can be withdrawn and given again within the same round
+        multisig.unsign_multisig(&signers[0]).unwrap();
+        assert_eq!(0, { multisig.num_signed });
+        assert_eq!(
+            1,
+            multisig
+                .sign_multisig(&signers[0], instruction_accounts, &instruction_data)
+                .unwrap()
+        );
+
+        // unknown signers are rejected
+        assert_eq!(
+            multisig
+                .sign_multisig(outsider, instruction_accounts, &instruction_data)
+                .unwrap_err(),
+            PerpetualsError::MultisigAccountNotAuthorized.into()
+        );
+
+        // second signature completes the round, further signatures are rejected
+        assert_eq!(
+            0,
+            multisig
+                .sign_multisig(&signers[1], instruction_accounts, &instruction_data)
+                .unwrap()
+        );
+        assert_eq!(
+            multisig
+                .sign_multisig(&signers[2], instruction_accounts, &instruction_data)
+                .unwrap_err(),
+            PerpetualsError::MultisigAlreadyExecuted.into()
+        );
+
+        // a different instruction starts a new round
+        let other_instruction_data =
+            Multisig::get_instruction_data(AdminInstruction::SetTestTime, &params).unwrap();
+        assert_eq!(
+            1,
+            multisig
+                .sign_multisig(&signers[2], instruction_accounts, &other_instruction_data)
+                .unwrap()
+        );
+    }
+}
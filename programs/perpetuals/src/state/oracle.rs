@@ -0,0 +1,115 @@
+use {
+    crate::{error::PerpetualsError, math, state::perpetuals::Perpetuals},
+    anchor_lang::prelude::*,
+};
+
+// How prices of the primary and the secondary oracle are combined
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+pub enum OracleAggregation {
+    // secondary oracle is ignored
+    PrimaryOnly,
+    // secondary oracle is used when the primary price is stale
+    Fallback,
+    // median of the available prices
+    Median,
+    // primary price, rejected when the sources diverge by more than max_divergence
+    RejectOnDivergence,
+}
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub struct OracleParams {
+    // off-chain signer allowed to push custom oracle prices through permissionless updates
+    pub oracle_authority: Pubkey,
+    // maximum confidence interval relative to the price, in BPS
+    pub max_price_error: u64,
+    // maximum price age for closes, liquidations, swaps and liquidity changes
+    pub max_price_age_sec: u32,
+    // stricter maximum price age for opening or increasing positions
+    pub max_open_price_age_sec: u32,
+    pub aggregation: OracleAggregation,
+    // maximum difference between primary and secondary prices, in BPS
+    pub max_divergence: u64,
+}
+
+impl Default for OracleAggregation {
+    fn default() -> Self {
+        Self::PrimaryOnly
+    }
+}
+
+impl OracleParams {
+    pub fn validate(&self) -> bool {
+        self.max_price_error > 0
+            && (self.max_price_error as u128) <= Perpetuals::BPS_POWER
+            && self.max_open_price_age_sec > 0
+            && self.max_open_price_age_sec <= self.max_price_age_sec
+            && (self.max_divergence as u128) <= Perpetuals::BPS_POWER
+            && (self.aggregation != OracleAggregation::RejectOnDivergence
+                || self.max_divergence > 0)
+    }
+
+    // Oracle params to read prices with when opening or increasing a position
+    pub fn get_open_params(&self) -> OracleParams {
+        OracleParams {
+            max_price_age_sec: self.max_open_price_age_sec,
+            ..*self
+        }
+    }
+}
+
+// Protocol-pushed price for assets without a third-party feed, updated by admins
+#[account]
+#[derive(Default, Debug)]
+pub struct CustomOracle {
+    pub price: u64,
+    pub expo: i32,
+    pub conf: u64,
+    pub ema: u64,
+    pub publish_time: i64,
+}
+
+impl CustomOracle {
+    pub const LEN: usize = 8 + std::mem::size_of::<CustomOracle>();
+    // tolerated clock difference between the price publisher and the cluster
+    pub const MAX_PUBLISH_TIME_SKEW_SEC: i64 = 5;
+
+    pub fn validate_publish_time(publish_time: i64, curtime: i64) -> Result<()> {
+        if publish_time > math::checked_add(curtime, Self::MAX_PUBLISH_TIME_SKEW_SEC)? {
+            msg!("Error: Oracle publish time is in the future");
+            return err!(PerpetualsError::OraclePublishTimeInFuture);
+        }
+        Ok(())
+    }
+
+    pub fn set(&mut self, price: u64, expo: i32, conf: u64, ema: u64, publish_time: i64) {
+        self.price = price;
+        self.expo = expo;
+        self.conf = conf;
+        self.ema = ema;
+        self.publish_time = publish_time;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate_publish_time() {
+        let curtime = 1_000;
+
+        assert!(CustomOracle::validate_publish_time(curtime - 10, curtime).is_ok());
+        assert!(CustomOracle::validate_publish_time(curtime, curtime).is_ok());
+        assert!(CustomOracle::validate_publish_time(
+            curtime + CustomOracle::MAX_PUBLISH_TIME_SKEW_SEC,
+            curtime
+        )
+        .is_ok());
+        assert!(CustomOracle::validate_publish_time(
+            curtime + CustomOracle::MAX_PUBLISH_TIME_SKEW_SEC + 1,
+            curtime
+        )
+        .is_err());
+        assert!(CustomOracle::validate_publish_time(i64::MAX, curtime).is_err());
+    }
+}
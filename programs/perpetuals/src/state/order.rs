@@ -0,0 +1,102 @@
+use {crate::state::position::Side, anchor_lang::prelude::*};
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
+pub enum OrderType {
+    #[default]
+    Limit,
+    StopLoss,
+    TakeProfit,
+}
+
+#[account]
+#[derive(Default, Debug)]
+pub struct Order {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub custody: Pubkey,
+    pub collateral_custody: Pubkey,
+
+    pub order_id: u64,
+    // limit orders: id of the position to open, stop-loss and take-profit: position to close
+    pub position_id: u64,
+    pub order_type: OrderType,
+    pub side: Side,
+    pub trigger_price: u64,
+    // limit orders: position size in tokens, stop-loss and take-profit: size_usd to close
+    pub size: u64,
+    // amount escrowed in the order token account, limit orders only
+    pub collateral_amount: u64,
+    pub create_time: i64,
+
+    pub bump: u8,
+    pub token_account_bump: u8,
+}
+
+impl Order {
+    pub const LEN: usize = 8 + std::mem::size_of::<Order>();
+
+    pub fn validate(&self) -> bool {
+        self.side != Side::None
+            && self.trigger_price > 0
+            && self.size > 0
+            && (self.order_type == OrderType::Limit) == (self.collateral_amount > 0)
+    }
+
+    /// Returns true if the order can be executed at the given price (with PRICE_DECIMALS)
+    pub fn is_triggered(&self, price: u64) -> bool {
+        // limit longs buy low, stop-loss on longs and take-profit on shorts sell/buy back low
+        let below = matches!(
+            (self.order_type, self.side),
+            (OrderType::Limit, Side::Long)
+                | (OrderType::StopLoss, Side::Long)
+                | (OrderType::TakeProfit, Side::Short)
+        );
+        if below {
+            price <= self.trigger_price
+        } else {
+            price >= self.trigger_price
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_triggered() {
+        let mut order = Order {
+            order_type: OrderType::Limit,
+            side: Side::Long,
+            trigger_price: 20_000_000,
+            size: 1,
+            collateral_amount: 1,
+            ..Order::default()
+        };
+        assert!(order.validate());
+        assert!(order.is_triggered(19_000_000));
+        assert!(order.is_triggered(20_000_000));
+        assert!(!order.is_triggered(21_000_000));
+
+        order.side = Side::Short;
+        assert!(!order.is_triggered(19_000_000));
+        assert!(order.is_triggered(21_000_000));
+
+        order.order_type = OrderType::StopLoss;
+        order.collateral_amount = 0;
+        assert!(order.validate());
+        assert!(!order.is_triggered(19_000_000));
+        assert!(order.is_triggered(21_000_000));
+
+        order.order_type = OrderType::TakeProfit;
+        assert!(order.is_triggered(19_000_000));
+        assert!(!order.is_triggered(21_000_000));
+
+        order.side = Side::Long;
+        assert!(!order.is_triggered(19_000_000));
+        assert!(order.is_triggered(21_000_000));
+
+        order.collateral_amount = 1;
+        assert!(!order.validate());
+    }
+}
@@ -7,6 +7,8 @@ use {
 pub struct PriceAndFee {
     pub price: u64,
     pub fee: u64,
+    // price impact in BPS included in the price
+    pub price_impact: u64,
 }
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
@@ -20,6 +22,8 @@ pub struct NewPositionPricesAndFee {
     pub entry_price: u64,
     pub liquidation_price: u64,
     pub fee: u64,
+    // price impact in BPS included in the entry price
+    pub price_impact: u64,
 }
 
 #[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Default, Debug)]
@@ -47,6 +51,20 @@ pub struct Permissions {
     pub allow_size_change: bool,
 }
 
+impl Permissions {
+    /// Returns true if these permissions don't allow anything the current ones disallow
+    pub fn is_risk_reducing(&self, current: &Permissions) -> bool {
+        (!self.allow_swap || current.allow_swap)
+            && (!self.allow_add_liquidity || current.allow_add_liquidity)
+            && (!self.allow_remove_liquidity || current.allow_remove_liquidity)
+            && (!self.allow_open_position || current.allow_open_position)
+            && (!self.allow_close_position || current.allow_close_position)
+            && (!self.allow_pnl_withdrawal || current.allow_pnl_withdrawal)
+            && (!self.allow_collateral_withdrawal || current.allow_collateral_withdrawal)
+            && (!self.allow_size_change || current.allow_size_change)
+    }
+}
+
 #[account]
 #[derive(Default, Debug)]
 pub struct Perpetuals {
@@ -56,6 +74,8 @@ pub struct Perpetuals {
     pub perpetuals_bump: u8,
     // time of inception, also used as current wall clock time for testing
     pub inception_time: i64,
+    // seconds between a proposal being queued and becoming executable
+    pub proposal_delay: i64,
 }
 
 impl anchor_lang::Id for Perpetuals {
@@ -75,7 +95,7 @@ impl Perpetuals {
     pub const RATE_POWER: u128 = 10u64.pow(Self::RATE_DECIMALS as u32) as u128;
 
     pub fn validate(&self) -> bool {
-        true
+        self.proposal_delay >= 0
     }
 
     #[cfg(feature = "test")]
@@ -282,3 +302,30 @@ impl Perpetuals {
             .map_err(|_| ProgramError::InvalidRealloc.into())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_permissions_risk_reducing() {
+        let current = Permissions {
+            allow_swap: true,
+            allow_open_position: true,
+            ..Default::default()
+        };
+
+        assert!(current.is_risk_reducing(&current));
+        assert!(Permissions::default().is_risk_reducing(&current));
+        assert!(Permissions {
+            allow_swap: true,
+            ..Default::default()
+        }
+        .is_risk_reducing(&current));
+        assert!(!Permissions {
+            allow_close_position: true,
+            ..Default::default()
+        }
+        .is_risk_reducing(&current));
+    }
+}
@@ -6,7 +6,8 @@ use {
         oracle::OraclePrice,
         state::{
             custody::{Custody, FeesMode},
-            perpetuals::Perpetuals,
+            margin_account::MarginAccount,
+            perpetuals::{Permissions, Perpetuals},
             position::{Position, Side},
         },
     },
@@ -40,6 +41,27 @@ pub struct Pool {
     pub bump: u8,
     pub lp_token_bump: u8,
     pub inception_time: i64,
+    // total open interest across all custodies of the pool
+    pub oi_usd: u64,
+    // USD denominated cap on oi_usd, zero means no limit
+    pub max_oi_usd: u64,
+    // pool level permissions, checked together with protocol and custody permissions
+    pub permissions: Permissions,
+    // layout version, zero for pools in the original layout
+    pub version: u8,
+}
+
+/// Original pool layout, pools are migrated to the current layout with upgrade_pool
+#[account]
+#[derive(Default, Debug)]
+pub struct DeprecatedPool {
+    pub name: String,
+    pub custodies: Vec<Pubkey>,
+    pub ratios: Vec<TokenRatios>,
+    pub aum_usd: u128,
+    pub bump: u8,
+    pub lp_token_bump: u8,
+    pub inception_time: i64,
 }
 
 impl TokenRatios {
@@ -58,6 +80,7 @@ impl TokenRatios {
 ///
 impl Pool {
     pub const LEN: usize = 8 + 64 + std::mem::size_of::<Pool>();
+    pub const VERSION: u8 = 1;
 
     pub fn validate(&self) -> bool {
         for ratio in &self.ratios {
@@ -100,17 +123,17 @@ impl Pool {
         token_price: &OraclePrice,
         token_ema_price: &OraclePrice,
         side: Side,
+        size: u64,
         custody: &Custody,
     ) -> Result<u64> {
+        let size_usd = token_ema_price.get_asset_amount_usd(size, custody.decimals)?;
+        let price_impact = self.get_price_impact(token_ema_price, size_usd, custody)?;
+
         let price = self.get_price(
             token_price,
             token_ema_price,
             side,
-            if side == Side::Long {
-                custody.pricing.trade_spread_long
-            } else {
-                custody.pricing.trade_spread_short
-            },
+            math::checked_add(self.get_trade_spread(side, custody)?, price_impact)?,
         )?;
         require_gt!(price.price, 0, PerpetualsError::MaxPriceSlippage);
 
@@ -179,8 +202,11 @@ impl Pool {
         token_price: &OraclePrice,
         token_ema_price: &OraclePrice,
         side: Side,
+        size_usd: u64,
         custody: &Custody,
     ) -> Result<u64> {
+        let price_impact = self.get_price_impact(token_ema_price, size_usd, custody)?;
+
         let price = self.get_price(
             token_price,
             token_ema_price,
@@ -189,11 +215,17 @@ impl Pool {
             } else {
                 Side::Long
             },
-            if side == Side::Long {
-                custody.pricing.trade_spread_short
-            } else {
-                custody.pricing.trade_spread_long
-            },
+            math::checked_add(
+                self.get_trade_spread(
+                    if side == Side::Long {
+                        Side::Short
+                    } else {
+                        Side::Long
+                    },
+                    custody,
+                )?,
+                price_impact,
+            )?,
         )?;
 
         Ok(price
@@ -201,6 +233,80 @@ impl Pool {
             .price)
     }
 
+    // trades that add to the crowded side pay the static spread plus skew_spread_mult scaled
+    // by the long/short imbalance, trades that reduce the imbalance get the same discount
+    // down to trade_spread_min, side is the direction of the trade (exiting a long is Short)
+    pub fn get_trade_spread(&self, side: Side, custody: &Custody) -> Result<u64> {
+        let spread = if side == Side::Long {
+            custody.pricing.trade_spread_long
+        } else {
+            custody.pricing.trade_spread_short
+        };
+
+        let long_size_usd = custody.long_positions.size_usd;
+        let short_size_usd = custody.short_positions.size_usd;
+        if custody.pricing.skew_spread_mult == 0 || long_size_usd == short_size_usd {
+            return Ok(spread);
+        }
+
+        // skew = |long - short| / (long + short)
+        let total_size_usd = math::checked_add(long_size_usd as u128, short_size_usd as u128)?;
+        let skew = math::checked_div(
+            math::checked_mul(
+                long_size_usd.abs_diff(short_size_usd) as u128,
+                Perpetuals::BPS_POWER,
+            )?,
+            total_size_usd,
+        )?;
+        let skew_spread = math::checked_as_u64(math::checked_div(
+            math::checked_mul(custody.pricing.skew_spread_mult as u128, skew)?,
+            Perpetuals::BPS_POWER,
+        )?)?;
+
+        let crowded = if side == Side::Long {
+            long_size_usd > short_size_usd
+        } else {
+            short_size_usd > long_size_usd
+        };
+
+        if crowded {
+            math::checked_add(spread, skew_spread)
+        } else {
+            Ok(std::cmp::max(
+                spread.saturating_sub(skew_spread),
+                custody.pricing.trade_spread_min,
+            ))
+        }
+    }
+
+    // price impact in BPS grows linearly with the trade size relative to the custody's
+    // owned assets and is capped at price_impact_max
+    pub fn get_price_impact(
+        &self,
+        token_price: &OraclePrice,
+        size_usd: u64,
+        custody: &Custody,
+    ) -> Result<u64> {
+        if custody.pricing.price_impact_mult == 0 || size_usd == 0 {
+            return Ok(0);
+        }
+
+        let owned_usd = token_price.get_asset_amount_usd(custody.assets.owned, custody.decimals)?;
+        if owned_usd == 0 {
+            return Ok(custody.pricing.price_impact_max);
+        }
+
+        let price_impact = math::checked_div(
+            math::checked_mul(size_usd as u128, custody.pricing.price_impact_mult as u128)?,
+            owned_usd as u128,
+        )?;
+
+        math::checked_as_u64(std::cmp::min(
+            price_impact,
+            custody.pricing.price_impact_max as u128,
+        ))
+    }
+
     pub fn get_exit_fee(&self, size: u64, custody: &Custody) -> Result<u64> {
         Self::get_fee_amount(custody.fees.close_position, size)
     }
@@ -397,6 +503,41 @@ impl Pool {
         Self::get_fee_amount(custody.fees.liquidation, size)
     }
 
+    pub fn get_liquidation_reward(
+        &self,
+        amount: u64,
+        custody: &Custody,
+        liquidatable_since: i64,
+        curtime: i64,
+    ) -> Result<u64> {
+        let fees = &custody.fees;
+        if fees.liquidation_reward_window_sec == 0 {
+            return Self::get_fee_amount(fees.liquidation, amount);
+        }
+
+        let elapsed = if liquidatable_since > 0 && curtime > liquidatable_since {
+            std::cmp::min(
+                math::checked_sub(curtime, liquidatable_since)? as u64,
+                fees.liquidation_reward_window_sec,
+            )
+        } else {
+            0
+        };
+        let reward_rate = math::checked_add(
+            fees.liquidation_reward_min,
+            math::checked_as_u64(math::checked_div(
+                math::checked_mul(
+                    math::checked_sub(fees.liquidation_reward_max, fees.liquidation_reward_min)?
+                        as u128,
+                    elapsed as u128,
+                )?,
+                fees.liquidation_reward_window_sec as u128,
+            )?)?,
+        )?;
+
+        Self::get_fee_amount(reward_rate, amount)
+    }
+
     pub fn check_token_ratio(
         &self,
         token_id: usize,
@@ -416,6 +557,21 @@ impl Pool {
         }
     }
 
+    pub fn add_open_interest(&mut self, size_usd: u64) -> Result<()> {
+        self.oi_usd = math::checked_add(self.oi_usd, size_usd)?;
+        if self.max_oi_usd > 0 {
+            require!(
+                self.oi_usd <= self.max_oi_usd,
+                PerpetualsError::MaxPoolOpenInterest
+            );
+        }
+        Ok(())
+    }
+
+    pub fn remove_open_interest(&mut self, size_usd: u64) {
+        self.oi_usd = self.oi_usd.saturating_sub(size_usd);
+    }
+
     pub fn check_available_amount(&self, amount: u64, custody: &Custody) -> Result<bool> {
         let available_amount = math::checked_sub(
             math::checked_add(custody.assets.owned, custody.assets.collateral)?,
@@ -424,6 +580,420 @@ impl Pool {
         Ok(available_amount >= amount)
     }
 
+    // returns size_usd, locked amount, borrow size and entry fee of a new position,
+    // the fee amount is denominated in the collateral token
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_open_position_amounts(
+        &self,
+        side: Side,
+        size: u64,
+        position_price: u64,
+        token_ema_price: &OraclePrice,
+        custody: &Custody,
+        collateral_token_price: &OraclePrice,
+        collateral_token_ema_price: &OraclePrice,
+        collateral_custody: &Custody,
+    ) -> Result<(u64, u64, u64, u64, u64)> {
+        let use_collateral_custody = side == Side::Short || custody.is_virtual;
+        let position_oracle_price =
+            OraclePrice::new(position_price, -(Perpetuals::PRICE_DECIMALS as i32));
+        let size_usd = position_oracle_price.get_asset_amount_usd(size, custody.decimals)?;
+
+        let locked_amount = if use_collateral_custody {
+            let min_collateral_price = collateral_token_price
+                .get_min_price(collateral_token_ema_price, collateral_custody.is_stable)?;
+            custody.get_locked_amount(
+                min_collateral_price.get_token_amount(size_usd, collateral_custody.decimals)?,
+                side,
+            )?
+        } else {
+            custody.get_locked_amount(size, side)?
+        };
+
+        let borrow_size_usd = if custody.pricing.max_payoff_mult as u128 != Perpetuals::BPS_POWER {
+            if use_collateral_custody {
+                let max_collateral_price = if collateral_token_price < collateral_token_ema_price {
+                    collateral_token_ema_price
+                } else {
+                    collateral_token_price
+                };
+                max_collateral_price
+                    .get_asset_amount_usd(locked_amount, collateral_custody.decimals)?
+            } else {
+                position_oracle_price.get_asset_amount_usd(locked_amount, custody.decimals)?
+            }
+        } else {
+            size_usd
+        };
+
+        let mut fee_amount = self.get_entry_fee(
+            custody.fees.open_position,
+            size,
+            locked_amount,
+            collateral_custody,
+        )?;
+        let fee_amount_usd = token_ema_price.get_asset_amount_usd(fee_amount, custody.decimals)?;
+        if use_collateral_custody {
+            fee_amount = collateral_token_ema_price
+                .get_token_amount(fee_amount_usd, collateral_custody.decimals)?;
+        }
+
+        Ok((
+            size_usd,
+            locked_amount,
+            borrow_size_usd,
+            fee_amount,
+            fee_amount_usd,
+        ))
+    }
+
+    // locks funds and records a new position in custody and pool stats, returns
+    // the insurance fund share of the fee for the caller to transfer
+    #[allow(clippy::too_many_arguments)]
+    pub fn settle_open_position(
+        &mut self,
+        position: &Position,
+        fee_amount: u64,
+        fee_amount_usd: u64,
+        token_ema_price: &OraclePrice,
+        custody: &mut Custody,
+        collateral_custody: &mut Custody,
+        curtime: i64,
+    ) -> Result<u64> {
+        // lock funds for potential profit payoff
+        collateral_custody.lock_funds(position.locked_amount)?;
+
+        collateral_custody.collected_fees.open_position_usd = collateral_custody
+            .collected_fees
+            .open_position_usd
+            .wrapping_add(fee_amount_usd);
+
+        collateral_custody.assets.collateral = math::checked_add(
+            collateral_custody.assets.collateral,
+            position.collateral_amount,
+        )?;
+
+        let protocol_fee = Self::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
+        collateral_custody.assets.protocol_fees =
+            math::checked_add(collateral_custody.assets.protocol_fees, protocol_fee)?;
+
+        let insurance_fee = Self::get_fee_amount(custody.fees.insurance_share, fee_amount)?;
+
+        // if custody and collateral_custody accounts are the same, ensure that data is in sync
+        if position.side == Side::Long && !custody.is_virtual {
+            collateral_custody.volume_stats.open_position_usd = collateral_custody
+                .volume_stats
+                .open_position_usd
+                .wrapping_add(position.size_usd);
+
+            collateral_custody.trade_stats.oi_long_usd = math::checked_add(
+                collateral_custody.trade_stats.oi_long_usd,
+                position.size_usd,
+            )?;
+
+            collateral_custody.add_position(position, token_ema_price, curtime, None)?;
+            collateral_custody.update_borrow_rate(curtime)?;
+            collateral_custody.update_funding_rate(curtime)?;
+            *custody = collateral_custody.clone();
+        } else {
+            custody.volume_stats.open_position_usd = custody
+                .volume_stats
+                .open_position_usd
+                .wrapping_add(position.size_usd);
+
+            if position.side == Side::Long {
+                custody.trade_stats.oi_long_usd =
+                    math::checked_add(custody.trade_stats.oi_long_usd, position.size_usd)?;
+            } else {
+                custody.trade_stats.oi_short_usd =
+                    math::checked_add(custody.trade_stats.oi_short_usd, position.size_usd)?;
+            }
+
+            custody.add_position(position, token_ema_price, curtime, Some(collateral_custody))?;
+            collateral_custody.update_borrow_rate(curtime)?;
+            custody.update_funding_rate(curtime)?;
+        }
+
+        // check open interest limits
+        custody.check_open_interest(position.side)?;
+        self.add_open_interest(position.size_usd)?;
+
+        Ok(insurance_fee)
+    }
+
+    // unlocks funds of the closed part of the position and replaces the position with
+    // the remaining part in custody and pool stats, `amount_out` is the total amount paid
+    // out of the custody, fees and volume are recorded as liquidation stats if `liquidation`
+    // is set, returns the insurance fund share of the fee for the caller to transfer
+    #[allow(clippy::too_many_arguments)]
+    pub fn settle_close_position(
+        &mut self,
+        position: &Position,
+        closed_position: &Position,
+        remaining_position: &Position,
+        amount_out: u64,
+        fee_amount: u64,
+        fee_amount_usd: u64,
+        profit_usd: u64,
+        loss_usd: u64,
+        token_ema_price: &OraclePrice,
+        custody: &mut Custody,
+        collateral_custody: &mut Custody,
+        curtime: i64,
+        liquidation: bool,
+    ) -> Result<u64> {
+        // unlock pool funds
+        collateral_custody.unlock_funds(closed_position.locked_amount)?;
+
+        // check pool constraints
+        require!(
+            self.check_available_amount(amount_out, collateral_custody)?,
+            PerpetualsError::CustodyAmountLimit
+        );
+
+        if liquidation {
+            collateral_custody.collected_fees.liquidation_usd = collateral_custody
+                .collected_fees
+                .liquidation_usd
+                .wrapping_add(fee_amount_usd);
+        } else {
+            collateral_custody.collected_fees.close_position_usd = collateral_custody
+                .collected_fees
+                .close_position_usd
+                .wrapping_add(fee_amount_usd);
+        }
+
+        if amount_out > closed_position.collateral_amount {
+            let amount_lost = amount_out.saturating_sub(closed_position.collateral_amount);
+            collateral_custody.assets.owned =
+                math::checked_sub(collateral_custody.assets.owned, amount_lost)?;
+        } else {
+            let amount_gained = closed_position.collateral_amount.saturating_sub(amount_out);
+            collateral_custody.assets.owned =
+                math::checked_add(collateral_custody.assets.owned, amount_gained)?;
+        }
+        collateral_custody.assets.collateral = math::checked_sub(
+            collateral_custody.assets.collateral,
+            closed_position.collateral_amount,
+        )?;
+
+        // pay protocol fee from custody if possible, otherwise no protocol fee
+        let protocol_fee = Self::get_fee_amount(custody.fees.protocol_share, fee_amount)?;
+        if self.check_available_amount(protocol_fee, collateral_custody)? {
+            collateral_custody.assets.protocol_fees =
+                math::checked_add(collateral_custody.assets.protocol_fees, protocol_fee)?;
+
+            collateral_custody.assets.owned =
+                math::checked_sub(collateral_custody.assets.owned, protocol_fee)?;
+        }
+
+        // fund insurance from custody if possible, same as protocol fee
+        let mut insurance_fee = Self::get_fee_amount(custody.fees.insurance_share, fee_amount)?;
+        if insurance_fee > 0 && self.check_available_amount(insurance_fee, collateral_custody)? {
+            collateral_custody.assets.owned =
+                math::checked_sub(collateral_custody.assets.owned, insurance_fee)?;
+        } else {
+            insurance_fee = 0;
+        }
+
+        // if custody and collateral_custody accounts are the same, ensure that data is in sync
+        let partial_close = remaining_position.size_usd > 0;
+        if position.side == Side::Long && !custody.is_virtual {
+            if liquidation {
+                collateral_custody.volume_stats.liquidation_usd = collateral_custody
+                    .volume_stats
+                    .liquidation_usd
+                    .wrapping_add(closed_position.size_usd);
+            } else {
+                collateral_custody.volume_stats.close_position_usd = collateral_custody
+                    .volume_stats
+                    .close_position_usd
+                    .wrapping_add(closed_position.size_usd);
+            }
+
+            collateral_custody.trade_stats.oi_long_usd = collateral_custody
+                .trade_stats
+                .oi_long_usd
+                .saturating_sub(closed_position.size_usd);
+
+            collateral_custody.trade_stats.profit_usd = collateral_custody
+                .trade_stats
+                .profit_usd
+                .wrapping_add(profit_usd);
+            collateral_custody.trade_stats.loss_usd = collateral_custody
+                .trade_stats
+                .loss_usd
+                .wrapping_add(loss_usd);
+
+            collateral_custody.remove_position(position, curtime, None)?;
+            if partial_close {
+                collateral_custody.add_position(
+                    remaining_position,
+                    token_ema_price,
+                    curtime,
+                    None,
+                )?;
+            }
+            collateral_custody.update_borrow_rate(curtime)?;
+            collateral_custody.update_funding_rate(curtime)?;
+            *custody = collateral_custody.clone();
+        } else {
+            if liquidation {
+                custody.volume_stats.liquidation_usd = custody
+                    .volume_stats
+                    .liquidation_usd
+                    .wrapping_add(closed_position.size_usd);
+            } else {
+                custody.volume_stats.close_position_usd = custody
+                    .volume_stats
+                    .close_position_usd
+                    .wrapping_add(closed_position.size_usd);
+            }
+
+            if position.side == Side::Long {
+                custody.trade_stats.oi_long_usd = custody
+                    .trade_stats
+                    .oi_long_usd
+                    .saturating_sub(closed_position.size_usd);
+            } else {
+                custody.trade_stats.oi_short_usd = custody
+                    .trade_stats
+                    .oi_short_usd
+                    .saturating_sub(closed_position.size_usd);
+            }
+
+            custody.trade_stats.profit_usd =
+                custody.trade_stats.profit_usd.wrapping_add(profit_usd);
+            custody.trade_stats.loss_usd = custody.trade_stats.loss_usd.wrapping_add(loss_usd);
+
+            custody.remove_position(position, curtime, Some(collateral_custody))?;
+            if partial_close {
+                custody.add_position(
+                    remaining_position,
+                    token_ema_price,
+                    curtime,
+                    Some(collateral_custody),
+                )?;
+            }
+            collateral_custody.update_borrow_rate(curtime)?;
+            custody.update_funding_rate(curtime)?;
+        }
+
+        self.remove_open_interest(closed_position.size_usd);
+
+        Ok(insurance_fee)
+    }
+
+    // swaps `amount_in` of the receiving custody token into the dispensing custody token,
+    // checks pool constraints and updates custody stats, returns the amount to be
+    // transferred out net of fees and the fees collected in both tokens
+    #[allow(clippy::too_many_arguments)]
+    pub fn settle_swap(
+        &self,
+        token_id_in: usize,
+        token_id_out: usize,
+        amount_in: u64,
+        received_token_price: &OraclePrice,
+        received_token_ema_price: &OraclePrice,
+        receiving_custody: &mut Custody,
+        dispensed_token_price: &OraclePrice,
+        dispensed_token_ema_price: &OraclePrice,
+        dispensing_custody: &mut Custody,
+        curtime: i64,
+    ) -> Result<(u64, (u64, u64))> {
+        let amount_out = self.get_swap_amount(
+            received_token_price,
+            received_token_ema_price,
+            dispensed_token_price,
+            dispensed_token_ema_price,
+            receiving_custody,
+            dispensing_custody,
+            amount_in,
+        )?;
+
+        let fees = self.get_swap_fees(
+            token_id_in,
+            token_id_out,
+            amount_in,
+            amount_out,
+            receiving_custody,
+            received_token_price,
+            dispensing_custody,
+            dispensed_token_price,
+        )?;
+        let no_fee_amount = math::checked_sub(amount_out, fees.1)?;
+
+        // check pool constraints
+        let protocol_fee_in = Self::get_fee_amount(receiving_custody.fees.protocol_share, fees.0)?;
+        let protocol_fee_out =
+            Self::get_fee_amount(dispensing_custody.fees.protocol_share, fees.1)?;
+        let deposit_amount = math::checked_sub(amount_in, protocol_fee_in)?;
+        let withdrawal_amount = math::checked_add(no_fee_amount, protocol_fee_out)?;
+
+        require!(
+            self.check_token_ratio(
+                token_id_in,
+                deposit_amount,
+                0,
+                receiving_custody,
+                received_token_price
+            )? && self.check_token_ratio(
+                token_id_out,
+                0,
+                withdrawal_amount,
+                dispensing_custody,
+                dispensed_token_price
+            )?,
+            PerpetualsError::TokenRatioOutOfRange
+        );
+        require!(
+            math::checked_sub(
+                dispensing_custody.assets.owned,
+                dispensing_custody.assets.locked
+            )? >= withdrawal_amount,
+            PerpetualsError::CustodyAmountLimit
+        );
+
+        // update custody stats
+        receiving_custody.volume_stats.swap_usd =
+            receiving_custody.volume_stats.swap_usd.wrapping_add(
+                received_token_price.get_asset_amount_usd(amount_in, receiving_custody.decimals)?,
+            );
+
+        receiving_custody.collected_fees.swap_usd =
+            receiving_custody.collected_fees.swap_usd.wrapping_add(
+                received_token_price.get_asset_amount_usd(fees.0, receiving_custody.decimals)?,
+            );
+
+        receiving_custody.assets.owned =
+            math::checked_add(receiving_custody.assets.owned, deposit_amount)?;
+
+        receiving_custody.assets.protocol_fees =
+            math::checked_add(receiving_custody.assets.protocol_fees, protocol_fee_in)?;
+
+        dispensing_custody.collected_fees.swap_usd =
+            dispensing_custody.collected_fees.swap_usd.wrapping_add(
+                dispensed_token_price.get_asset_amount_usd(fees.1, dispensing_custody.decimals)?,
+            );
+
+        dispensing_custody.volume_stats.swap_usd =
+            dispensing_custody.volume_stats.swap_usd.wrapping_add(
+                dispensed_token_price
+                    .get_asset_amount_usd(amount_out, dispensing_custody.decimals)?,
+            );
+
+        dispensing_custody.assets.protocol_fees =
+            math::checked_add(dispensing_custody.assets.protocol_fees, protocol_fee_out)?;
+
+        dispensing_custody.assets.owned =
+            math::checked_sub(dispensing_custody.assets.owned, withdrawal_amount)?;
+
+        receiving_custody.update_borrow_rate(curtime)?;
+        dispensing_custody.update_borrow_rate(curtime)?;
+
+        Ok((no_fee_amount, fees))
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn get_leverage(
         &self,
@@ -496,6 +1066,330 @@ impl Pool {
                     && current_leverage <= custody.pricing.max_initial_leverage)))
     }
 
+    /// Returns aggregate equity and maintenance margin of the margin account in USD.
+    /// Equity is the margin collateral at the min price plus collateral and pnl of each linked
+    /// position, maintenance margin is the sum of position sizes over their max leverage.
+    /// Linked positions, margin collateral custodies and all their oracles must be in the map.
+    pub fn get_margin_account_health(
+        &self,
+        margin_account_key: &Pubkey,
+        margin_account: &MarginAccount,
+        accounts: &AccountMap,
+        clock: &Clock,
+        curtime: i64,
+    ) -> Result<(u64, u64)> {
+        let mut equity_usd: i128 = 0;
+        let mut margin_usd: u128 = 0;
+        let mut collateral_custodies: Vec<Pubkey> = Vec::new();
+
+        for position_key in margin_account.positions.iter() {
+            // closed or re-opened positions are no longer part of the account
+            let position = match Account::<Position>::try_from(accounts.get_account(position_key)?)
+            {
+                Ok(position) if position.margin_account == *margin_account_key => position,
+                _ => continue,
+            };
+            require_keys_eq!(
+                position.pool,
+                margin_account.pool,
+                PerpetualsError::InvalidPositionState
+            );
+
+            let custody = Account::<Custody>::try_from(accounts.get_account(&position.custody)?)?;
+            let collateral_custody =
+                Account::<Custody>::try_from(accounts.get_account(&position.collateral_custody)?)?;
+            if !collateral_custodies.contains(&position.collateral_custody) {
+                collateral_custodies.push(position.collateral_custody);
+            }
+            let (token_price, token_ema_price) =
+                Self::get_custody_prices(&custody, accounts, clock)?;
+            let (collateral_token_price, collateral_token_ema_price) =
+                Self::get_custody_prices(&collateral_custody, accounts, clock)?;
+
+            let (profit_usd, loss_usd, _) = self.get_pnl_usd(
+                &position,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                &collateral_token_price,
+                &collateral_token_ema_price,
+                &collateral_custody,
+                curtime,
+                false,
+            )?;
+
+            equity_usd = math::checked_add(
+                equity_usd,
+                math::checked_sub(
+                    math::checked_add(position.collateral_usd as i128, profit_usd as i128)?,
+                    loss_usd as i128,
+                )?,
+            )?;
+            margin_usd = math::checked_add(
+                margin_usd,
+                math::checked_div(
+                    math::checked_mul(position.size_usd as u128, Perpetuals::BPS_POWER)?,
+                    custody.pricing.max_leverage as u128,
+                )?,
+            )?;
+        }
+
+        // only collateral held in collateral custodies of linked positions can be seized
+        // to cover their losses, the rest doesn't back the positions
+        for collateral in margin_account.collaterals.iter() {
+            if !collateral_custodies.contains(&collateral.custody) {
+                continue;
+            }
+            let custody = Account::<Custody>::try_from(accounts.get_account(&collateral.custody)?)?;
+            let (token_price, token_ema_price) =
+                Self::get_custody_prices(&custody, accounts, clock)?;
+            let min_price = token_price.get_min_price(&token_ema_price, custody.is_stable)?;
+
+            equity_usd = math::checked_add(
+                equity_usd,
+                min_price.get_asset_amount_usd(collateral.amount, custody.decimals)? as i128,
+            )?;
+        }
+
+        Ok((
+            math::checked_as_u64(std::cmp::max(equity_usd, 0))?,
+            math::checked_as_u64(margin_usd)?,
+        ))
+    }
+
+    // returns loss of the closed position in excess of its collateral, in collateral tokens
+    pub fn get_bad_debt_amount(
+        &self,
+        closed_position: &Position,
+        loss_usd: u64,
+        collateral_token_price: &OraclePrice,
+        collateral_token_ema_price: &OraclePrice,
+        collateral_custody: &Custody,
+    ) -> Result<u64> {
+        let bad_debt_usd = loss_usd.saturating_sub(closed_position.collateral_usd);
+        if bad_debt_usd == 0 {
+            return Ok(0);
+        }
+
+        let max_collateral_price = if collateral_token_price > collateral_token_ema_price {
+            collateral_token_price
+        } else {
+            collateral_token_ema_price
+        };
+        max_collateral_price.get_token_amount(bad_debt_usd, collateral_custody.decimals)
+    }
+
+    pub fn check_margin_account_health(
+        &self,
+        margin_account_key: &Pubkey,
+        margin_account: &MarginAccount,
+        accounts: &AccountMap,
+        clock: &Clock,
+        curtime: i64,
+    ) -> Result<bool> {
+        let (equity_usd, margin_usd) = self.get_margin_account_health(
+            margin_account_key,
+            margin_account,
+            accounts,
+            clock,
+            curtime,
+        )?;
+
+        Ok(equity_usd >= margin_usd)
+    }
+
+    // returns position size to close on liquidation, the whole position is closed if partial
+    // liquidations are disabled or the equity is below the maintenance margin
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_liquidation_size(
+        &self,
+        position: &Position,
+        token_price: &OraclePrice,
+        token_ema_price: &OraclePrice,
+        custody: &Custody,
+        collateral_token_price: &OraclePrice,
+        collateral_token_ema_price: &OraclePrice,
+        collateral_custody: &Custody,
+        curtime: i64,
+    ) -> Result<u64> {
+        let target_leverage = custody.pricing.liquidation_target_leverage;
+        if target_leverage == 0 {
+            return Ok(position.size_usd);
+        }
+
+        let (profit_usd, loss_usd, _) = self.get_pnl_usd(
+            position,
+            token_price,
+            token_ema_price,
+            custody,
+            collateral_token_price,
+            collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+            false,
+        )?;
+
+        let current_margin_usd = if profit_usd > 0 {
+            math::checked_add(position.collateral_usd, profit_usd)?
+        } else if loss_usd <= position.collateral_usd {
+            math::checked_sub(position.collateral_usd, loss_usd)?
+        } else {
+            0
+        };
+
+        let maintenance_margin_usd = math::checked_div(
+            math::checked_mul(
+                position.size_usd as u128,
+                custody.pricing.maintenance_margin as u128,
+            )?,
+            Perpetuals::BPS_POWER,
+        )?;
+        if current_margin_usd == 0 || (current_margin_usd as u128) < maintenance_margin_usd {
+            return Ok(position.size_usd);
+        }
+
+        let target_size_usd = math::checked_as_u64(math::checked_div(
+            math::checked_mul(current_margin_usd as u128, target_leverage as u128)?,
+            Perpetuals::BPS_POWER,
+        )?)?;
+
+        Ok(position.size_usd.saturating_sub(target_size_usd))
+    }
+
+    // ranks positions for auto-deleveraging, returns profit-to-collateral ratio
+    // multiplied by leverage with implied BPS_DECIMALS decimals
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_adl_score(
+        &self,
+        position: &Position,
+        token_price: &OraclePrice,
+        token_ema_price: &OraclePrice,
+        custody: &Custody,
+        collateral_token_price: &OraclePrice,
+        collateral_token_ema_price: &OraclePrice,
+        collateral_custody: &Custody,
+        curtime: i64,
+    ) -> Result<u128> {
+        let (profit_usd, _, _) = self.get_pnl_usd(
+            position,
+            token_price,
+            token_ema_price,
+            custody,
+            collateral_token_price,
+            collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+            false,
+        )?;
+        if profit_usd == 0 {
+            return Ok(0);
+        }
+        if position.collateral_usd == 0 {
+            return Ok(u128::MAX);
+        }
+
+        let profit_ratio = math::checked_div(
+            math::checked_mul(profit_usd as u128, Perpetuals::BPS_POWER)?,
+            position.collateral_usd as u128,
+        )?;
+        let leverage = self.get_leverage(
+            position,
+            token_price,
+            token_ema_price,
+            custody,
+            collateral_token_price,
+            collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+        )?;
+
+        math::checked_div(
+            math::checked_mul(profit_ratio, leverage as u128)?,
+            Perpetuals::BPS_POWER,
+        )
+    }
+
+    // minimum score a position must have to be auto-deleveraged, equal to the score
+    // of all open positions on the given side aggregated into one
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_adl_score_threshold(
+        &self,
+        side: Side,
+        token_price: &OraclePrice,
+        token_ema_price: &OraclePrice,
+        custody: &Custody,
+        collateral_token_price: &OraclePrice,
+        collateral_token_ema_price: &OraclePrice,
+        collateral_custody: &Custody,
+        curtime: i64,
+    ) -> Result<u128> {
+        let mut collective_position = custody.get_collective_position(side)?;
+        collective_position.collateral_usd = if side == Side::Long {
+            custody.long_positions.collateral_usd
+        } else {
+            custody.short_positions.collateral_usd
+        };
+
+        self.get_adl_score(
+            &collective_position,
+            token_price,
+            token_ema_price,
+            custody,
+            collateral_token_price,
+            collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+        )
+    }
+
+    // checks if collateral custody assets net of unrealized profits of all positions
+    // on the given side have fallen below the auto-deleveraging threshold
+    #[allow(clippy::too_many_arguments)]
+    pub fn check_adl_threshold(
+        &self,
+        side: Side,
+        token_price: &OraclePrice,
+        token_ema_price: &OraclePrice,
+        custody: &Custody,
+        collateral_token_price: &OraclePrice,
+        collateral_token_ema_price: &OraclePrice,
+        collateral_custody: &Custody,
+        curtime: i64,
+    ) -> Result<bool> {
+        if collateral_custody.pricing.adl_threshold == 0 {
+            return Ok(false);
+        }
+
+        let (pending_profit_usd, _, _) = self.get_pnl_usd(
+            &custody.get_collective_position(side)?,
+            token_price,
+            token_ema_price,
+            custody,
+            collateral_token_price,
+            collateral_token_ema_price,
+            collateral_custody,
+            curtime,
+            false,
+        )?;
+
+        let min_collateral_price = collateral_token_price
+            .get_min_price(collateral_token_ema_price, collateral_custody.is_stable)?;
+        let pending_payoff = min_collateral_price
+            .get_token_amount(pending_profit_usd, collateral_custody.decimals)?;
+        let remaining_amount = collateral_custody
+            .assets
+            .owned
+            .saturating_sub(pending_payoff);
+
+        Ok(
+            math::checked_mul(remaining_amount as u128, Perpetuals::BPS_POWER)?
+                < math::checked_mul(
+                    collateral_custody.assets.owned as u128,
+                    collateral_custody.pricing.adl_threshold as u128,
+                )?,
+        )
+    }
+
     pub fn get_liquidation_price(
         &self,
         position: &Position,
@@ -515,10 +1409,17 @@ impl Pool {
         let exit_fee_usd =
             token_ema_price.get_asset_amount_usd(exit_fee_tokens, custody.decimals)?;
         let interest_usd = collateral_custody.get_interest_amount_usd(position, curtime)?;
+        let (funding_profit_usd, funding_loss_usd) =
+            Self::get_funding_pnl_usd(position, custody, curtime)?;
         let unrealized_loss_usd = math::checked_add(
-            math::checked_add(exit_fee_usd, interest_usd)?,
+            math::checked_add(
+                math::checked_add(exit_fee_usd, interest_usd)?,
+                funding_loss_usd,
+            )?,
             position.unrealized_loss_usd,
         )?;
+        let unrealized_profit_usd =
+            math::checked_add(position.unrealized_profit_usd, funding_profit_usd)?;
 
         let max_loss_usd = math::checked_as_u64(math::checked_div(
             math::checked_mul(position.size_usd as u128, Perpetuals::BPS_POWER)?,
@@ -526,8 +1427,7 @@ impl Pool {
         )?)?;
         let max_loss_usd = math::checked_add(max_loss_usd, unrealized_loss_usd)?;
 
-        let margin_usd =
-            math::checked_add(position.collateral_usd, position.unrealized_profit_usd)?;
+        let margin_usd = math::checked_add(position.collateral_usd, unrealized_profit_usd)?;
 
         let max_price_diff = if max_loss_usd >= margin_usd {
             math::checked_sub(max_loss_usd, margin_usd)?
@@ -589,8 +1489,13 @@ impl Pool {
             return Ok((0, 0, 0));
         }
 
-        let exit_price =
-            self.get_exit_price(token_price, token_ema_price, position.side, custody)?;
+        let exit_price = self.get_exit_price(
+            token_price,
+            token_ema_price,
+            position.side,
+            position.size_usd,
+            custody,
+        )?;
 
         let size = token_ema_price.get_token_amount(position.size_usd, custody.decimals)?;
 
@@ -602,10 +1507,17 @@ impl Pool {
 
         let exit_fee_usd = token_ema_price.get_asset_amount_usd(exit_fee, custody.decimals)?;
         let interest_usd = collateral_custody.get_interest_amount_usd(position, curtime)?;
+        let (funding_profit_usd, funding_loss_usd) =
+            Self::get_funding_pnl_usd(position, custody, curtime)?;
         let unrealized_loss_usd = math::checked_add(
-            math::checked_add(exit_fee_usd, interest_usd)?,
+            math::checked_add(
+                math::checked_add(exit_fee_usd, interest_usd)?,
+                funding_loss_usd,
+            )?,
             position.unrealized_loss_usd,
         )?;
+        let unrealized_profit_usd =
+            math::checked_add(position.unrealized_profit_usd, funding_profit_usd)?;
 
         let (price_diff_profit, price_diff_loss) = if position.side == Side::Long {
             if exit_price > position.price {
@@ -632,7 +1544,7 @@ impl Pool {
             )?)?;
 
             let potential_profit_usd =
-                math::checked_add(potential_profit_usd, position.unrealized_profit_usd)?;
+                math::checked_add(potential_profit_usd, unrealized_profit_usd)?;
 
             if potential_profit_usd >= unrealized_loss_usd {
                 let cur_profit_usd = math::checked_sub(potential_profit_usd, unrealized_loss_usd)?;
@@ -643,6 +1555,7 @@ impl Pool {
                     OraclePrice {
                         price: 10u64.pow(Perpetuals::USD_DECIMALS as u32),
                         exponent: -(Perpetuals::USD_DECIMALS as i32),
+                        conf: 0,
                     }
                 } else {
                     collateral_token_price
@@ -674,19 +1587,19 @@ impl Pool {
 
             let potential_loss_usd = math::checked_add(potential_loss_usd, unrealized_loss_usd)?;
 
-            if potential_loss_usd >= position.unrealized_profit_usd {
+            if potential_loss_usd >= unrealized_profit_usd {
                 Ok((
                     0u64,
-                    math::checked_sub(potential_loss_usd, position.unrealized_profit_usd)?,
+                    math::checked_sub(potential_loss_usd, unrealized_profit_usd)?,
                     exit_fee,
                 ))
             } else {
-                let cur_profit_usd =
-                    math::checked_sub(position.unrealized_profit_usd, potential_loss_usd)?;
+                let cur_profit_usd = math::checked_sub(unrealized_profit_usd, potential_loss_usd)?;
                 let min_collateral_price = if collateral_custody.is_virtual {
                     OraclePrice {
                         price: 10u64.pow(Perpetuals::USD_DECIMALS as u32),
                         exponent: -(Perpetuals::USD_DECIMALS as i32),
+                        conf: 0,
                     }
                 } else {
                     collateral_token_price
@@ -726,14 +1639,28 @@ impl Pool {
 
             let oracle_account = accounts.get_account(&oracle.key())?;
             let ema_oracle_account = accounts.get_account(&ema_oracle.key())?;
-
-            let token_price: OraclePrice =
-                OraclePrice::new_from_oracle(oracle_account, clock, custody.oracle, false)?;
+            let secondary_oracle = custody.secondary_oracle.and_then(|secondary_oracle| {
+                accounts
+                    .get_account(&secondary_oracle.key())
+                    .ok()
+                    .map(|account| (secondary_oracle, account))
+            });
+
+            let token_price: OraclePrice = OraclePrice::new_from_oracle(
+                oracle_account,
+                clock,
+                custody.oracle,
+                &custody.oracle_params,
+                secondary_oracle,
+                false,
+            )?;
 
             let token_ema_price = OraclePrice::new_from_oracle(
                 ema_oracle_account,
                 clock,
                 custody.oracle,
+                &custody.oracle_params,
+                secondary_oracle,
                 custody.pricing.use_ema,
             )?;
 
@@ -810,6 +1737,20 @@ impl Pool {
         Ok(pool_amount_usd)
     }
 
+    // returns (funding_received_usd, funding_paid_usd)
+    pub fn get_funding_pnl_usd(
+        position: &Position,
+        custody: &Custody,
+        curtime: i64,
+    ) -> Result<(u64, u64)> {
+        let funding_usd = custody.get_funding_amount_usd(position, curtime)?;
+        if funding_usd > 0 {
+            Ok((0, math::checked_as_u64(funding_usd)?))
+        } else {
+            Ok((math::checked_as_u64(-funding_usd)?, 0))
+        }
+    }
+
     pub fn get_fee_amount(fee: u64, amount: u64) -> Result<u64> {
         if fee == 0 || amount == 0 {
             return Ok(0);
@@ -890,6 +1831,42 @@ impl Pool {
         Ok(std::cmp::min(ratio, Perpetuals::BPS_POWER as u64))
     }
 
+    fn get_custody_prices(
+        custody: &Custody,
+        accounts: &AccountMap,
+        clock: &Clock,
+    ) -> Result<(OraclePrice, OraclePrice)> {
+        let oracle_account = accounts.get_account(&custody.oracle.key())?;
+        let ema_oracle_account =
+            accounts.get_account(&custody.ema_oracle.unwrap_or(custody.oracle).key())?;
+        let secondary_oracle = custody.secondary_oracle.and_then(|secondary_oracle| {
+            accounts
+                .get_account(&secondary_oracle.key())
+                .ok()
+                .map(|account| (secondary_oracle, account))
+        });
+
+        let token_price = OraclePrice::new_from_oracle(
+            oracle_account,
+            clock,
+            custody.oracle,
+            &custody.oracle_params,
+            secondary_oracle,
+            false,
+        )?;
+
+        let token_ema_price = OraclePrice::new_from_oracle(
+            ema_oracle_account,
+            clock,
+            custody.oracle,
+            &custody.oracle_params,
+            secondary_oracle,
+            custody.pricing.use_ema,
+        )?;
+
+        Ok((token_price, token_ema_price))
+    }
+
     fn get_price(
         &self,
         token_price: &OraclePrice,
@@ -897,51 +1874,56 @@ impl Pool {
         side: Side,
         spread: u64,
     ) -> Result<OraclePrice> {
+        // prices are widened by the oracle confidence interval first,
+        // so LPs are protected when the oracle is uncertain
         if side == Side::Long {
             let max_price = if token_price > token_ema_price {
                 token_price
             } else {
                 token_ema_price
             };
+            let max_price_with_conf = math::checked_add(max_price.price, max_price.conf)?;
 
-            Ok(OraclePrice {
-                price: math::checked_add(
-                    max_price.price,
+            Ok(OraclePrice::new(
+                math::checked_add(
+                    max_price_with_conf,
                     math::checked_decimal_ceil_mul(
-                        max_price.price,
+                        max_price_with_conf,
                         max_price.exponent,
                         spread,
                         -(Perpetuals::BPS_DECIMALS as i32),
                         max_price.exponent,
                     )?,
                 )?,
-                exponent: max_price.exponent,
-            })
+                max_price.exponent,
+            ))
         } else {
             let min_price = if token_price < token_ema_price {
                 token_price
             } else {
                 token_ema_price
             };
+            let min_price_with_conf = if min_price.conf < min_price.price {
+                math::checked_sub(min_price.price, min_price.conf)?
+            } else {
+                0
+            };
 
             let spread = math::checked_decimal_mul(
-                min_price.price,
+                min_price_with_conf,
                 min_price.exponent,
                 spread,
                 -(Perpetuals::BPS_DECIMALS as i32),
                 min_price.exponent,
             )?;
 
-            let price = if spread < min_price.price {
-                math::checked_sub(min_price.price, spread)?
+            let price = if spread < min_price_with_conf {
+                math::checked_sub(min_price_with_conf, spread)?
             } else {
                 0
             };
 
-            Ok(OraclePrice {
-                price,
-                exponent: min_price.exponent,
-            })
+            Ok(OraclePrice::new(price, min_price.exponent))
         }
     }
 
@@ -1127,8 +2109,8 @@ mod test {
     use {
         super::*,
         crate::state::{
-            custody::{BorrowRateParams, Fees, PricingParams},
-            oracle::{OracleParams, OracleType},
+            custody::{BorrowRateParams, Fees, PositionStats, PricingParams},
+            oracle::OracleParams,
             perpetuals::Permissions,
         },
     };
@@ -1140,12 +2122,12 @@ mod test {
             max: 9_000,
         };
 
-        let oracle = OracleParams {
-            oracle_account: Pubkey::default(),
-            oracle_type: OracleType::Custom,
+        let oracle_params = OracleParams {
             oracle_authority: Pubkey::default(),
             max_price_error: 100,
             max_price_age_sec: 1,
+            max_open_price_age_sec: 1,
+            ..OracleParams::default()
         };
 
         let pricing = PricingParams {
@@ -1154,13 +2136,22 @@ mod test {
             trade_spread_long: 100,
             trade_spread_short: 100,
             swap_spread: 300,
+            trade_spread_min: 0,
+            skew_spread_mult: 0,
+            price_impact_mult: 0,
+            price_impact_max: 0,
             min_initial_leverage: 10_000,
             max_initial_leverage: 100_000,
             max_leverage: 100_000,
+            liquidation_target_leverage: 0,
+            maintenance_margin: 0,
             max_payoff_mult: 10_000,
             max_utilization: 0,
+            adl_threshold: 0,
             max_position_locked_usd: 0,
             max_total_locked_usd: 0,
+            max_oi_long_usd: 0,
+            max_oi_short_usd: 0,
         };
 
         let permissions = Permissions {
@@ -1187,7 +2178,12 @@ mod test {
             open_position: 100,
             close_position: 0,
             liquidation: 50,
+            liquidation_reward_min: 0,
+            liquidation_reward_max: 0,
+            liquidation_reward_window_sec: 0,
+            execute_order: 10,
             protocol_share: 25,
+            insurance_share: 0,
             fee_max: 0,
             fee_optimal: 0,
         };
@@ -1196,7 +2192,7 @@ mod test {
             token_account: Pubkey::default(),
             mint: Pubkey::default(),
             decimals: 9,
-            oracle,
+            oracle_params,
             pricing,
             permissions,
             fees,
@@ -1218,10 +2214,12 @@ mod test {
         let token_price = OraclePrice {
             price: 25_000_000,
             exponent: -3,
+            conf: 0,
         };
         let token_ema_price = OraclePrice {
             price: 25_300_000,
             exponent: -3,
+            conf: 0,
         };
 
         (
@@ -1312,8 +2310,49 @@ mod test {
 
         assert_eq!(
             OraclePrice {
-                price: 25_553_000,
-                exponent: -3
+                price: 25_553_000,
+                exponent: -3,
+                conf: 0,
+            },
+            pool.get_price(
+                &token_price,
+                &token_ema_price,
+                Side::Long,
+                custody.pricing.trade_spread_long,
+            )
+            .unwrap()
+        );
+
+        assert_eq!(
+            OraclePrice {
+                price: 24_750_000,
+                exponent: -3,
+                conf: 0,
+            },
+            pool.get_price(
+                &token_price,
+                &token_ema_price,
+                Side::Short,
+                custody.pricing.trade_spread_short,
+            )
+            .unwrap()
+        );
+
+        // wide confidence interval widens both sides
+        let token_price = OraclePrice {
+            conf: 100_000,
+            ..token_price
+        };
+        let token_ema_price = OraclePrice {
+            conf: 100_000,
+            ..token_ema_price
+        };
+
+        assert_eq!(
+            OraclePrice {
+                price: 25_654_000,
+                exponent: -3,
+                conf: 0,
             },
             pool.get_price(
                 &token_price,
@@ -1326,8 +2365,9 @@ mod test {
 
         assert_eq!(
             OraclePrice {
-                price: 24_750_000,
-                exponent: -3
+                price: 24_651_000,
+                exponent: -3,
+                conf: 0,
             },
             pool.get_price(
                 &token_price,
@@ -1339,6 +2379,98 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_get_trade_spread() {
+        let (pool, mut custody, _position, _token_price, _token_ema_price) = get_fixture();
+        custody.long_positions.size_usd = 300;
+        custody.short_positions.size_usd = 100;
+
+        // skew spread disabled
+        assert_eq!(100, pool.get_trade_spread(Side::Long, &custody).unwrap());
+        assert_eq!(100, pool.get_trade_spread(Side::Short, &custody).unwrap());
+
+        // 50% skew to the long side
+        custody.pricing.skew_spread_mult = 200;
+        custody.pricing.trade_spread_min = 50;
+        assert_eq!(200, pool.get_trade_spread(Side::Long, &custody).unwrap());
+        assert_eq!(50, pool.get_trade_spread(Side::Short, &custody).unwrap());
+
+        // 25% skew to the short side
+        custody.short_positions.size_usd = 500;
+        assert_eq!(50, pool.get_trade_spread(Side::Long, &custody).unwrap());
+        assert_eq!(150, pool.get_trade_spread(Side::Short, &custody).unwrap());
+
+        // balanced
+        custody.long_positions.size_usd = 500;
+        assert_eq!(100, pool.get_trade_spread(Side::Long, &custody).unwrap());
+        assert_eq!(100, pool.get_trade_spread(Side::Short, &custody).unwrap());
+    }
+
+    #[test]
+    fn test_get_price_impact() {
+        let (pool, mut custody, _position, token_price, token_ema_price) = get_fixture();
+
+        // price impact disabled
+        assert_eq!(
+            25_553_000_000,
+            pool.get_entry_price(
+                &token_price,
+                &token_ema_price,
+                Side::Long,
+                scale(1, 9),
+                &custody
+            )
+            .unwrap()
+        );
+
+        custody.pricing.price_impact_mult = 1_000;
+        custody.pricing.price_impact_max = 200;
+
+        // no liquidity
+        assert_eq!(
+            200,
+            pool.get_price_impact(
+                &token_ema_price,
+                scale(25_300, Perpetuals::USD_DECIMALS),
+                &custody
+            )
+            .unwrap()
+        );
+
+        // 253_000 USD owned
+        custody.assets.owned = scale(10, 9);
+        assert_eq!(
+            100,
+            pool.get_price_impact(
+                &token_ema_price,
+                scale(25_300, Perpetuals::USD_DECIMALS),
+                &custody
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            200,
+            pool.get_price_impact(
+                &token_ema_price,
+                scale(253_000, Perpetuals::USD_DECIMALS),
+                &custody
+            )
+            .unwrap()
+        );
+
+        assert_eq!(
+            25_806_000_000,
+            pool.get_entry_price(
+                &token_price,
+                &token_ema_price,
+                Side::Long,
+                scale(1, 9),
+                &custody
+            )
+            .unwrap()
+        );
+    }
+
     #[test]
     fn test_get_entry_fee() {
         let (pool, mut custody, _position, _token_price, _token_ema_price) = get_fixture();
@@ -1916,6 +3048,257 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_get_liquidation_size() {
+        let (pool, mut custody, mut position, token_price, token_ema_price) = get_fixture();
+
+        // partial liquidations disabled
+        assert_eq!(
+            position.size_usd,
+            pool.get_liquidation_size(
+                &position,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                1
+            )
+            .unwrap()
+        );
+
+        // 24_000 equity at x3 target leverage keeps 72_000 of size
+        custody.pricing.liquidation_target_leverage = 30_000;
+        custody.pricing.maintenance_margin = 300;
+        assert_eq!(
+            scale(28_000, Perpetuals::USD_DECIMALS),
+            pool.get_liquidation_size(
+                &position,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                1
+            )
+            .unwrap()
+        );
+
+        // equity below maintenance margin
+        position.price = scale(32_000, Perpetuals::PRICE_DECIMALS);
+        assert_eq!(
+            position.size_usd,
+            pool.get_liquidation_size(
+                &position,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                1
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_liquidation_reward() {
+        let (pool, mut custody, _position, _token_price, _token_ema_price) = get_fixture();
+
+        // flat reward
+        assert_eq!(
+            5_000,
+            pool.get_liquidation_reward(1_000_000, &custody, 100, 200)
+                .unwrap()
+        );
+
+        custody.fees.liquidation_reward_min = 10;
+        custody.fees.liquidation_reward_max = 110;
+        custody.fees.liquidation_reward_window_sec = 100;
+
+        // not flagged
+        assert_eq!(
+            1_000,
+            pool.get_liquidation_reward(1_000_000, &custody, 0, 200)
+                .unwrap()
+        );
+
+        // just flagged
+        assert_eq!(
+            1_000,
+            pool.get_liquidation_reward(1_000_000, &custody, 200, 200)
+                .unwrap()
+        );
+
+        // half way through the window
+        assert_eq!(
+            6_000,
+            pool.get_liquidation_reward(1_000_000, &custody, 150, 200)
+                .unwrap()
+        );
+
+        // window elapsed
+        assert_eq!(
+            11_000,
+            pool.get_liquidation_reward(1_000_000, &custody, 100, 200)
+                .unwrap()
+        );
+        assert_eq!(
+            11_000,
+            pool.get_liquidation_reward(1_000_000, &custody, 1, 200)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_adl_score() {
+        let (pool, custody, mut position, token_price, token_ema_price) = get_fixture();
+
+        // losing position is never deleveraged
+        assert_eq!(
+            0,
+            pool.get_adl_score(
+                &position,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                1
+            )
+            .unwrap()
+        );
+
+        // winning position, 4.08% profit at x3.8431 leverage
+        position.price = scale(24_500, Perpetuals::PRICE_DECIMALS);
+        assert_eq!(
+            1_567,
+            pool.get_adl_score(
+                &position,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                1
+            )
+            .unwrap()
+        );
+
+        // same profit with less collateral ranks higher
+        position.collateral_usd = scale(12_500, Perpetuals::USD_DECIMALS);
+        assert!(
+            pool.get_adl_score(
+                &position,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                1
+            )
+            .unwrap()
+                > 1_567
+        );
+    }
+
+    #[test]
+    fn test_get_adl_score_threshold() {
+        let (pool, mut custody, mut position, token_price, token_ema_price) = get_fixture();
+
+        // no open positions
+        assert_eq!(
+            0,
+            pool.get_adl_score_threshold(
+                Side::Long,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                1
+            )
+            .unwrap()
+        );
+
+        position.price = scale(24_500, Perpetuals::PRICE_DECIMALS);
+        let mut high_position = position.clone();
+        high_position.collateral_usd = scale(12_500, Perpetuals::USD_DECIMALS);
+        custody
+            .add_position(&position, &token_ema_price, 1, None)
+            .unwrap();
+        custody
+            .add_position(&high_position, &token_ema_price, 1, None)
+            .unwrap();
+
+        let get_score = |position: &Position, custody: &Custody| {
+            pool.get_adl_score(
+                position,
+                &token_price,
+                &token_ema_price,
+                custody,
+                &token_price,
+                &token_ema_price,
+                custody,
+                1,
+            )
+            .unwrap()
+        };
+        let threshold = pool
+            .get_adl_score_threshold(
+                Side::Long,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                &token_price,
+                &token_ema_price,
+                &custody,
+                1,
+            )
+            .unwrap();
+
+        // only the higher ranked position can be deleveraged
+        assert!(get_score(&position, &custody) < threshold);
+        assert!(get_score(&high_position, &custody) >= threshold);
+    }
+
+    #[test]
+    fn test_get_bad_debt_amount() {
+        let (pool, custody, position, token_price, token_ema_price) = get_fixture();
+
+        assert_eq!(
+            0,
+            pool.get_bad_debt_amount(
+                &position,
+                scale(25_000, Perpetuals::USD_DECIMALS),
+                &token_price,
+                &token_ema_price,
+                &custody
+            )
+            .unwrap()
+        );
+
+        // excess loss is converted at the higher of spot and ema prices
+        assert_eq!(
+            252_964_426,
+            pool.get_bad_debt_amount(
+                &position,
+                scale(31_400, Perpetuals::USD_DECIMALS),
+                &token_price,
+                &token_ema_price,
+                &custody
+            )
+            .unwrap()
+        );
+    }
+
     #[test]
     fn test_get_liquidation_price() {
         let (pool, custody, mut position, token_price, _token_ema_price) = get_fixture();
@@ -2019,4 +3402,71 @@ mod test {
         let interest = custody.get_interest_amount_usd(&position, 7_200).unwrap();
         assert_eq!(interest, scale(7, Perpetuals::USD_DECIMALS));
     }
+
+    #[test]
+    fn test_settle_open_and_close_position() {
+        let (mut pool, mut custody, position, _token_price, token_ema_price) = get_fixture();
+
+        custody.assets.owned = scale(10, 9);
+        custody.fees.insurance_share = 10;
+        let mut collateral_custody = custody.clone();
+        let fee_amount = scale(1, 8);
+
+        let insurance_fee = pool
+            .settle_open_position(
+                &position,
+                fee_amount,
+                scale(2_500, Perpetuals::USD_DECIMALS),
+                &token_ema_price,
+                &mut custody,
+                &mut collateral_custody,
+                1,
+            )
+            .unwrap();
+        assert_eq!(insurance_fee, scale(1, 5));
+        assert_eq!(custody, collateral_custody);
+        assert_eq!(custody.assets.locked, position.locked_amount);
+        assert_eq!(custody.assets.collateral, position.collateral_amount);
+        assert_eq!(custody.trade_stats.oi_long_usd, position.size_usd);
+        assert_eq!(custody.long_positions.open_positions, 1);
+        assert_eq!(
+            custody.long_positions.collateral_usd,
+            position.collateral_usd
+        );
+        assert_eq!(pool.oi_usd, position.size_usd);
+
+        // close the position at a loss, the custody keeps the rest of the collateral
+        let amount_out = scale(5, 8);
+        let insurance_fee = pool
+            .settle_close_position(
+                &position,
+                &position,
+                &Position::default(),
+                amount_out,
+                fee_amount,
+                0,
+                0,
+                scale(12_500, Perpetuals::USD_DECIMALS),
+                &token_ema_price,
+                &mut custody,
+                &mut collateral_custody,
+                1,
+                false,
+            )
+            .unwrap();
+        assert_eq!(insurance_fee, scale(1, 5));
+        assert_eq!(custody, collateral_custody);
+        assert_eq!(custody.assets.locked, 0);
+        assert_eq!(custody.assets.collateral, 0);
+        assert_eq!(
+            custody.assets.owned,
+            scale(10, 9) + position.collateral_amount
+                - amount_out
+                - custody.assets.protocol_fees / 2
+                - insurance_fee
+        );
+        assert_eq!(custody.trade_stats.oi_long_usd, 0);
+        assert_eq!(custody.long_positions, PositionStats::default());
+        assert_eq!(pool.oi_usd, 0);
+    }
 }
@@ -36,9 +36,14 @@ pub struct Position {
     pub pool: Pubkey,
     pub custody: Pubkey,
     pub collateral_custody: Pubkey,
+    pub position_id: u64,
+    // cross-margin account the position is linked to, default for isolated positions
+    pub margin_account: Pubkey,
 
     pub open_time: i64,
     pub update_time: i64,
+    // zero unless the position has been flagged for liquidation
+    pub liquidatable_since: i64,
     pub side: Side,
     pub price: u64,
     pub size_usd: u64,
@@ -47,6 +52,7 @@ pub struct Position {
     pub unrealized_profit_usd: u64,
     pub unrealized_loss_usd: u64,
     pub cumulative_interest_snapshot: u128,
+    pub cumulative_funding_snapshot: i128,
     pub locked_amount: u64,
     pub collateral_amount: u64,
 
@@ -62,4 +68,147 @@ impl Position {
             self.collateral_usd as u128,
         )?)
     }
+
+    /// Splits the position into the part that corresponds to `size_usd` and the remainder.
+    /// Amounts of the closed part are pro rata to the position size, the remainder gets
+    /// whatever is left so that both parts always add up to the original position.
+    pub fn split(&self, size_usd: u64) -> Result<(Position, Position)> {
+        if size_usd >= self.size_usd {
+            return Ok((self.clone(), Position::default()));
+        }
+
+        let pro_rata = |amount: u64| -> Result<u64> {
+            math::checked_as_u64(math::checked_div(
+                math::checked_mul(amount as u128, size_usd as u128)?,
+                self.size_usd as u128,
+            )?)
+        };
+
+        let closed = Position {
+            size_usd,
+            borrow_size_usd: pro_rata(self.borrow_size_usd)?,
+            collateral_usd: pro_rata(self.collateral_usd)?,
+            unrealized_profit_usd: pro_rata(self.unrealized_profit_usd)?,
+            unrealized_loss_usd: pro_rata(self.unrealized_loss_usd)?,
+            locked_amount: pro_rata(self.locked_amount)?,
+            collateral_amount: pro_rata(self.collateral_amount)?,
+            ..self.clone()
+        };
+
+        let remaining = Position {
+            size_usd: math::checked_sub(self.size_usd, closed.size_usd)?,
+            borrow_size_usd: math::checked_sub(self.borrow_size_usd, closed.borrow_size_usd)?,
+            collateral_usd: math::checked_sub(self.collateral_usd, closed.collateral_usd)?,
+            unrealized_profit_usd: math::checked_sub(
+                self.unrealized_profit_usd,
+                closed.unrealized_profit_usd,
+            )?,
+            unrealized_loss_usd: math::checked_sub(
+                self.unrealized_loss_usd,
+                closed.unrealized_loss_usd,
+            )?,
+            locked_amount: math::checked_sub(self.locked_amount, closed.locked_amount)?,
+            collateral_amount: math::checked_sub(self.collateral_amount, closed.collateral_amount)?,
+            // the remaining part has to be flagged again once it becomes liquidatable
+            liquidatable_since: 0,
+            ..self.clone()
+        };
+
+        Ok((closed, remaining))
+    }
+
+    /// Returns the entry price of the position after adding `size_usd` at `price`.
+    /// Prices are weighted by quantity, i.e. the result is total size over total quantity.
+    pub fn get_average_price(&self, size_usd: u64, price: u64) -> Result<u64> {
+        if self.size_usd == 0 || self.price == 0 {
+            return Ok(price);
+        }
+
+        let quantity = |size_usd: u64, price: u64| -> Result<u128> {
+            math::checked_div(
+                math::checked_mul(size_usd as u128, Perpetuals::RATE_POWER)?,
+                price as u128,
+            )
+        };
+
+        let total_quantity = math::checked_add(
+            quantity(self.size_usd, self.price)?,
+            quantity(size_usd, price)?,
+        )?;
+        let total_size_usd = math::checked_add(self.size_usd as u128, size_usd as u128)?;
+
+        math::checked_as_u64(math::checked_div(
+            math::checked_mul(total_size_usd, Perpetuals::RATE_POWER)?,
+            total_quantity,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let position = Position {
+            side: Side::Long,
+            price: 25_000_000_000,
+            size_usd: 100_000_000_000,
+            borrow_size_usd: 100_000_000_000,
+            collateral_usd: 25_000_000_000,
+            unrealized_loss_usd: 3,
+            locked_amount: 4_000_000_000,
+            collateral_amount: 1_000_000_001,
+            liquidatable_since: 10,
+            ..Position::default()
+        };
+
+        let (closed, remaining) = position.split(25_000_000_000).unwrap();
+        assert_eq!(closed.size_usd, 25_000_000_000);
+        assert_eq!(closed.borrow_size_usd, 25_000_000_000);
+        assert_eq!(closed.collateral_usd, 6_250_000_000);
+        assert_eq!(closed.unrealized_loss_usd, 0);
+        assert_eq!(closed.locked_amount, 1_000_000_000);
+        assert_eq!(closed.collateral_amount, 250_000_000);
+        assert_eq!(closed.price, position.price);
+        assert_eq!(closed.liquidatable_since, position.liquidatable_since);
+
+        assert_eq!(remaining.size_usd, 75_000_000_000);
+        assert_eq!(remaining.collateral_usd, 18_750_000_000);
+        assert_eq!(remaining.unrealized_loss_usd, 3);
+        assert_eq!(remaining.locked_amount, 3_000_000_000);
+        assert_eq!(remaining.collateral_amount, 750_000_001);
+        assert_eq!(remaining.liquidatable_since, 0);
+
+        // full close
+        let (closed, remaining) = position.split(position.size_usd).unwrap();
+        assert_eq!(closed.size_usd, position.size_usd);
+        assert_eq!(closed.collateral_amount, position.collateral_amount);
+        assert_eq!(remaining.size_usd, 0);
+    }
+
+    #[test]
+    fn test_get_average_price() {
+        let position = Position {
+            side: Side::Long,
+            price: 25_000_000,
+            size_usd: 100_000_000,
+            ..Position::default()
+        };
+
+        assert_eq!(
+            position.get_average_price(100_000_000, 20_000_000).unwrap(),
+            22_222_222
+        );
+        assert_eq!(
+            position.get_average_price(100_000_000, 25_000_000).unwrap(),
+            25_000_000
+        );
+        assert_eq!(
+            Position::default()
+                .get_average_price(100_000_000, 20_000_000)
+                .unwrap(),
+            20_000_000
+        );
+    }
 }
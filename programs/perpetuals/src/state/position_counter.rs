@@ -0,0 +1,48 @@
+use {
+    crate::{error::PerpetualsError, math},
+    anchor_lang::prelude::*,
+};
+
+/// Per-user counter of opened positions, provides the `position_id` seed of new positions
+/// so that a user can hold any number of positions in the same market.
+#[account]
+#[derive(Default, Debug)]
+pub struct PositionCounter {
+    pub owner: Pubkey,
+    pub next_position_id: u64,
+
+    pub bump: u8,
+}
+
+impl PositionCounter {
+    pub const LEN: usize = 8 + std::mem::size_of::<PositionCounter>();
+
+    /// Consumes the next position id, the id requested by the user must match it
+    pub fn use_position_id(&mut self, position_id: u64) -> Result<()> {
+        require_eq!(
+            position_id,
+            self.next_position_id,
+            PerpetualsError::InvalidPositionId
+        );
+        self.next_position_id = math::checked_add(self.next_position_id, 1)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_use_position_id() {
+        let mut counter = PositionCounter::default();
+
+        counter.use_position_id(0).unwrap();
+        counter.use_position_id(1).unwrap();
+        assert_eq!(2, counter.next_position_id);
+
+        assert!(counter.use_position_id(1).is_err());
+        assert!(counter.use_position_id(3).is_err());
+        assert_eq!(2, counter.next_position_id);
+    }
+}
@@ -0,0 +1,123 @@
+use {
+    crate::state::multisig::{AdminInstruction, Role},
+    anchor_lang::prelude::*,
+};
+
+#[derive(Copy, Clone, PartialEq, AnchorSerialize, AnchorDeserialize, Debug)]
+pub enum ProposalStatus {
+    Queued,
+    Executed,
+    Cancelled,
+}
+
+impl Default for ProposalStatus {
+    fn default() -> Self {
+        Self::Queued
+    }
+}
+
+/// Timelocked admin instruction approved by the role multisig,
+/// can be executed by anyone with execute_proposal once `eta` has passed.
+#[account]
+#[derive(Default, Debug)]
+pub struct Proposal {
+    pub proposal_id: u64,
+    // hash of the instruction accounts and data, see Multisig::get_instruction_hash
+    pub instruction_hash: u64,
+    // serialized instruction params followed by the AdminInstruction type
+    pub instruction_data: Vec<u8>,
+    pub eta: i64,
+    // set if the proposal was exempt from the delay, re-checked on execution
+    pub risk_reducing: bool,
+    pub status: ProposalStatus,
+
+    pub bump: u8,
+}
+
+impl Proposal {
+    // instruction data is allocated on top of this
+    pub const LEN: usize = 8 + 8 + 8 + 4 + 8 + 1 + 1 + 1;
+
+    /// Returns the AdminInstruction type of the serialized instruction data
+    pub fn get_instruction_type(instruction_data: &[u8]) -> Result<u8> {
+        instruction_data
+            .last()
+            .copied()
+            .ok_or(ProgramError::InvalidInstructionData.into())
+    }
+
+    /// Returns the role allowed to propose the given AdminInstruction type
+    pub fn get_role(instruction_type: u8) -> Result<Role> {
+        if instruction_type == AdminInstruction::SetCustodyConfig as u8
+            || instruction_type == AdminInstruction::SetPoolConfig as u8
+        {
+            Ok(Role::RiskAdmin)
+        } else if instruction_type == AdminInstruction::SetPermissions as u8
+            || instruction_type == AdminInstruction::SetPoolPermissions as u8
+            || instruction_type == AdminInstruction::SetMarketStatus as u8
+        {
+            Ok(Role::Pauser)
+        } else {
+            msg!("Error: Instruction {} can't be proposed", instruction_type);
+            Err(ProgramError::InvalidArgument.into())
+        }
+    }
+
+    /// Returns the serialized instruction params
+    pub fn get_params_data(&self) -> &[u8] {
+        &self.instruction_data[..self.instruction_data.len().saturating_sub(1)]
+    }
+
+    pub fn is_executable(&self, curtime: i64) -> bool {
+        self.status == ProposalStatus::Queued && curtime >= self.eta
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_get_role() {
+        assert_eq!(
+            Role::RiskAdmin,
+            Proposal::get_role(AdminInstruction::SetCustodyConfig as u8).unwrap()
+        );
+        assert_eq!(
+            Role::RiskAdmin,
+            Proposal::get_role(AdminInstruction::SetPoolConfig as u8).unwrap()
+        );
+        assert_eq!(
+            Role::Pauser,
+            Proposal::get_role(AdminInstruction::SetPermissions as u8).unwrap()
+        );
+        assert_eq!(
+            Role::Pauser,
+            Proposal::get_role(AdminInstruction::SetPoolPermissions as u8).unwrap()
+        );
+        assert_eq!(
+            Role::Pauser,
+            Proposal::get_role(AdminInstruction::SetMarketStatus as u8).unwrap()
+        );
+        assert!(Proposal::get_role(AdminInstruction::WithdrawFees as u8).is_err());
+        assert!(Proposal::get_instruction_type(&[]).is_err());
+    }
+
+    #[test]
+    fn test_is_executable() {
+        let mut proposal = Proposal {
+            instruction_data: vec![1, 2, AdminInstruction::SetPoolConfig as u8],
+            eta: 100,
+            ..Default::default()
+        };
+        assert_eq!(&[1, 2], proposal.get_params_data());
+
+        assert!(!proposal.is_executable(99));
+        assert!(proposal.is_executable(100));
+
+        proposal.status = ProposalStatus::Cancelled;
+        assert!(!proposal.is_executable(100));
+        proposal.status = ProposalStatus::Executed;
+        assert!(!proposal.is_executable(100));
+    }
+}
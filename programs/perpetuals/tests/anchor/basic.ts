@@ -25,6 +25,7 @@ describe("perpetuals", () => {
   let multisigExpected;
   let tokenExpected;
   let positionExpected;
+  let positionAccount;
 
   it("init", async () => {
     await tc.initFixture();
@@ -48,6 +49,7 @@ describe("perpetuals", () => {
       transferAuthorityBump: tc.authority.bump,
       perpetualsBump: tc.perpetuals.bump,
       inceptionTime: new BN(0),
+      proposalDelay: new BN(0),
     };
 
     multisigExpected = {
@@ -66,13 +68,19 @@ describe("perpetuals", () => {
         PublicKey.default,
       ],
       signed: [0, 0, 0, 0, 0, 0],
-      bump: tc.multisig.bump,
+      bump: 0,
     };
 
-    let multisig = await tc.program.account.multisig.fetch(
-      tc.multisig.publicKey
-    );
-    expect(JSON.stringify(multisig)).to.equal(JSON.stringify(multisigExpected));
+    // initial admins are members of every role
+    for (const role of TestClient.roles) {
+      let multisig = await tc.program.account.multisig.fetch(
+        tc.multisigs[role].publicKey
+      );
+      multisigExpected.bump = tc.multisigs[role].bump;
+      expect(JSON.stringify(multisig)).to.equal(
+        JSON.stringify(multisigExpected)
+      );
+    }
 
     let perpetuals = await tc.program.account.perpetuals.fetch(
       tc.perpetuals.publicKey
@@ -82,14 +90,36 @@ describe("perpetuals", () => {
     );
   });
 
-  it("setAdminSigners", async () => {
-    await tc.setAdminSigners(1);
-
-    let multisig = await tc.program.account.multisig.fetch(
-      tc.multisig.publicKey
-    );
+  it("grantAndRevokeRole", async () => {
+    let member = Keypair.generate().publicKey;
+    for (const role of TestClient.roles) {
+      await tc.grantRole(role, member, 2);
+
+      let multisig = await tc.program.account.multisig.fetch(
+        tc.multisigs[role].publicKey
+      );
+      multisigExpected.numSigners = 3;
+      multisigExpected.signers[2] = member;
+      multisigExpected.bump = tc.multisigs[role].bump;
+      expect(JSON.stringify(multisig)).to.equal(
+        JSON.stringify(multisigExpected)
+      );
+
+      // the remaining admins sign with a single signature from now on
+      await tc.revokeRole(role, member, 1);
+
+      multisig = await tc.program.account.multisig.fetch(
+        tc.multisigs[role].publicKey
+      );
+      multisigExpected.numSigners = 2;
+      multisigExpected.minSignatures = 1;
+      multisigExpected.signers[2] = PublicKey.default;
+      expect(JSON.stringify(multisig)).to.equal(
+        JSON.stringify(multisigExpected)
+      );
+      multisigExpected.minSignatures = 2;
+    }
     multisigExpected.minSignatures = 1;
-    expect(JSON.stringify(multisig)).to.equal(JSON.stringify(multisigExpected));
   });
 
   it("setPermissions", async () => {
@@ -571,20 +601,17 @@ describe("perpetuals", () => {
   });
 
   it("openPosition", async () => {
-    await tc.openPosition(
+    positionAccount = await tc.openPosition(
       125,
       tc.toTokenAmount(1, tc.custodies[0].decimals),
       tc.toTokenAmount(7, tc.custodies[0].decimals),
       "long",
       tc.users[0],
       tc.users[0].tokenAccounts[0],
-      tc.users[0].positionAccountsLong[0],
       tc.custodies[0]
     );
 
-    let position = await tc.program.account.position.fetch(
-      tc.users[0].positionAccountsLong[0]
-    );
+    let position = await tc.program.account.position.fetch(positionAccount);
     positionExpected = {
       owner: tc.users[0].wallet.publicKey.toBase58(),
       pool: tc.pool.publicKey.toBase58(),
@@ -613,7 +640,7 @@ describe("perpetuals", () => {
       tc.toTokenAmount(1, tc.custodies[0].decimals),
       tc.users[0],
       tc.users[0].tokenAccounts[0],
-      tc.users[0].positionAccountsLong[0],
+      positionAccount,
       tc.custodies[0]
     );
   });
@@ -623,7 +650,7 @@ describe("perpetuals", () => {
       tc.toTokenAmount(1, 6),
       tc.users[0],
       tc.users[0].tokenAccounts[0],
-      tc.users[0].positionAccountsLong[0],
+      positionAccount,
       tc.custodies[0]
     );
   });
@@ -631,36 +658,32 @@ describe("perpetuals", () => {
   it("closePosition", async () => {
     await tc.closePosition(
       1,
+      new BN(positionExpected.sizeUsd),
       tc.users[0],
       tc.users[0].tokenAccounts[0],
-      tc.users[0].positionAccountsLong[0],
+      positionAccount,
       tc.custodies[0]
     );
-    await tc.ensureFails(
-      tc.program.account.position.fetch(tc.users[0].positionAccountsLong[0])
-    );
+    await tc.ensureFails(tc.program.account.position.fetch(positionAccount));
   });
 
   it("liquidate", async () => {
-    await tc.openPosition(
+    positionAccount = await tc.openPosition(
       125,
       tc.toTokenAmount(1, tc.custodies[0].decimals),
       tc.toTokenAmount(7, tc.custodies[0].decimals),
       "long",
       tc.users[0],
       tc.users[0].tokenAccounts[0],
-      tc.users[0].positionAccountsLong[0],
       tc.custodies[0]
     );
     await tc.setCustomOraclePrice(80, tc.custodies[0]);
     await tc.liquidate(
       tc.users[0],
       tc.users[0].tokenAccounts[0],
-      tc.users[0].positionAccountsLong[0],
+      positionAccount,
       tc.custodies[0]
     );
-    await tc.ensureFails(
-      tc.program.account.position.fetch(tc.users[0].positionAccountsLong[0])
-    );
+    await tc.ensureFails(tc.program.account.position.fetch(positionAccount));
   });
 });
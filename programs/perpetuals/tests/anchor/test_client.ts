@@ -19,6 +19,38 @@ import BN from "bn.js";
 export type PositionSide = "long" | "short";
 
 export class TestClient {
+  static roles = [
+    "poolAdmin",
+    "riskAdmin",
+    "feeWithdrawer",
+    "pauser",
+    "oracleAuthority",
+  ];
+
+  // AdminInstruction discriminants, appended to the proposed instruction data
+  static adminInstructions = [
+    "addPool",
+    "removePool",
+    "addCustody",
+    "removeCustody",
+    "grantRole",
+    "setCustodyConfig",
+    "setPermissions",
+    "setBorrowRate",
+    "withdrawFees",
+    "withdrawSolFees",
+    "setCustomOraclePrice",
+    "setTestTime",
+    "upgradeCustody",
+    "setPoolConfig",
+    "revokeRole",
+    "propose",
+    "cancelProposal",
+    "setPoolPermissions",
+    "upgradePool",
+    "setMarketStatus",
+  ];
+
   provider: anchor.AnchorProvider;
   program: anchor.Program<Perpetuals>;
   printErrors: boolean;
@@ -27,9 +59,10 @@ export class TestClient {
   feesAccount: PublicKey;
   adminMetas: AccountMeta[];
   oracleAuthority: Keypair;
+  proposalId: number;
 
   // pdas
-  multisig: { publicKey: PublicKey; bump: number };
+  multisigs: { [role: string]: { publicKey: PublicKey; bump: number } };
   authority: { publicKey: PublicKey; bump: number };
   perpetuals: { publicKey: PublicKey; bump: number };
   pool: { publicKey: PublicKey; bump: number };
@@ -48,8 +81,7 @@ export class TestClient {
     wallet: Keypair;
     tokenAccounts: PublicKey[];
     lpTokenAccount: PublicKey;
-    positionAccountsLong: PublicKey[];
-    positionAccountsShort: PublicKey[];
+    positionCounter: PublicKey;
   }[];
 
   constructor() {
@@ -79,9 +111,14 @@ export class TestClient {
     }
 
     this.oracleAuthority = Keypair.generate();
+    this.proposalId = 0;
 
     // pdas
-    this.multisig = this.findProgramAddress("multisig");
+    // each governance role is a separate multisig, indexed by the Role enum
+    this.multisigs = {};
+    for (const [i, role] of TestClient.roles.entries()) {
+      this.multisigs[role] = this.findProgramAddress("multisig", [[i]]);
+    }
     this.authority = this.findProgramAddress("transfer_authority");
     this.perpetuals = this.findProgramAddress("perpetuals");
     this.pool = this.findProgramAddress("pool", "test pool");
@@ -140,8 +177,6 @@ export class TestClient {
       await this.requestAirdrop(wallet.publicKey);
 
       let tokenAccounts = [];
-      let positionAccountsLong = [];
-      let positionAccountsShort = [];
       for (const custody of this.custodies) {
         let tokenAccount = await spl.createAssociatedTokenAccount(
           this.provider.connection,
@@ -156,30 +191,15 @@ export class TestClient {
           tokenAccount
         );
         tokenAccounts.push(tokenAccount);
-
-        let positionAccount = this.findProgramAddress("position", [
-          wallet.publicKey,
-          this.pool.publicKey,
-          custody.custody,
-          [1],
-        ]).publicKey;
-        positionAccountsLong.push(positionAccount);
-
-        positionAccount = this.findProgramAddress("position", [
-          wallet.publicKey,
-          this.pool.publicKey,
-          custody.custody,
-          [2],
-        ]).publicKey;
-        positionAccountsShort.push(positionAccount);
       }
 
       this.users.push({
         wallet,
         tokenAccounts,
         lpTokenAccount: PublicKey.default,
-        positionAccountsLong,
-        positionAccountsShort,
+        positionCounter: this.findProgramAddress("position_counter", [
+          wallet.publicKey,
+        ]).publicKey,
       });
     }
   };
@@ -230,6 +250,21 @@ export class TestClient {
     };
   };
 
+  getPositionAccount = (
+    user,
+    custody,
+    side: PositionSide,
+    positionId: BN
+  ) => {
+    return this.findProgramAddress("position", [
+      user.wallet.publicKey,
+      this.pool.publicKey,
+      custody.custody,
+      [side === "long" ? 1 : 2],
+      positionId.toArrayLike(Buffer, "le", 8),
+    ]).publicKey;
+  };
+
   findProgramAddress = (label: string, extraSeeds = null) => {
     let seeds = [Buffer.from(anchor.utils.bytes.utf8.encode(label))];
     if (extraSeeds) {
@@ -336,11 +371,6 @@ export class TestClient {
 
   init = async () => {
     try {
-      let programData = PublicKey.findProgramAddressSync(
-        [this.program.programId.toBuffer()],
-        new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
-      )[0];
-
       await this.program.methods
         .init({
           minSignatures: 2,
@@ -352,14 +382,17 @@ export class TestClient {
           allowPnlWithdrawal: true,
           allowCollateralWithdrawal: true,
           allowSizeChange: true,
+          proposalDelay: new BN(0),
         })
         .accounts({
-          upgradeAuthority: this.provider.wallet.publicKey,
-          multisig: this.multisig.publicKey,
+          signer: this.provider.wallet.publicKey,
+          poolAdmin: this.multisigs.poolAdmin.publicKey,
+          riskAdmin: this.multisigs.riskAdmin.publicKey,
+          feeWithdrawer: this.multisigs.feeWithdrawer.publicKey,
+          pauser: this.multisigs.pauser.publicKey,
+          oracleAuthority: this.multisigs.oracleAuthority.publicKey,
           transferAuthority: this.authority.publicKey,
           perpetuals: this.perpetuals.publicKey,
-          perpetualsProgramData: programData,
-          perpetualsProgram: this.program.programId,
           systemProgram: SystemProgram.programId,
           tokenProgram: spl.TOKEN_PROGRAM_ID,
         })
@@ -373,21 +406,57 @@ export class TestClient {
     }
   };
 
-  setAdminSigners = async (minSignatures: number) => {
+  grantRole = async (
+    role: string,
+    member: PublicKey,
+    minSignatures: number
+  ) => {
+    let multisig = await this.program.account.multisig.fetch(
+      this.multisigs[role].publicKey
+    );
+    for (let i = 0; i < multisig.minSignatures; ++i) {
+      try {
+        await this.program.methods
+          .grantRole({
+            role: { [role]: {} },
+            minSignatures,
+          })
+          .accounts({
+            admin: this.admins[i].publicKey,
+            multisig: this.multisigs[role].publicKey,
+            member,
+          })
+          .signers([this.admins[i]])
+          .rpc();
+      } catch (err) {
+        if (this.printErrors) {
+          console.log(err);
+        }
+        throw err;
+      }
+    }
+  };
+
+  revokeRole = async (
+    role: string,
+    member: PublicKey,
+    minSignatures: number
+  ) => {
     let multisig = await this.program.account.multisig.fetch(
-      this.multisig.publicKey
+      this.multisigs[role].publicKey
     );
     for (let i = 0; i < multisig.minSignatures; ++i) {
       try {
         await this.program.methods
-          .setAdminSigners({
+          .revokeRole({
+            role: { [role]: {} },
             minSignatures,
           })
           .accounts({
             admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
+            multisig: this.multisigs[role].publicKey,
+            member,
           })
-          .remainingAccounts(this.adminMetas)
           .signers([this.admins[i]])
           .rpc();
       } catch (err) {
@@ -401,7 +470,7 @@ export class TestClient {
 
   setPermissions = async (permissions) => {
     let multisig = await this.program.account.multisig.fetch(
-      this.multisig.publicKey
+      this.multisigs.pauser.publicKey
     );
     for (let i = 0; i < multisig.minSignatures; ++i) {
       try {
@@ -409,7 +478,7 @@ export class TestClient {
           .setPermissions(permissions)
           .accounts({
             admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
+            multisig: this.multisigs.pauser.publicKey,
             perpetuals: this.perpetuals.publicKey,
           })
           .signers([this.admins[i]])
@@ -425,7 +494,7 @@ export class TestClient {
 
   addPool = async (name) => {
     let multisig = await this.program.account.multisig.fetch(
-      this.multisig.publicKey
+      this.multisigs.poolAdmin.publicKey
     );
     for (let i = 0; i < multisig.minSignatures; ++i) {
       try {
@@ -433,7 +502,7 @@ export class TestClient {
           .addPool({ name })
           .accounts({
             admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
+            multisig: this.multisigs.poolAdmin.publicKey,
             transferAuthority: this.authority.publicKey,
             perpetuals: this.perpetuals.publicKey,
             pool: this.pool.publicKey,
@@ -466,7 +535,7 @@ export class TestClient {
 
   removePool = async () => {
     let multisig = await this.program.account.multisig.fetch(
-      this.multisig.publicKey
+      this.multisigs.poolAdmin.publicKey
     );
     for (let i = 0; i < multisig.minSignatures; ++i) {
       try {
@@ -474,7 +543,7 @@ export class TestClient {
           .removePool({})
           .accounts({
             admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
+            multisig: this.multisigs.poolAdmin.publicKey,
             transferAuthority: this.authority.publicKey,
             perpetuals: this.perpetuals.publicKey,
             pool: this.pool.publicKey,
@@ -503,7 +572,7 @@ export class TestClient {
     ratios
   ) => {
     let multisig = await this.program.account.multisig.fetch(
-      this.multisig.publicKey
+      this.multisigs.poolAdmin.publicKey
     );
     for (let i = 0; i < multisig.minSignatures; ++i) {
       try {
@@ -520,7 +589,7 @@ export class TestClient {
           })
           .accounts({
             admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
+            multisig: this.multisigs.poolAdmin.publicKey,
             transferAuthority: this.authority.publicKey,
             perpetuals: this.perpetuals.publicKey,
             pool: this.pool.publicKey,
@@ -544,7 +613,7 @@ export class TestClient {
 
   removeCustody = async (custody, ratios) => {
     let multisig = await this.program.account.multisig.fetch(
-      this.multisig.publicKey
+      this.multisigs.poolAdmin.publicKey
     );
     for (let i = 0; i < multisig.minSignatures; ++i) {
       try {
@@ -552,7 +621,7 @@ export class TestClient {
           .removeCustody({ ratios })
           .accounts({
             admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
+            multisig: this.multisigs.poolAdmin.publicKey,
             transferAuthority: this.authority.publicKey,
             perpetuals: this.perpetuals.publicKey,
             pool: this.pool.publicKey,
@@ -583,28 +652,61 @@ export class TestClient {
     borrowRate,
     ratios
   ) => {
+    // custody config changes are timelocked, the proposal is executed once
+    // approved since the test protocol is initialized without a delay
+    let instructionData = Buffer.concat([
+      this.program.coder.types.encode("SetCustodyConfigParams", {
+        isStable,
+        isVirtual,
+        oracle: oracleConfig,
+        pricing,
+        permissions,
+        fees,
+        borrowRate,
+        ratios,
+      }),
+      Buffer.from([TestClient.adminInstructions.indexOf("setCustodyConfig")]),
+    ]);
+    let instructionAccounts = [
+      { isSigner: false, isWritable: true, pubkey: this.pool.publicKey },
+      { isSigner: false, isWritable: true, pubkey: custody.custody },
+    ];
+    let proposal = await this.propose(
+      "riskAdmin",
+      instructionData,
+      instructionAccounts
+    );
+    await this.executeProposal(proposal, instructionAccounts);
+  };
+
+  propose = async (
+    role: string,
+    instructionData: Buffer,
+    instructionAccounts: AccountMeta[]
+  ) => {
+    let proposalId = new BN(this.proposalId++);
+    let proposal = this.findProgramAddress("proposal", [
+      proposalId.toArrayLike(Buffer, "le", 8),
+    ]).publicKey;
     let multisig = await this.program.account.multisig.fetch(
-      this.multisig.publicKey
+      this.multisigs[role].publicKey
     );
     for (let i = 0; i < multisig.minSignatures; ++i) {
       try {
         await this.program.methods
-          .setCustodyConfig({
-            isStable,
-            isVirtual,
-            oracle: oracleConfig,
-            pricing,
-            permissions,
-            fees,
-            borrowRate,
-            ratios,
+          .propose({
+            role: { [role]: {} },
+            proposalId,
+            instructionData,
           })
           .accounts({
             admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
-            pool: this.pool.publicKey,
-            custody: custody.custody,
+            multisig: this.multisigs[role].publicKey,
+            perpetuals: this.perpetuals.publicKey,
+            proposal,
+            systemProgram: SystemProgram.programId,
           })
+          .remainingAccounts(instructionAccounts)
           .signers([this.admins[i]])
           .rpc();
       } catch (err) {
@@ -614,11 +716,34 @@ export class TestClient {
         throw err;
       }
     }
+    return proposal;
+  };
+
+  executeProposal = async (
+    proposal: PublicKey,
+    instructionAccounts: AccountMeta[]
+  ) => {
+    try {
+      await this.program.methods
+        .executeProposal({})
+        .accounts({
+          signer: this.provider.wallet.publicKey,
+          perpetuals: this.perpetuals.publicKey,
+          proposal,
+        })
+        .remainingAccounts(instructionAccounts)
+        .rpc();
+    } catch (err) {
+      if (this.printErrors) {
+        console.log(err);
+      }
+      throw err;
+    }
   };
 
   withdrawFees = async (amount: BN, custody, receivingTokenAccount) => {
     let multisig = await this.program.account.multisig.fetch(
-      this.multisig.publicKey
+      this.multisigs.feeWithdrawer.publicKey
     );
     for (let i = 0; i < multisig.minSignatures; ++i) {
       try {
@@ -628,7 +753,7 @@ export class TestClient {
           })
           .accounts({
             admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
+            multisig: this.multisigs.feeWithdrawer.publicKey,
             transferAuthority: this.authority.publicKey,
             perpetuals: this.perpetuals.publicKey,
             pool: this.pool.publicKey,
@@ -650,7 +775,7 @@ export class TestClient {
 
   withdrawSolFees = async (amount: BN, custody, receivingAccount) => {
     let multisig = await this.program.account.multisig.fetch(
-      this.multisig.publicKey
+      this.multisigs.feeWithdrawer.publicKey
     );
     for (let i = 0; i < multisig.minSignatures; ++i) {
       try {
@@ -660,7 +785,7 @@ export class TestClient {
           })
           .accounts({
             admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
+            multisig: this.multisigs.feeWithdrawer.publicKey,
             transferAuthority: this.authority.publicKey,
             perpetuals: this.perpetuals.publicKey,
             receivingAccount: receivingAccount,
@@ -678,7 +803,7 @@ export class TestClient {
 
   setCustomOraclePrice = async (price: number, custody) => {
     let multisig = await this.program.account.multisig.fetch(
-      this.multisig.publicKey
+      this.multisigs.oracleAuthority.publicKey
     );
     for (let i = 0; i < multisig.minSignatures; ++i) {
       try {
@@ -692,7 +817,7 @@ export class TestClient {
           })
           .accounts({
             admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
+            multisig: this.multisigs.oracleAuthority.publicKey,
             perpetuals: this.perpetuals.publicKey,
             pool: this.pool.publicKey,
             custody: custody.custody,
@@ -784,7 +909,7 @@ export class TestClient {
 
   setTestTime = async (time: number) => {
     let multisig = await this.program.account.multisig.fetch(
-      this.multisig.publicKey
+      this.multisigs.poolAdmin.publicKey
     );
     for (let i = 0; i < multisig.minSignatures; ++i) {
       try {
@@ -794,7 +919,7 @@ export class TestClient {
           })
           .accounts({
             admin: this.admins[i].publicKey,
-            multisig: this.multisig.publicKey,
+            multisig: this.multisigs.poolAdmin.publicKey,
             perpetuals: this.perpetuals.publicKey,
           })
           .signers([this.admins[i]])
@@ -929,12 +1054,26 @@ export class TestClient {
     side: PositionSide,
     user,
     fundingAccount: PublicKey,
-    positionAccount: PublicKey,
     custody
   ) => {
+    // new positions take the next id of the owner's position counter
+    let positionCounter =
+      await this.program.account.positionCounter.fetchNullable(
+        user.positionCounter
+      );
+    let positionId = positionCounter
+      ? positionCounter.nextPositionId
+      : new BN(0);
+    let positionAccount = this.getPositionAccount(
+      user,
+      custody,
+      side,
+      positionId
+    );
     try {
       await this.program.methods
         .openPosition({
+          positionId,
           price: new BN(price * 1000000),
           collateral,
           size,
@@ -946,6 +1085,7 @@ export class TestClient {
           transferAuthority: this.authority.publicKey,
           perpetuals: this.perpetuals.publicKey,
           pool: this.pool.publicKey,
+          positionCounter: user.positionCounter,
           position: positionAccount,
           custody: custody.custody,
           custodyOracleAccount: custody.oracleAccount,
@@ -963,6 +1103,7 @@ export class TestClient {
       }
       throw err;
     }
+    return positionAccount;
   };
 
   addCollateral = async (
@@ -1039,6 +1180,7 @@ export class TestClient {
 
   closePosition = async (
     price: number,
+    sizeUsd: BN,
     user,
     receivingAccount,
     positionAccount,
@@ -1048,6 +1190,7 @@ export class TestClient {
       await this.program.methods
         .closePosition({
           price: new BN(price),
+          sizeUsd,
         })
         .accounts({
           owner: user.wallet.publicKey,
@@ -1,21 +1,40 @@
 pub mod get_update_pool_ix;
 pub mod test_add_custody;
 pub mod test_add_liquidity;
+pub mod test_add_margin_collateral;
 pub mod test_add_pool;
+pub mod test_auto_deleverage;
+pub mod test_cancel_order;
+pub mod test_cancel_proposal;
 pub mod test_close_position;
+pub mod test_execute_order;
+pub mod test_execute_proposal;
 pub mod test_get_lp_token_price;
+pub mod test_increase_position;
 pub mod test_init;
+pub mod test_init_margin_account;
+pub mod test_link_position;
 pub mod test_liquidate;
 pub mod test_open_position;
+pub mod test_open_position_with_swap;
+pub mod test_place_order;
+pub mod test_propose;
 pub mod test_remove_liquidity;
+pub mod test_remove_margin_collateral;
 pub mod test_set_custody_config;
 pub mod test_set_custom_oracle_price;
 pub mod test_swap;
+pub mod test_unlink_position;
 pub mod test_update_pool_aum;
 
 pub use {
-    get_update_pool_ix::*, test_add_custody::*, test_add_liquidity::*, test_add_pool::*,
-    test_close_position::*, test_get_lp_token_price::*, test_init::*, test_liquidate::*,
-    test_open_position::*, test_remove_liquidity::*, test_set_custody_config::*,
-    test_set_custom_oracle_price::*, test_swap::*, test_update_pool_aum::*,
+    get_update_pool_ix::*, test_add_custody::*, test_add_liquidity::*,
+    test_add_margin_collateral::*, test_add_pool::*, test_auto_deleverage::*, test_cancel_order::*,
+    test_cancel_proposal::*, test_close_position::*, test_execute_order::*,
+    test_execute_proposal::*, test_get_lp_token_price::*, test_increase_position::*, test_init::*,
+    test_init_margin_account::*, test_link_position::*, test_liquidate::*, test_open_position::*,
+    test_open_position_with_swap::*, test_place_order::*, test_propose::*,
+    test_remove_liquidity::*, test_remove_margin_collateral::*, test_set_custody_config::*,
+    test_set_custom_oracle_price::*, test_swap::*, test_unlink_position::*,
+    test_update_pool_aum::*,
 };
@@ -6,7 +6,11 @@ use {
     },
     perpetuals::{
         instructions::AddCustodyParams,
-        state::{custody::Custody, multisig::Multisig, pool::Pool},
+        state::{
+            custody::{Custody, Oracle},
+            multisig::{Multisig, Role},
+            pool::Pool,
+        },
     },
     solana_program_test::{BanksClientError, ProgramTestContext},
     solana_sdk::signer::{keypair::Keypair, Signer},
@@ -25,12 +29,16 @@ pub async fn test_add_custody(
     multisig_signers: &[&Keypair],
 ) -> std::result::Result<(anchor_lang::prelude::Pubkey, u8), BanksClientError> {
     // ==== WHEN ==============================================================
-    let multisig_pda = pda::get_multisig_pda().0;
+    let multisig_pda = pda::get_multisig_pda(Role::PoolAdmin).0;
     let transfer_authority_pda = pda::get_transfer_authority_pda().0;
     let perpetuals_pda = pda::get_perpetuals_pda().0;
     let (custody_pda, custody_bump) = pda::get_custody_pda(pool_pda, custody_token_mint);
     let (custody_token_account_pda, custody_token_account_bump) =
         pda::get_custody_token_account_pda(pool_pda, custody_token_mint);
+    let insurance_fund_token_account_pda =
+        pda::get_insurance_fund_token_account_pda(pool_pda, custody_token_mint).0;
+    // custodies are set up with a custom oracle, created on the first price update
+    let custom_oracle_pda = pda::get_custom_oracle_account(pool_pda, custody_token_mint).0;
 
     let multisig_account = utils::get_account::<Multisig>(program_test_ctx, multisig_pda).await;
 
@@ -47,7 +55,10 @@ pub async fn test_add_custody(
                 pool: *pool_pda,
                 custody: custody_pda,
                 custody_token_account: custody_token_account_pda,
+                insurance_fund_token_account: insurance_fund_token_account_pda,
                 custody_token_mint: *custody_token_mint,
+                oracle_account: custom_oracle_pda,
+                secondary_oracle_account: None,
                 system_program: anchor_lang::system_program::ID,
                 token_program: anchor_spl::token::ID,
                 rent: solana_program::sysvar::rent::ID,
@@ -88,7 +99,8 @@ pub async fn test_add_custody(
         assert_eq!(custody_account.token_account, custody_token_account_pda);
         assert_eq!(custody_account.decimals, custody_token_decimals);
         assert_eq!(custody_account.is_stable, params.is_stable);
-        assert_eq!(custody_account.oracle, params.oracle);
+        assert_eq!(custody_account.oracle, Oracle::Custom(custom_oracle_pda));
+        assert_eq!(custody_account.oracle_params, params.oracle_params);
         assert_eq!(custody_account.pricing, params.pricing);
         assert_eq!(custody_account.permissions, params.permissions);
         assert_eq!(custody_account.fees, params.fees);
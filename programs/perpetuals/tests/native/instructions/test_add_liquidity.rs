@@ -23,7 +23,6 @@ pub async fn test_add_liquidity(
 ) -> std::result::Result<(), BanksClientError> {
     // ==== WHEN ==============================================================
     // Prepare PDA and addresses
-    let transfer_authority_pda = pda::get_transfer_authority_pda().0;
     let perpetuals_pda = pda::get_perpetuals_pda().0;
     let custody_pda = pda::get_custody_pda(pool_pda, custody_token_mint).0;
     let custody_token_account_pda =
@@ -51,11 +50,12 @@ pub async fn test_add_liquidity(
             owner: owner.pubkey(),
             funding_account: funding_account_address,
             lp_token_account: lp_token_account_address,
-            transfer_authority: transfer_authority_pda,
+            transfer_authority: pda::get_transfer_authority_pda().0,
             perpetuals: perpetuals_pda,
             pool: *pool_pda,
             custody: custody_pda,
             custody_oracle_account: custody_oracle_account_address,
+            custody_ema_oracle_account: custody_account.ema_oracle.map(|o| o.key()),
             custody_token_account: custody_token_account_pda,
             lp_token_mint: lp_token_mint_pda,
             token_program: anchor_spl::token::ID,
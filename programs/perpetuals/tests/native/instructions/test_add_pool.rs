@@ -3,7 +3,11 @@ use {
     anchor_lang::{prelude::AccountMeta, ToAccountMetas},
     perpetuals::{
         instructions::AddPoolParams,
-        state::{multisig::Multisig, perpetuals::Perpetuals, pool::Pool},
+        state::{
+            multisig::{Multisig, Role},
+            perpetuals::Perpetuals,
+            pool::Pool,
+        },
     },
     solana_program_test::{BanksClientError, ProgramTestContext},
     solana_sdk::signer::{keypair::Keypair, Signer},
@@ -28,9 +32,11 @@ pub async fn test_add_pool(
     BanksClientError,
 > {
     // ==== WHEN ==============================================================
-    let multisig_pda = pda::get_multisig_pda().0;
-    let transfer_authority_pda = pda::get_transfer_authority_pda().0;
+    let multisig_pda = pda::get_multisig_pda(Role::PoolAdmin).0;
     let perpetuals_pda = pda::get_perpetuals_pda().0;
+    let pools_count = utils::get_account::<Perpetuals>(program_test_ctx, perpetuals_pda)
+        .await
+        .pools;
     let (pool_pda, pool_bump) = pda::get_pool_pda(String::from_str(pool_name).unwrap());
     let (lp_token_mint_pda, lp_token_mint_bump) = pda::get_lp_token_mint_pda(&pool_pda);
 
@@ -44,7 +50,7 @@ pub async fn test_add_pool(
             let accounts = perpetuals::accounts::AddPool {
                 admin: admin.pubkey(),
                 multisig: multisig_pda,
-                transfer_authority: transfer_authority_pda,
+                transfer_authority: pda::get_transfer_authority_pda().0,
                 perpetuals: perpetuals_pda,
                 pool: pool_pda,
                 lp_token_mint: lp_token_mint_pda,
@@ -70,6 +76,7 @@ pub async fn test_add_pool(
             perpetuals::instruction::AddPool {
                 params: AddPoolParams {
                     name: String::from_str(pool_name).unwrap(),
+                    permissions: utils::permissions_full(),
                 },
             },
             Some(&payer.pubkey()),
@@ -86,11 +93,13 @@ pub async fn test_add_pool(
     assert_eq!(pool_account.name.as_str(), pool_name);
     assert_eq!(pool_account.bump, pool_bump);
     assert_eq!(pool_account.lp_token_bump, lp_token_mint_bump);
+    assert_eq!(pool_account.permissions, utils::permissions_full());
+    assert_eq!(pool_account.version, Pool::VERSION);
 
     let perpetuals_account =
         utils::get_account::<Perpetuals>(program_test_ctx, perpetuals_pda).await;
 
-    assert_eq!(*perpetuals_account.pools.last().unwrap(), pool_pda);
+    assert_eq!(perpetuals_account.pools, pools_count + 1);
 
     // Need to handle test feature
     // assert_eq!(
@@ -0,0 +1,109 @@
+use {
+    super::get_update_pool_ix,
+    crate::utils::{self, pda},
+    anchor_lang::{prelude::Pubkey, ToAccountMetas},
+    perpetuals::{
+        instructions::AutoDeleverageParams,
+        state::{custody::Custody, position::Position},
+    },
+    solana_program::instruction::AccountMeta,
+    solana_program_test::{BanksClientError, ProgramTestContext},
+    solana_sdk::signer::{keypair::Keypair, Signer},
+    tokio::sync::RwLock,
+};
+
+pub async fn test_auto_deleverage(
+    program_test_ctx: &RwLock<ProgramTestContext>,
+    keeper: &Keypair,
+    payer: &Keypair,
+    pool_pda: &Pubkey,
+    custody_token_mint: &Pubkey,
+    position_pda: &Pubkey,
+    // other profitable positions the deleveraged position is ranked against
+    other_position_pdas: &[Pubkey],
+) -> std::result::Result<(), BanksClientError> {
+    // ==== WHEN ==============================================================
+    let owner = {
+        let position_account =
+            utils::get_account::<Position>(program_test_ctx, *position_pda).await;
+        position_account.owner
+    };
+
+    // Prepare PDA and addresses
+    let transfer_authority_pda = pda::get_transfer_authority_pda().0;
+    let perpetuals_pda = pda::get_perpetuals_pda().0;
+    let custody_pda = pda::get_custody_pda(pool_pda, custody_token_mint).0;
+    let custody_token_account_pda =
+        pda::get_custody_token_account_pda(pool_pda, custody_token_mint).0;
+
+    let receiving_account_address =
+        utils::find_associated_token_account(&owner, custody_token_mint).0;
+
+    let custody_account = utils::get_account::<Custody>(program_test_ctx, custody_pda).await;
+    let custody_oracle_account_address = custody_account.oracle.key();
+
+    // Save account state before tx execution
+    let receiving_account_before =
+        utils::get_token_account(program_test_ctx, receiving_account_address).await;
+
+    let mut accounts_meta = perpetuals::accounts::AutoDeleverage {
+        signer: keeper.pubkey(),
+        owner,
+        receiving_account: receiving_account_address,
+        transfer_authority: transfer_authority_pda,
+        perpetuals: perpetuals_pda,
+        pool: *pool_pda,
+        position: *position_pda,
+        custody: custody_pda,
+        custody_oracle_account: custody_oracle_account_address,
+        collateral_custody: custody_pda,
+        collateral_custody_oracle_account: custody_oracle_account_address,
+        collateral_custody_token_account: custody_token_account_pda,
+        token_program: anchor_spl::token::ID,
+    }
+    .to_account_metas(None);
+
+    for other_position_pda in other_position_pdas {
+        accounts_meta.push(AccountMeta::new_readonly(*other_position_pda, false));
+    }
+
+    utils::create_and_execute_perpetuals_ix(
+        program_test_ctx,
+        accounts_meta,
+        perpetuals::instruction::AutoDeleverage {
+            params: AutoDeleverageParams {},
+        },
+        Some(&payer.pubkey()),
+        &[keeper, payer],
+        Some(get_update_pool_ix(program_test_ctx, payer, pool_pda).await?),
+        Some(get_update_pool_ix(program_test_ctx, payer, pool_pda).await?),
+    )
+    .await?;
+
+    // ==== THEN ==============================================================
+    // Check the position is closed
+    {
+        assert!(!utils::account_exists(program_test_ctx, *position_pda).await);
+    }
+
+    // Check the balance change
+    {
+        let receiving_account_after =
+            utils::get_token_account(program_test_ctx, receiving_account_address).await;
+
+        assert!(receiving_account_after.amount > receiving_account_before.amount);
+    }
+
+    // Check the custody stats
+    {
+        let custody_account_after =
+            utils::get_account::<Custody>(program_test_ctx, custody_pda).await;
+
+        assert_eq!(
+            custody_account_after.long_positions.open_positions,
+            custody_account.long_positions.open_positions - 1
+        );
+    }
+
+    Ok(())
+}
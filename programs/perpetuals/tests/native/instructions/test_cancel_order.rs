@@ -0,0 +1,70 @@
+use {
+    crate::utils::{self, pda},
+    anchor_lang::{prelude::Pubkey, ToAccountMetas},
+    perpetuals::{instructions::CancelOrderParams, state::order::Order},
+    solana_program_test::{BanksClientError, ProgramTestContext},
+    solana_sdk::signer::{keypair::Keypair, Signer},
+    tokio::sync::RwLock,
+};
+
+pub async fn test_cancel_order(
+    program_test_ctx: &RwLock<ProgramTestContext>,
+    owner: &Keypair,
+    payer: &Keypair,
+    order_pda: &Pubkey,
+) -> std::result::Result<(), BanksClientError> {
+    // ==== WHEN ==============================================================
+
+    // Prepare PDA and addresses
+    let transfer_authority_pda = pda::get_transfer_authority_pda().0;
+    let perpetuals_pda = pda::get_perpetuals_pda().0;
+    let order_token_account_pda = pda::get_order_token_account_pda(order_pda).0;
+
+    let order_token_account =
+        utils::get_token_account(program_test_ctx, order_token_account_pda).await;
+    let receiving_account_address =
+        utils::find_associated_token_account(&owner.pubkey(), &order_token_account.mint).0;
+
+    // Save account state before tx execution
+    let order_account = utils::get_account::<Order>(program_test_ctx, *order_pda).await;
+    let owner_receiving_account_before =
+        utils::get_token_account(program_test_ctx, receiving_account_address).await;
+
+    utils::create_and_execute_perpetuals_ix(
+        program_test_ctx,
+        perpetuals::accounts::CancelOrder {
+            owner: owner.pubkey(),
+            receiving_account: receiving_account_address,
+            transfer_authority: transfer_authority_pda,
+            perpetuals: perpetuals_pda,
+            order: *order_pda,
+            order_token_account: order_token_account_pda,
+            token_program: anchor_spl::token::ID,
+        }
+        .to_account_metas(None),
+        perpetuals::instruction::CancelOrder {
+            params: CancelOrderParams {},
+        },
+        Some(&payer.pubkey()),
+        &[owner, payer],
+        None,
+        None,
+    )
+    .await?;
+
+    // ==== THEN ==============================================================
+    // Check the escrowed collateral is returned and the order accounts are closed
+    {
+        let owner_receiving_account_after =
+            utils::get_token_account(program_test_ctx, receiving_account_address).await;
+
+        assert_eq!(
+            owner_receiving_account_after.amount,
+            owner_receiving_account_before.amount + order_account.collateral_amount
+        );
+        assert!(!utils::account_exists(program_test_ctx, *order_pda).await);
+        assert!(!utils::account_exists(program_test_ctx, order_token_account_pda).await);
+    }
+
+    Ok(())
+}
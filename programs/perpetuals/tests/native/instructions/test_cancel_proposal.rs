@@ -0,0 +1,57 @@
+use {
+    crate::utils::{self, pda},
+    anchor_lang::ToAccountMetas,
+    perpetuals::{
+        instructions::CancelProposalParams,
+        state::{
+            multisig::{Multisig, Role},
+            proposal::{Proposal, ProposalStatus},
+        },
+    },
+    solana_program_test::{BanksClientError, ProgramTestContext},
+    solana_sdk::signer::{keypair::Keypair, Signer},
+    tokio::sync::RwLock,
+};
+
+pub async fn test_cancel_proposal(
+    program_test_ctx: &RwLock<ProgramTestContext>,
+    payer: &Keypair,
+    proposal_id: u64,
+    multisig_signers: &[&Keypair],
+) -> std::result::Result<(), BanksClientError> {
+    // ==== WHEN ==============================================================
+    // Pauser acts as guardian over all queued proposals
+    let multisig_pda = pda::get_multisig_pda(Role::Pauser).0;
+    let multisig_account = utils::get_account::<Multisig>(program_test_ctx, multisig_pda).await;
+    let proposal_pda = pda::get_proposal_pda(proposal_id).0;
+
+    // One Tx per multisig signer
+    for i in 0..multisig_account.min_signatures {
+        let signer: &Keypair = multisig_signers[i as usize];
+
+        utils::create_and_execute_perpetuals_ix(
+            program_test_ctx,
+            perpetuals::accounts::CancelProposal {
+                admin: signer.pubkey(),
+                multisig: multisig_pda,
+                proposal: proposal_pda,
+            }
+            .to_account_metas(None),
+            perpetuals::instruction::CancelProposal {
+                params: CancelProposalParams {},
+            },
+            Some(&payer.pubkey()),
+            &[payer, signer],
+            None,
+            None,
+        )
+        .await?;
+    }
+
+    // ==== THEN ==============================================================
+    let proposal_account = utils::get_account::<Proposal>(program_test_ctx, proposal_pda).await;
+
+    assert_eq!(proposal_account.status, ProposalStatus::Cancelled);
+
+    Ok(())
+}
@@ -2,7 +2,11 @@ use {
     super::get_update_pool_ix,
     crate::utils::{self, pda},
     anchor_lang::{prelude::Pubkey, ToAccountMetas},
-    perpetuals::{instructions::ClosePositionParams, state::custody::Custody},
+    perpetuals::{
+        instructions::ClosePositionParams,
+        state::{custody::Custody, position::Position},
+    },
+    solana_program::instruction::AccountMeta,
     solana_program_test::{BanksClientError, ProgramTestContext},
     solana_sdk::signer::{keypair::Keypair, Signer},
     tokio::sync::RwLock,
@@ -25,6 +29,8 @@ pub async fn test_close_position(
     let custody_pda = pda::get_custody_pda(pool_pda, custody_token_mint).0;
     let custody_token_account_pda =
         pda::get_custody_token_account_pda(pool_pda, custody_token_mint).0;
+    let insurance_fund_token_account_pda =
+        pda::get_insurance_fund_token_account_pda(pool_pda, custody_token_mint).0;
 
     let receiving_account_address =
         utils::find_associated_token_account(&owner.pubkey(), custody_token_mint).0;
@@ -39,23 +45,32 @@ pub async fn test_close_position(
     let custody_token_account_before =
         utils::get_token_account(program_test_ctx, custody_token_account_pda).await;
 
+    let mut accounts_meta = perpetuals::accounts::ClosePosition {
+        owner: owner.pubkey(),
+        receiving_account: receiving_account_address,
+        transfer_authority: transfer_authority_pda,
+        perpetuals: perpetuals_pda,
+        pool: *pool_pda,
+        position: *position_pda,
+        custody: custody_pda,
+        custody_oracle_account: custody_oracle_account_address,
+        collateral_custody: custody_pda,
+        collateral_custody_oracle_account: custody_oracle_account_address,
+        collateral_custody_token_account: custody_token_account_pda,
+        insurance_fund_token_account: insurance_fund_token_account_pda,
+        token_program: anchor_spl::token::ID,
+    }
+    .to_account_metas(None);
+
+    // linked positions settle with their margin account
+    let position_account = utils::get_account::<Position>(program_test_ctx, *position_pda).await;
+    if position_account.margin_account != Pubkey::default() {
+        accounts_meta.push(AccountMeta::new(position_account.margin_account, false));
+    }
+
     utils::create_and_execute_perpetuals_ix(
         program_test_ctx,
-        perpetuals::accounts::ClosePosition {
-            owner: owner.pubkey(),
-            receiving_account: receiving_account_address,
-            transfer_authority: transfer_authority_pda,
-            perpetuals: perpetuals_pda,
-            pool: *pool_pda,
-            position: *position_pda,
-            custody: custody_pda,
-            custody_oracle_account: custody_oracle_account_address,
-            collateral_custody: custody_pda,
-            collateral_custody_oracle_account: custody_oracle_account_address,
-            collateral_custody_token_account: custody_token_account_pda,
-            token_program: anchor_spl::token::ID,
-        }
-        .to_account_metas(None),
+        accounts_meta,
         perpetuals::instruction::ClosePosition { params },
         Some(&payer.pubkey()),
         &[owner, payer],
@@ -0,0 +1,139 @@
+use {
+    super::get_update_pool_ix,
+    crate::utils::{self, pda},
+    anchor_lang::{prelude::Pubkey, ToAccountMetas},
+    perpetuals::{
+        instructions::ExecuteOrderParams,
+        state::{
+            custody::Custody,
+            order::{Order, OrderType},
+            position::Position,
+        },
+    },
+    solana_program_test::{BanksClientError, ProgramTestContext},
+    solana_sdk::signer::{keypair::Keypair, Signer},
+    tokio::sync::RwLock,
+};
+
+pub async fn test_execute_order(
+    program_test_ctx: &RwLock<ProgramTestContext>,
+    keeper: &Keypair,
+    payer: &Keypair,
+    pool_pda: &Pubkey,
+    custody_token_mint: &Pubkey,
+    order_pda: &Pubkey,
+) -> std::result::Result<Pubkey, BanksClientError> {
+    // ==== WHEN ==============================================================
+    let order_account = utils::get_account::<Order>(program_test_ctx, *order_pda).await;
+
+    // Prepare PDA and addresses
+    let transfer_authority_pda = pda::get_transfer_authority_pda().0;
+    let perpetuals_pda = pda::get_perpetuals_pda().0;
+    let custody_pda = pda::get_custody_pda(pool_pda, custody_token_mint).0;
+    let custody_token_account_pda =
+        pda::get_custody_token_account_pda(pool_pda, custody_token_mint).0;
+    let insurance_fund_token_account_pda =
+        pda::get_insurance_fund_token_account_pda(pool_pda, custody_token_mint).0;
+    let order_token_account_pda = pda::get_order_token_account_pda(order_pda).0;
+    let position_pda = pda::get_position_pda(
+        &order_account.owner,
+        pool_pda,
+        &custody_pda,
+        order_account.side,
+        order_account.position_id,
+    )
+    .0;
+
+    // limit orders don't pay out to the owner
+    let receiving_account_address = if order_account.order_type == OrderType::Limit {
+        None
+    } else {
+        Some(utils::find_associated_token_account(&order_account.owner, custody_token_mint).0)
+    };
+    let rewards_receiving_account_address =
+        utils::find_associated_token_account(&keeper.pubkey(), custody_token_mint).0;
+
+    let custody_account = utils::get_account::<Custody>(program_test_ctx, custody_pda).await;
+    let custody_oracle_account_address = custody_account.oracle.key();
+
+    // Save account state before tx execution
+    let rewards_receiving_account_before =
+        utils::get_token_account(program_test_ctx, rewards_receiving_account_address).await;
+    let position_size_usd_before = if order_account.order_type == OrderType::Limit {
+        0
+    } else {
+        utils::get_account::<Position>(program_test_ctx, position_pda)
+            .await
+            .size_usd
+    };
+
+    utils::create_and_execute_perpetuals_ix(
+        program_test_ctx,
+        perpetuals::accounts::ExecuteOrder {
+            signer: keeper.pubkey(),
+            owner: order_account.owner,
+            receiving_account: receiving_account_address,
+            rewards_receiving_account: rewards_receiving_account_address,
+            transfer_authority: transfer_authority_pda,
+            perpetuals: perpetuals_pda,
+            pool: *pool_pda,
+            order: *order_pda,
+            order_token_account: order_token_account_pda,
+            position: position_pda,
+            custody: custody_pda,
+            custody_oracle_account: custody_oracle_account_address,
+            custody_ema_oracle_account: custody_account.ema_oracle.map(|o| o.key()),
+            collateral_custody: custody_pda,
+            collateral_custody_oracle_account: custody_oracle_account_address,
+            collateral_custody_ema_oracle_account: custody_account.ema_oracle.map(|o| o.key()),
+            collateral_custody_token_account: custody_token_account_pda,
+            insurance_fund_token_account: insurance_fund_token_account_pda,
+            system_program: anchor_lang::system_program::ID,
+            token_program: anchor_spl::token::ID,
+        }
+        .to_account_metas(None),
+        perpetuals::instruction::ExecuteOrder {
+            params: ExecuteOrderParams {},
+        },
+        Some(&payer.pubkey()),
+        &[keeper, payer],
+        Some(get_update_pool_ix(program_test_ctx, payer, pool_pda).await?),
+        None,
+    )
+    .await?;
+
+    // ==== THEN ==============================================================
+    // Check the order accounts are closed
+    {
+        assert!(!utils::account_exists(program_test_ctx, *order_pda).await);
+        assert!(!utils::account_exists(program_test_ctx, order_token_account_pda).await);
+    }
+
+    // Check the position
+    if order_account.order_type == OrderType::Limit {
+        let position_account = utils::get_account::<Position>(program_test_ctx, position_pda).await;
+
+        assert_eq!(position_account.owner, order_account.owner);
+        assert_eq!(position_account.pool, *pool_pda);
+        assert_eq!(position_account.custody, custody_pda);
+        assert_eq!(position_account.side, order_account.side);
+        assert!(position_account.collateral_amount < order_account.collateral_amount);
+
+        // the keeper is rewarded from the escrowed collateral
+        let rewards_receiving_account_after =
+            utils::get_token_account(program_test_ctx, rewards_receiving_account_address).await;
+
+        assert!(rewards_receiving_account_after.amount >= rewards_receiving_account_before.amount);
+    } else if order_account.size >= position_size_usd_before {
+        assert!(!utils::account_exists(program_test_ctx, position_pda).await);
+    } else {
+        let position_account = utils::get_account::<Position>(program_test_ctx, position_pda).await;
+
+        assert_eq!(
+            position_account.size_usd,
+            position_size_usd_before - order_account.size
+        );
+    }
+
+    Ok(position_pda)
+}
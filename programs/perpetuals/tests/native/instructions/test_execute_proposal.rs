@@ -0,0 +1,56 @@
+use {
+    crate::utils::{self, pda},
+    anchor_lang::{prelude::AccountMeta, ToAccountMetas},
+    perpetuals::{
+        instructions::ExecuteProposalParams,
+        state::proposal::{Proposal, ProposalStatus},
+    },
+    solana_program_test::{BanksClientError, ProgramTestContext},
+    solana_sdk::signer::{keypair::Keypair, Signer},
+    tokio::sync::RwLock,
+};
+
+pub async fn test_execute_proposal(
+    program_test_ctx: &RwLock<ProgramTestContext>,
+    executor: &Keypair,
+    payer: &Keypair,
+    proposal_id: u64,
+    // accounts of the proposed instruction, same as passed to propose
+    instruction_accounts_meta: &[AccountMeta],
+) -> std::result::Result<(), BanksClientError> {
+    // ==== WHEN ==============================================================
+    let proposal_pda = pda::get_proposal_pda(proposal_id).0;
+
+    let accounts_meta = {
+        let accounts = perpetuals::accounts::ExecuteProposal {
+            signer: executor.pubkey(),
+            perpetuals: pda::get_perpetuals_pda().0,
+            proposal: proposal_pda,
+        };
+
+        let mut accounts_meta = accounts.to_account_metas(None);
+        accounts_meta.extend_from_slice(instruction_accounts_meta);
+
+        accounts_meta
+    };
+
+    utils::create_and_execute_perpetuals_ix(
+        program_test_ctx,
+        accounts_meta,
+        perpetuals::instruction::ExecuteProposal {
+            params: ExecuteProposalParams {},
+        },
+        Some(&payer.pubkey()),
+        &[executor, payer],
+        None,
+        None,
+    )
+    .await?;
+
+    // ==== THEN ==============================================================
+    let proposal_account = utils::get_account::<Proposal>(program_test_ctx, proposal_pda).await;
+
+    assert_eq!(proposal_account.status, ProposalStatus::Executed);
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use {
+    super::get_update_pool_ix,
+    crate::utils::{self, pda},
+    anchor_lang::{prelude::Pubkey, ToAccountMetas},
+    perpetuals::{
+        instructions::IncreasePositionParams,
+        state::{custody::Custody, position::Position},
+    },
+    solana_program_test::{BanksClientError, ProgramTestContext},
+    solana_sdk::signer::{keypair::Keypair, Signer},
+    tokio::sync::RwLock,
+};
+
+pub async fn test_increase_position(
+    program_test_ctx: &RwLock<ProgramTestContext>,
+    owner: &Keypair,
+    payer: &Keypair,
+    pool_pda: &Pubkey,
+    custody_token_mint: &Pubkey,
+    position_pda: &Pubkey,
+    params: IncreasePositionParams,
+) -> std::result::Result<(), BanksClientError> {
+    // ==== WHEN ==============================================================
+
+    // Prepare PDA and addresses
+    let transfer_authority_pda = pda::get_transfer_authority_pda().0;
+    let perpetuals_pda = pda::get_perpetuals_pda().0;
+    let custody_pda = pda::get_custody_pda(pool_pda, custody_token_mint).0;
+    let custody_token_account_pda =
+        pda::get_custody_token_account_pda(pool_pda, custody_token_mint).0;
+    let insurance_fund_token_account_pda =
+        pda::get_insurance_fund_token_account_pda(pool_pda, custody_token_mint).0;
+
+    let funding_account_address =
+        utils::find_associated_token_account(&owner.pubkey(), custody_token_mint).0;
+
+    let custody_account = utils::get_account::<Custody>(program_test_ctx, custody_pda).await;
+    let custody_oracle_account_address = custody_account.oracle.key();
+
+    // Save account state before tx execution
+    let owner_funding_account_before =
+        utils::get_token_account(program_test_ctx, funding_account_address).await;
+    let position_account_before =
+        utils::get_account::<Position>(program_test_ctx, *position_pda).await;
+
+    utils::create_and_execute_perpetuals_ix(
+        program_test_ctx,
+        perpetuals::accounts::IncreasePosition {
+            owner: owner.pubkey(),
+            funding_account: funding_account_address,
+            transfer_authority: transfer_authority_pda,
+            perpetuals: perpetuals_pda,
+            pool: *pool_pda,
+            position: *position_pda,
+            custody: custody_pda,
+            custody_oracle_account: custody_oracle_account_address,
+            collateral_custody: custody_pda,
+            collateral_custody_oracle_account: custody_oracle_account_address,
+            collateral_custody_token_account: custody_token_account_pda,
+            insurance_fund_token_account: insurance_fund_token_account_pda,
+            token_program: anchor_spl::token::ID,
+        }
+        .to_account_metas(None),
+        perpetuals::instruction::IncreasePosition { params },
+        Some(&payer.pubkey()),
+        &[owner, payer],
+        Some(get_update_pool_ix(program_test_ctx, payer, pool_pda).await?),
+        None,
+    )
+    .await?;
+
+    // ==== THEN ==============================================================
+    // Check the balance change, fees are paid even without additional collateral
+    {
+        let owner_funding_account_after =
+            utils::get_token_account(program_test_ctx, funding_account_address).await;
+
+        assert!(owner_funding_account_after.amount < owner_funding_account_before.amount);
+    }
+
+    // Check the position
+    {
+        let position_account =
+            utils::get_account::<Position>(program_test_ctx, *position_pda).await;
+
+        assert!(position_account.size_usd > position_account_before.size_usd);
+        assert!(position_account.locked_amount > position_account_before.locked_amount);
+        assert_eq!(
+            position_account.collateral_amount,
+            position_account_before.collateral_amount + params.collateral
+        );
+        assert_eq!(
+            position_account.open_time,
+            position_account_before.open_time
+        );
+        assert_eq!(position_account.liquidatable_since, 0);
+    }
+
+    Ok(())
+}
@@ -3,7 +3,10 @@ use {
     anchor_lang::{prelude::AccountMeta, ToAccountMetas},
     perpetuals::{
         instructions::InitParams,
-        state::{multisig::Multisig, perpetuals::Perpetuals},
+        state::{
+            multisig::{Multisig, Role},
+            perpetuals::Perpetuals,
+        },
     },
     solana_program_test::{BanksClientError, ProgramTestContext},
     solana_sdk::signer::{keypair::Keypair, Signer},
@@ -17,19 +20,26 @@ pub async fn test_init(
     multisig_signers: &[&Keypair],
 ) -> std::result::Result<(), BanksClientError> {
     // ==== WHEN ==============================================================
-    let perpetuals_program_data_pda = pda::get_program_data_pda().0;
-    let (multisig_pda, multisig_bump) = pda::get_multisig_pda();
-    let (transfer_authority_pda, transfer_authority_bump) = pda::get_transfer_authority_pda();
+    let roles = [
+        Role::PoolAdmin,
+        Role::RiskAdmin,
+        Role::FeeWithdrawer,
+        Role::Pauser,
+        Role::OracleAuthority,
+    ];
+    let transfer_authority_bump = pda::get_transfer_authority_pda().1;
     let (perpetuals_pda, perpetuals_bump) = pda::get_perpetuals_pda();
 
     let accounts_meta = {
         let accounts = perpetuals::accounts::Init {
-            upgrade_authority: upgrade_authority.pubkey(),
-            multisig: multisig_pda,
-            transfer_authority: transfer_authority_pda,
+            signer: upgrade_authority.pubkey(),
+            pool_admin: pda::get_multisig_pda(Role::PoolAdmin).0,
+            risk_admin: pda::get_multisig_pda(Role::RiskAdmin).0,
+            fee_withdrawer: pda::get_multisig_pda(Role::FeeWithdrawer).0,
+            pauser: pda::get_multisig_pda(Role::Pauser).0,
+            oracle_authority: pda::get_multisig_pda(Role::OracleAuthority).0,
+            transfer_authority: pda::get_transfer_authority_pda().0,
             perpetuals: perpetuals_pda,
-            perpetuals_program_data: perpetuals_program_data_pda,
-            perpetuals_program: perpetuals::ID,
             system_program: anchor_lang::system_program::ID,
             token_program: anchor_spl::token::ID,
         };
@@ -85,10 +95,11 @@ pub async fn test_init(
     );
     assert_eq!(perpetuals_account.perpetuals_bump, perpetuals_bump);
 
-    let multisig_account = utils::get_account::<Multisig>(program_test_ctx, multisig_pda).await;
+    // Assert every role multisig
+    for role in roles {
+        let (multisig_pda, multisig_bump) = pda::get_multisig_pda(role);
+        let multisig_account = utils::get_account::<Multisig>(program_test_ctx, multisig_pda).await;
 
-    // Assert multisig
-    {
         assert_eq!(multisig_account.bump, multisig_bump);
         assert_eq!(multisig_account.min_signatures, params.min_signatures);
 
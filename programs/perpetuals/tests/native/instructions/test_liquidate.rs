@@ -32,6 +32,8 @@ pub async fn test_liquidate(
     let custody_pda = pda::get_custody_pda(pool_pda, custody_token_mint).0;
     let custody_token_account_pda =
         pda::get_custody_token_account_pda(pool_pda, custody_token_mint).0;
+    let insurance_fund_token_account_pda =
+        pda::get_insurance_fund_token_account_pda(pool_pda, custody_token_mint).0;
 
     let receiving_account_address =
         utils::find_associated_token_account(&owner, custody_token_mint).0;
@@ -65,6 +67,7 @@ pub async fn test_liquidate(
             collateral_custody: custody_pda,
             collateral_custody_oracle_account: custody_oracle_account_address,
             collateral_custody_token_account: custody_token_account_pda,
+            insurance_fund_token_account: insurance_fund_token_account_pda,
             token_program: anchor_spl::token::ID,
         }
         .to_account_metas(None),
@@ -27,9 +27,17 @@ pub async fn test_open_position(
     let custody_pda = pda::get_custody_pda(pool_pda, custody_token_mint).0;
     let custody_token_account_pda =
         pda::get_custody_token_account_pda(pool_pda, custody_token_mint).0;
+    let insurance_fund_token_account_pda =
+        pda::get_insurance_fund_token_account_pda(pool_pda, custody_token_mint).0;
 
-    let (position_pda, position_bump) =
-        pda::get_position_pda(&owner.pubkey(), pool_pda, &custody_pda, params.side);
+    let position_counter_pda = pda::get_position_counter_pda(&owner.pubkey()).0;
+    let (position_pda, position_bump) = pda::get_position_pda(
+        &owner.pubkey(),
+        pool_pda,
+        &custody_pda,
+        params.side,
+        params.position_id,
+    );
 
     let funding_account_address =
         utils::find_associated_token_account(&owner.pubkey(), custody_token_mint).0;
@@ -51,12 +59,16 @@ pub async fn test_open_position(
             transfer_authority: transfer_authority_pda,
             perpetuals: perpetuals_pda,
             pool: *pool_pda,
+            position_counter: position_counter_pda,
             position: position_pda,
             custody: custody_pda,
             custody_oracle_account: custody_oracle_account_address,
+            custody_ema_oracle_account: custody_account.ema_oracle.map(|o| o.key()),
             collateral_custody: custody_pda,
             collateral_custody_oracle_account: custody_oracle_account_address,
+            collateral_custody_ema_oracle_account: custody_account.ema_oracle.map(|o| o.key()),
             collateral_custody_token_account: custody_token_account_pda,
+            insurance_fund_token_account: insurance_fund_token_account_pda,
             system_program: anchor_lang::system_program::ID,
             token_program: anchor_spl::token::ID,
         }
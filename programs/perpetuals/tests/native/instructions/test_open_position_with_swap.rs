@@ -0,0 +1,135 @@
+use {
+    super::get_update_pool_ix,
+    crate::utils::{self, pda},
+    anchor_lang::{prelude::Pubkey, ToAccountMetas},
+    perpetuals::{
+        instructions::OpenPositionWithSwapParams,
+        state::{custody::Custody, position::Position},
+    },
+    solana_program_test::{BanksClientError, ProgramTestContext},
+    solana_sdk::signer::{keypair::Keypair, Signer},
+    tokio::sync::RwLock,
+};
+
+pub async fn test_open_position_with_swap(
+    program_test_ctx: &RwLock<ProgramTestContext>,
+    owner: &Keypair,
+    payer: &Keypair,
+    pool_pda: &Pubkey,
+    // token provided by the owner, swapped into the collateral token
+    receiving_custody_token_mint: &Pubkey,
+    custody_token_mint: &Pubkey,
+    params: OpenPositionWithSwapParams,
+) -> std::result::Result<(solana_sdk::pubkey::Pubkey, u8), BanksClientError> {
+    // ==== WHEN ==============================================================
+
+    // Prepare PDA and addresses
+    let transfer_authority_pda = pda::get_transfer_authority_pda().0;
+    let perpetuals_pda = pda::get_perpetuals_pda().0;
+    let receiving_custody_pda = pda::get_custody_pda(pool_pda, receiving_custody_token_mint).0;
+    let receiving_custody_token_account_pda =
+        pda::get_custody_token_account_pda(pool_pda, receiving_custody_token_mint).0;
+    let custody_pda = pda::get_custody_pda(pool_pda, custody_token_mint).0;
+    let custody_token_account_pda =
+        pda::get_custody_token_account_pda(pool_pda, custody_token_mint).0;
+    let insurance_fund_token_account_pda =
+        pda::get_insurance_fund_token_account_pda(pool_pda, custody_token_mint).0;
+
+    let position_counter_pda = pda::get_position_counter_pda(&owner.pubkey()).0;
+    let (position_pda, position_bump) = pda::get_position_pda(
+        &owner.pubkey(),
+        pool_pda,
+        &custody_pda,
+        params.side,
+        params.position_id,
+    );
+
+    let funding_account_address =
+        utils::find_associated_token_account(&owner.pubkey(), receiving_custody_token_mint).0;
+
+    let receiving_custody_account =
+        utils::get_account::<Custody>(program_test_ctx, receiving_custody_pda).await;
+    let receiving_custody_oracle_account_address = receiving_custody_account.oracle.key();
+
+    let custody_account = utils::get_account::<Custody>(program_test_ctx, custody_pda).await;
+    let custody_oracle_account_address = custody_account.oracle.key();
+
+    // Save account state before tx execution
+    let owner_funding_account_before =
+        utils::get_token_account(program_test_ctx, funding_account_address).await;
+    let receiving_custody_token_account_before =
+        utils::get_token_account(program_test_ctx, receiving_custody_token_account_pda).await;
+
+    utils::create_and_execute_perpetuals_ix(
+        program_test_ctx,
+        perpetuals::accounts::OpenPositionWithSwap {
+            owner: owner.pubkey(),
+            funding_account: funding_account_address,
+            transfer_authority: transfer_authority_pda,
+            perpetuals: perpetuals_pda,
+            pool: *pool_pda,
+            position_counter: position_counter_pda,
+            position: position_pda,
+            receiving_custody: receiving_custody_pda,
+            receiving_custody_oracle_account: receiving_custody_oracle_account_address,
+            receiving_custody_ema_oracle_account: receiving_custody_account
+                .ema_oracle
+                .map(|o| o.key()),
+            receiving_custody_token_account: receiving_custody_token_account_pda,
+            custody: custody_pda,
+            custody_oracle_account: custody_oracle_account_address,
+            custody_ema_oracle_account: custody_account.ema_oracle.map(|o| o.key()),
+            collateral_custody: custody_pda,
+            collateral_custody_oracle_account: custody_oracle_account_address,
+            collateral_custody_ema_oracle_account: custody_account.ema_oracle.map(|o| o.key()),
+            collateral_custody_token_account: custody_token_account_pda,
+            insurance_fund_token_account: insurance_fund_token_account_pda,
+            system_program: anchor_lang::system_program::ID,
+            token_program: anchor_spl::token::ID,
+        }
+        .to_account_metas(None),
+        perpetuals::instruction::OpenPositionWithSwap { params },
+        Some(&payer.pubkey()),
+        &[owner, payer],
+        Some(get_update_pool_ix(program_test_ctx, payer, pool_pda).await?),
+        None,
+    )
+    .await?;
+
+    // ==== THEN ==============================================================
+    // Check the balance change
+    {
+        let owner_funding_account_after =
+            utils::get_token_account(program_test_ctx, funding_account_address).await;
+        let receiving_custody_token_account_after =
+            utils::get_token_account(program_test_ctx, receiving_custody_token_account_pda).await;
+
+        assert_eq!(
+            owner_funding_account_after.amount,
+            owner_funding_account_before.amount - params.amount_in
+        );
+        assert_eq!(
+            receiving_custody_token_account_after.amount,
+            receiving_custody_token_account_before.amount + params.amount_in
+        );
+    }
+
+    // Check the position
+    {
+        let position_account = utils::get_account::<Position>(program_test_ctx, position_pda).await;
+
+        assert_eq!(position_account.owner, owner.pubkey());
+        assert_eq!(position_account.pool, *pool_pda);
+        assert_eq!(position_account.custody, custody_pda);
+        assert_eq!(position_account.collateral_custody, custody_pda);
+        assert_eq!(position_account.position_id, params.position_id);
+        assert_eq!(position_account.update_time, 0);
+        assert_eq!(position_account.side, params.side);
+        assert_eq!(position_account.unrealized_profit_usd, 0);
+        assert_eq!(position_account.unrealized_loss_usd, 0);
+        assert!(position_account.collateral_amount > 0);
+        assert_eq!(position_account.bump, position_bump);
+    }
+
+    Ok((position_pda, position_bump))
+}
@@ -0,0 +1,78 @@
+use {
+    crate::utils::{self, pda},
+    anchor_lang::{prelude::AccountMeta, ToAccountMetas},
+    perpetuals::{
+        instructions::ProposeParams,
+        state::{
+            multisig::{Multisig, Role},
+            proposal::{Proposal, ProposalStatus},
+        },
+    },
+    solana_program_test::{BanksClientError, ProgramTestContext},
+    solana_sdk::signer::{keypair::Keypair, Signer},
+    tokio::sync::RwLock,
+};
+
+pub async fn test_propose(
+    program_test_ctx: &RwLock<ProgramTestContext>,
+    payer: &Keypair,
+    role: Role,
+    proposal_id: u64,
+    // see Multisig::get_instruction_data
+    instruction_data: Vec<u8>,
+    // accounts of the proposed instruction
+    instruction_accounts_meta: &[AccountMeta],
+    multisig_signers: &[&Keypair],
+) -> std::result::Result<(), BanksClientError> {
+    // ==== WHEN ==============================================================
+    let multisig_pda = pda::get_multisig_pda(role).0;
+    let multisig_account = utils::get_account::<Multisig>(program_test_ctx, multisig_pda).await;
+    let (proposal_pda, proposal_bump) = pda::get_proposal_pda(proposal_id);
+
+    // One Tx per multisig signer
+    for i in 0..multisig_account.min_signatures {
+        let signer: &Keypair = multisig_signers[i as usize];
+
+        let accounts_meta = {
+            let accounts = perpetuals::accounts::Propose {
+                admin: signer.pubkey(),
+                multisig: multisig_pda,
+                perpetuals: pda::get_perpetuals_pda().0,
+                proposal: proposal_pda,
+                system_program: anchor_lang::system_program::ID,
+            };
+
+            let mut accounts_meta = accounts.to_account_metas(None);
+            accounts_meta.extend_from_slice(instruction_accounts_meta);
+
+            accounts_meta
+        };
+
+        utils::create_and_execute_perpetuals_ix(
+            program_test_ctx,
+            accounts_meta,
+            perpetuals::instruction::Propose {
+                params: ProposeParams {
+                    role,
+                    proposal_id,
+                    instruction_data: instruction_data.clone(),
+                },
+            },
+            Some(&payer.pubkey()),
+            &[payer, signer],
+            None,
+            None,
+        )
+        .await?;
+    }
+
+    // ==== THEN ==============================================================
+    let proposal_account = utils::get_account::<Proposal>(program_test_ctx, proposal_pda).await;
+
+    assert_eq!(proposal_account.proposal_id, proposal_id);
+    assert_eq!(proposal_account.instruction_data, instruction_data);
+    assert_eq!(proposal_account.status, ProposalStatus::Queued);
+    assert_eq!(proposal_account.bump, proposal_bump);
+
+    Ok(())
+}
@@ -5,46 +5,99 @@ use {
         ToAccountMetas,
     },
     perpetuals::{
-        instructions::SetCustodyConfigParams,
-        state::{custody::Custody, multisig::Multisig},
+        instructions::{ExecuteProposalParams, ProposeParams, SetCustodyConfigParams},
+        state::{
+            custody::Custody,
+            multisig::{AdminInstruction, Multisig, Role},
+            proposal::{Proposal, ProposalStatus},
+        },
     },
     solana_program_test::{BanksClientError, ProgramTestContext},
     solana_sdk::signer::{keypair::Keypair, Signer},
     tokio::sync::RwLock,
 };
 
+#[allow(clippy::too_many_arguments)]
 pub async fn test_set_custody_config(
     program_test_ctx: &RwLock<ProgramTestContext>,
     admin: &Keypair,
     payer: &Keypair,
     pool_pda: &Pubkey,
     custody_pda: &Pubkey,
+    proposal_id: u64,
     params: SetCustodyConfigParams,
     multisig_signers: &[&Keypair],
 ) -> std::result::Result<(), BanksClientError> {
     // ==== WHEN ==============================================================
-    let multisig_pda = pda::get_multisig_pda().0;
+    let multisig_pda = pda::get_multisig_pda(Role::RiskAdmin).0;
     let multisig_account = utils::get_account::<Multisig>(program_test_ctx, multisig_pda).await;
+    let proposal_pda = pda::get_proposal_pda(proposal_id).0;
+
+    // accounts of the proposed instruction
+    let proposal_accounts_meta = vec![
+        AccountMeta {
+            pubkey: *pool_pda,
+            is_signer: false,
+            is_writable: true,
+        },
+        AccountMeta {
+            pubkey: *custody_pda,
+            is_signer: false,
+            is_writable: true,
+        },
+    ];
+
+    let instruction_data =
+        Multisig::get_instruction_data(AdminInstruction::SetCustodyConfig, &params).unwrap();
 
     // One Tx per multisig signer
     for i in 0..multisig_account.min_signatures {
         let signer: &Keypair = multisig_signers[i as usize];
 
         let accounts_meta = {
-            let accounts = perpetuals::accounts::SetCustodyConfig {
-                admin: admin.pubkey(),
+            let accounts = perpetuals::accounts::Propose {
+                admin: signer.pubkey(),
                 multisig: multisig_pda,
-                pool: *pool_pda,
-                custody: *custody_pda,
+                perpetuals: pda::get_perpetuals_pda().0,
+                proposal: proposal_pda,
+                system_program: anchor_lang::system_program::ID,
             };
 
             let mut accounts_meta = accounts.to_account_metas(None);
+            accounts_meta.extend_from_slice(&proposal_accounts_meta);
+
+            accounts_meta
+        };
+
+        utils::create_and_execute_perpetuals_ix(
+            program_test_ctx,
+            accounts_meta,
+            perpetuals::instruction::Propose {
+                params: ProposeParams {
+                    role: Role::RiskAdmin,
+                    proposal_id,
+                    instruction_data: instruction_data.clone(),
+                },
+            },
+            Some(&payer.pubkey()),
+            &[payer, signer],
+            None,
+            None,
+        )
+        .await?;
+    }
 
-            accounts_meta.push(AccountMeta {
-                pubkey: signer.pubkey(),
-                is_signer: true,
-                is_writable: false,
-            });
+    // Proposals are executable right away with the test fixtures delay
+    {
+        let accounts_meta = {
+            let accounts = perpetuals::accounts::ExecuteProposal {
+                signer: admin.pubkey(),
+                perpetuals: pda::get_perpetuals_pda().0,
+                proposal: proposal_pda,
+            };
+
+            let mut accounts_meta = accounts.to_account_metas(None);
+            accounts_meta.extend_from_slice(&proposal_accounts_meta);
 
             accounts_meta
         };
@@ -52,11 +105,11 @@ pub async fn test_set_custody_config(
         utils::create_and_execute_perpetuals_ix(
             program_test_ctx,
             accounts_meta,
-            perpetuals::instruction::SetCustodyConfig {
-                params: params.clone(),
+            perpetuals::instruction::ExecuteProposal {
+                params: ExecuteProposalParams {},
             },
             Some(&payer.pubkey()),
-            &[admin, payer, signer],
+            &[admin, payer],
             None,
             None,
         )
@@ -64,13 +117,15 @@ pub async fn test_set_custody_config(
     }
 
     // ==== THEN ==============================================================
+    let proposal_account = utils::get_account::<Proposal>(program_test_ctx, proposal_pda).await;
+    assert_eq!(proposal_account.status, ProposalStatus::Executed);
+
     let custody_account = utils::get_account::<Custody>(program_test_ctx, *custody_pda).await;
 
     // Check custody account
     {
         assert_eq!(custody_account.pool, *pool_pda);
         assert_eq!(custody_account.is_stable, params.is_stable);
-        assert_eq!(custody_account.oracle, params.oracle);
         assert_eq!(custody_account.pricing, params.pricing);
         assert_eq!(custody_account.permissions, params.permissions);
         assert_eq!(custody_account.fees, params.fees);
@@ -6,7 +6,10 @@ use {
     },
     perpetuals::{
         instructions::SetCustomOraclePriceParams,
-        state::{multisig::Multisig, oracle::CustomOracle},
+        state::{
+            multisig::{Multisig, Role},
+            oracle::CustomOracle,
+        },
     },
     solana_program_test::{BanksClientError, ProgramTestContext},
     solana_sdk::signer::{keypair::Keypair, Signer},
@@ -25,7 +28,7 @@ pub async fn test_set_custom_oracle_price(
     multisig_signers: &[&Keypair],
 ) -> std::result::Result<(), BanksClientError> {
     // ==== WHEN ==============================================================
-    let multisig_pda = pda::get_multisig_pda().0;
+    let multisig_pda = pda::get_multisig_pda(Role::OracleAuthority).0;
     let perpetuals_pda = pda::get_perpetuals_pda().0;
 
     let multisig_account = utils::get_account::<Multisig>(program_test_ctx, multisig_pda).await;
@@ -0,0 +1,76 @@
+use {
+    crate::utils::{self, pda},
+    anchor_lang::{prelude::Pubkey, ToAccountMetas},
+    perpetuals::{
+        instructions::UnlinkPositionParams,
+        state::{custody::Custody, margin_account::MarginAccount, position::Position},
+    },
+    solana_program::instruction::AccountMeta,
+    solana_program_test::{BanksClientError, ProgramTestContext},
+    solana_sdk::signer::{keypair::Keypair, Signer},
+    tokio::sync::RwLock,
+};
+
+pub async fn test_unlink_position(
+    program_test_ctx: &RwLock<ProgramTestContext>,
+    owner: &Keypair,
+    payer: &Keypair,
+    pool_pda: &Pubkey,
+    custody_token_mint: &Pubkey,
+    position_pda: &Pubkey,
+    // other linked positions, their custodies and the oracle accounts of these custodies
+    remaining_accounts: &[Pubkey],
+) -> std::result::Result<(), BanksClientError> {
+    // ==== WHEN ==============================================================
+
+    // Prepare PDA and addresses
+    let perpetuals_pda = pda::get_perpetuals_pda().0;
+    let margin_account_pda = pda::get_margin_account_pda(&owner.pubkey(), pool_pda).0;
+    let custody_pda = pda::get_custody_pda(pool_pda, custody_token_mint).0;
+
+    let custody_account = utils::get_account::<Custody>(program_test_ctx, custody_pda).await;
+    let custody_oracle_account_address = custody_account.oracle.key();
+
+    let mut accounts_meta = perpetuals::accounts::UnlinkPosition {
+        owner: owner.pubkey(),
+        perpetuals: perpetuals_pda,
+        pool: *pool_pda,
+        margin_account: margin_account_pda,
+        position: *position_pda,
+        custody: custody_pda,
+        custody_oracle_account: custody_oracle_account_address,
+        collateral_custody: custody_pda,
+        collateral_custody_oracle_account: custody_oracle_account_address,
+    }
+    .to_account_metas(None);
+
+    for remaining_account in remaining_accounts {
+        accounts_meta.push(AccountMeta::new_readonly(*remaining_account, false));
+    }
+
+    utils::create_and_execute_perpetuals_ix(
+        program_test_ctx,
+        accounts_meta,
+        perpetuals::instruction::UnlinkPosition {
+            params: UnlinkPositionParams {},
+        },
+        Some(&payer.pubkey()),
+        &[owner, payer],
+        None,
+        None,
+    )
+    .await?;
+
+    // ==== THEN ==============================================================
+    {
+        let position_account =
+            utils::get_account::<Position>(program_test_ctx, *position_pda).await;
+        let margin_account =
+            utils::get_account::<MarginAccount>(program_test_ctx, margin_account_pda).await;
+
+        assert_eq!(position_account.margin_account, Pubkey::default());
+        assert!(!margin_account.positions.contains(position_pda));
+    }
+
+    Ok(())
+}
@@ -6,6 +6,8 @@ pub mod utils;
 pub async fn test_integration() {
     tests_suite::basic_interactions().await;
 
+    tests_suite::multisig::proposals().await;
+
     tests_suite::swap::insuffisient_fund().await;
 
     tests_suite::liquidity::fixed_fees().await;
@@ -15,6 +17,11 @@ pub async fn test_integration() {
     tests_suite::position::min_max_leverage().await;
     tests_suite::position::liquidate_position().await;
     tests_suite::position::max_user_profit().await;
+    tests_suite::position::increase_position().await;
+    tests_suite::position::execute_orders().await;
+    tests_suite::position::auto_deleverage().await;
+    tests_suite::position::margin_account().await;
+    tests_suite::position::open_position_with_swap().await;
 
     tests_suite::lp_token::lp_token_price().await;
 }
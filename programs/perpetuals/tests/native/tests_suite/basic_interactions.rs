@@ -107,6 +107,7 @@ pub async fn basic_interactions() {
             &test_setup.pool_pda,
             eth_mint,
             OpenPositionParams {
+                position_id: 0,
                 // max price paid (slippage implied)
                 price: utils::scale(1_550, USDC_DECIMALS),
                 collateral: utils::scale_f64(0.1, ETH_DECIMALS),
@@ -129,6 +130,7 @@ pub async fn basic_interactions() {
             ClosePositionParams {
                 // lowest exit price paid (slippage implied)
                 price: utils::scale(1_450, USDC_DECIMALS),
+                size_usd: u64::MAX,
             },
         )
         .await
@@ -0,0 +1,3 @@
+pub mod proposals;
+
+pub use proposals::*;
@@ -0,0 +1,184 @@
+use {
+    crate::{
+        instructions,
+        utils::{self, fixtures, pda},
+    },
+    perpetuals::{
+        instructions::{InitParams, SetPermissionsParams},
+        state::{
+            multisig::{AdminInstruction, Multisig, Role},
+            perpetuals::Perpetuals,
+        },
+    },
+    solana_program_test::{ProgramTest, ProgramTestContext},
+    solana_sdk::signature::Keypair,
+    tokio::sync::RwLock,
+};
+
+const PROPOSAL_DELAY: i64 = 3_600;
+
+fn set_permissions_data(allow_swap: bool, allow_add_liquidity: bool) -> Vec<u8> {
+    Multisig::get_instruction_data(
+        AdminInstruction::SetPermissions,
+        &SetPermissionsParams {
+            allow_swap,
+            allow_add_liquidity,
+            allow_remove_liquidity: true,
+            allow_open_position: true,
+            allow_close_position: true,
+            allow_pnl_withdrawal: true,
+            allow_collateral_withdrawal: true,
+            allow_size_change: true,
+        },
+    )
+    .unwrap()
+}
+
+pub async fn proposals() {
+    let mut program_test = ProgramTest::new("perpetuals", perpetuals::id(), None);
+
+    let keypairs: Vec<Keypair> =
+        utils::create_and_fund_multiple_accounts(&mut program_test, 7).await;
+    let (payer, upgrade_authority, executor_a, executor_b) =
+        (&keypairs[0], &keypairs[1], &keypairs[2], &keypairs[3]);
+    let multisig_signers: Vec<&Keypair> = keypairs[4..].iter().collect();
+
+    let program_test_ctx: RwLock<ProgramTestContext> =
+        RwLock::new(program_test.start_with_context().await);
+
+    instructions::test_init(
+        &program_test_ctx,
+        upgrade_authority,
+        InitParams {
+            proposal_delay: PROPOSAL_DELAY,
+            ..fixtures::init_params_permissions_full(1)
+        },
+        &multisig_signers,
+    )
+    .await
+    .unwrap();
+
+    let perpetuals_pda = pda::get_perpetuals_pda().0;
+
+    // Disabling swaps reduces risk and is executable right away
+    instructions::test_propose(
+        &program_test_ctx,
+        payer,
+        Role::Pauser,
+        0,
+        set_permissions_data(false, true),
+        &[],
+        &multisig_signers,
+    )
+    .await
+    .unwrap();
+
+    instructions::test_execute_proposal(&program_test_ctx, executor_a, payer, 0, &[])
+        .await
+        .unwrap();
+
+    let perpetuals_account =
+        utils::get_account::<Perpetuals>(&program_test_ctx, perpetuals_pda).await;
+    assert!(!perpetuals_account.permissions.allow_swap);
+
+    // Re-enabling swaps has to wait for the delay
+    instructions::test_propose(
+        &program_test_ctx,
+        payer,
+        Role::Pauser,
+        1,
+        set_permissions_data(true, true),
+        &[],
+        &multisig_signers,
+    )
+    .await
+    .unwrap();
+
+    assert!(
+        instructions::test_execute_proposal(&program_test_ctx, executor_a, payer, 1, &[])
+            .await
+            .is_err()
+    );
+
+    utils::warp_forward(&program_test_ctx, PROPOSAL_DELAY).await;
+
+    instructions::test_execute_proposal(&program_test_ctx, executor_b, payer, 1, &[])
+        .await
+        .unwrap();
+
+    let perpetuals_account =
+        utils::get_account::<Perpetuals>(&program_test_ctx, perpetuals_pda).await;
+    assert!(perpetuals_account.permissions.allow_swap);
+
+    // A proposal exempt from the delay waits for it if it no longer reduces risk
+    instructions::test_propose(
+        &program_test_ctx,
+        payer,
+        Role::Pauser,
+        2,
+        set_permissions_data(false, true),
+        &[],
+        &multisig_signers,
+    )
+    .await
+    .unwrap();
+
+    instructions::test_propose(
+        &program_test_ctx,
+        payer,
+        Role::Pauser,
+        3,
+        set_permissions_data(true, false),
+        &[],
+        &multisig_signers,
+    )
+    .await
+    .unwrap();
+
+    instructions::test_execute_proposal(&program_test_ctx, executor_a, payer, 3, &[])
+        .await
+        .unwrap();
+
+    // proposal 2 would now re-enable add liquidity
+    assert!(
+        instructions::test_execute_proposal(&program_test_ctx, executor_a, payer, 2, &[])
+            .await
+            .is_err()
+    );
+
+    utils::warp_forward(&program_test_ctx, PROPOSAL_DELAY).await;
+
+    instructions::test_execute_proposal(&program_test_ctx, executor_b, payer, 2, &[])
+        .await
+        .unwrap();
+
+    let perpetuals_account =
+        utils::get_account::<Perpetuals>(&program_test_ctx, perpetuals_pda).await;
+    assert!(!perpetuals_account.permissions.allow_swap);
+    assert!(perpetuals_account.permissions.allow_add_liquidity);
+
+    // Cancelled proposals can't be executed
+    instructions::test_propose(
+        &program_test_ctx,
+        payer,
+        Role::Pauser,
+        4,
+        set_permissions_data(true, true),
+        &[],
+        &multisig_signers,
+    )
+    .await
+    .unwrap();
+
+    instructions::test_cancel_proposal(&program_test_ctx, payer, 4, &multisig_signers)
+        .await
+        .unwrap();
+
+    utils::warp_forward(&program_test_ctx, PROPOSAL_DELAY).await;
+
+    assert!(
+        instructions::test_execute_proposal(&program_test_ctx, executor_a, payer, 4, &[])
+            .await
+            .is_err()
+    );
+}
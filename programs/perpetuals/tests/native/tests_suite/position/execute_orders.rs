@@ -0,0 +1,262 @@
+use {
+    crate::{instructions, utils},
+    maplit::hashmap,
+    perpetuals::{
+        instructions::{PlaceOrderParams, SetCustomOraclePriceParams},
+        state::{
+            custody::PricingParams,
+            order::OrderType,
+            perpetuals::Perpetuals,
+            position::{Position, Side},
+        },
+    },
+    solana_sdk::signer::keypair::Keypair,
+};
+
+const ETH_DECIMALS: u8 = 9;
+const USDC_DECIMALS: u8 = 6;
+
+pub async fn execute_orders() {
+    let test_setup = utils::TestSetup::new(
+        vec![
+            utils::UserParam {
+                name: "alice",
+                token_balances: hashmap! {
+                    "usdc" => utils::scale(1_000, USDC_DECIMALS),
+                    "eth" => utils::scale(100, ETH_DECIMALS),
+                },
+            },
+            utils::UserParam {
+                name: "martin",
+                token_balances: hashmap! {
+                    "usdc" => utils::scale(1_000, USDC_DECIMALS),
+                    "eth" => utils::scale(2, ETH_DECIMALS),
+                },
+            },
+        ],
+        vec![
+            utils::MintParam {
+                name: "usdc",
+                decimals: USDC_DECIMALS,
+            },
+            utils::MintParam {
+                name: "eth",
+                decimals: ETH_DECIMALS,
+            },
+        ],
+        vec!["admin_a", "admin_b", "admin_c"],
+        "main_pool",
+        vec![
+            utils::SetupCustodyWithLiquidityParams {
+                setup_custody_params: utils::SetupCustodyParams {
+                    mint_name: "usdc",
+                    is_stable: true,
+                    is_virtual: false,
+                    target_ratio: utils::ratio_from_percentage(50.0),
+                    min_ratio: utils::ratio_from_percentage(0.0),
+                    max_ratio: utils::ratio_from_percentage(100.0),
+                    initial_price: utils::scale(1, USDC_DECIMALS),
+                    initial_conf: utils::scale_f64(0.01, USDC_DECIMALS),
+                    pricing_params: None,
+                    permissions: None,
+                    fees: None,
+                    borrow_rate: None,
+                },
+                liquidity_amount: utils::scale(1_000, USDC_DECIMALS),
+                payer_user_name: "alice",
+            },
+            utils::SetupCustodyWithLiquidityParams {
+                setup_custody_params: utils::SetupCustodyParams {
+                    mint_name: "eth",
+                    is_stable: false,
+                    is_virtual: false,
+                    target_ratio: utils::ratio_from_percentage(100.0),
+                    min_ratio: utils::ratio_from_percentage(0.0),
+                    max_ratio: utils::ratio_from_percentage(100.0),
+                    initial_price: utils::scale(1_500, ETH_DECIMALS),
+                    initial_conf: utils::scale(10, ETH_DECIMALS),
+                    pricing_params: Some(PricingParams {
+                        // Expressed in BPS, with BPS = 10_000
+                        // 50_000 = x5, 100_000 = x10
+                        max_leverage: 100_000,
+                        ..utils::fixtures::pricing_params_regular(false)
+                    }),
+                    permissions: None,
+                    fees: None,
+                    borrow_rate: None,
+                },
+                liquidity_amount: utils::scale(100, ETH_DECIMALS),
+                payer_user_name: "alice",
+            },
+        ],
+    )
+    .await;
+
+    let alice = test_setup.get_user_keypair_by_name("alice");
+    let martin = test_setup.get_user_keypair_by_name("martin");
+
+    let admin_a = test_setup.get_multisig_member_keypair_by_name("admin_a");
+
+    let multisig_signers = test_setup.get_multisig_signers();
+
+    let eth_mint = &test_setup.get_mint_by_name("eth");
+
+    // Martin: Place a 1 ETH limit long x5 at 1_450
+    let (limit_order_pda, _) = instructions::test_place_order(
+        &test_setup.program_test_ctx,
+        martin,
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+        eth_mint,
+        PlaceOrderParams {
+            order_id: 0,
+            position_id: 0,
+            order_type: OrderType::Limit,
+            side: Side::Long,
+            trigger_price: utils::scale(1_450, Perpetuals::PRICE_DECIMALS),
+            size: utils::scale(5, ETH_DECIMALS),
+            collateral: utils::scale(1, ETH_DECIMALS),
+        },
+    )
+    .await
+    .unwrap();
+
+    // Martin: Place a second limit long and cancel it
+    let (cancelled_order_pda, _) = instructions::test_place_order(
+        &test_setup.program_test_ctx,
+        martin,
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+        eth_mint,
+        PlaceOrderParams {
+            order_id: 1,
+            position_id: 1,
+            order_type: OrderType::Limit,
+            side: Side::Long,
+            trigger_price: utils::scale(1_200, Perpetuals::PRICE_DECIMALS),
+            size: utils::scale_f64(2.5, ETH_DECIMALS),
+            collateral: utils::scale_f64(0.5, ETH_DECIMALS),
+        },
+    )
+    .await
+    .unwrap();
+
+    instructions::test_cancel_order(
+        &test_setup.program_test_ctx,
+        martin,
+        &test_setup.payer_keypair,
+        &cancelled_order_pda,
+    )
+    .await
+    .unwrap();
+
+    // Alice: Try and fail to execute the limit order above the trigger price
+    assert!(instructions::test_execute_order(
+        &test_setup.program_test_ctx,
+        alice,
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+        eth_mint,
+        &limit_order_pda,
+    )
+    .await
+    .is_err());
+
+    // Makes ETH price to drop to 1_400
+    set_eth_price(&test_setup, admin_a, &multisig_signers, 1_400).await;
+
+    // Alice: Execute the limit order
+    let position_pda = instructions::test_execute_order(
+        &test_setup.program_test_ctx,
+        alice,
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+        eth_mint,
+        &limit_order_pda,
+    )
+    .await
+    .unwrap();
+
+    // Martin: Place a stop-loss closing the whole position at 1_380
+    let position_account =
+        utils::get_account::<Position>(&test_setup.program_test_ctx, position_pda).await;
+
+    let (stop_loss_order_pda, _) = instructions::test_place_order(
+        &test_setup.program_test_ctx,
+        martin,
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+        eth_mint,
+        PlaceOrderParams {
+            order_id: 2,
+            position_id: 0,
+            order_type: OrderType::StopLoss,
+            side: Side::Long,
+            trigger_price: utils::scale(1_380, Perpetuals::PRICE_DECIMALS),
+            size: position_account.size_usd,
+            collateral: 0,
+        },
+    )
+    .await
+    .unwrap();
+
+    // Alice: Try and fail to execute the stop-loss above the trigger price
+    assert!(instructions::test_execute_order(
+        &test_setup.program_test_ctx,
+        alice,
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+        eth_mint,
+        &stop_loss_order_pda,
+    )
+    .await
+    .is_err());
+
+    // Makes ETH price to drop to 1_350
+    set_eth_price(&test_setup, admin_a, &multisig_signers, 1_350).await;
+
+    // Alice: Execute the stop-loss, closing the position
+    instructions::test_execute_order(
+        &test_setup.program_test_ctx,
+        alice,
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+        eth_mint,
+        &stop_loss_order_pda,
+    )
+    .await
+    .unwrap();
+}
+
+async fn set_eth_price(
+    test_setup: &utils::TestSetup,
+    admin: &Keypair,
+    multisig_signers: &[&Keypair],
+    price: u64,
+) {
+    let eth_test_oracle_pda = test_setup.custodies_info[1].custom_oracle_pda;
+    let eth_custody_pda = test_setup.custodies_info[1].custody_pda;
+
+    let publish_time = utils::get_current_unix_timestamp(&test_setup.program_test_ctx).await;
+
+    instructions::test_set_custom_oracle_price(
+        &test_setup.program_test_ctx,
+        admin,
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+        &eth_custody_pda,
+        &eth_test_oracle_pda,
+        SetCustomOraclePriceParams {
+            price: utils::scale(price, ETH_DECIMALS),
+            expo: -(ETH_DECIMALS as i32),
+            conf: utils::scale(10, ETH_DECIMALS),
+            ema: utils::scale(price, ETH_DECIMALS),
+            publish_time,
+        },
+        multisig_signers,
+    )
+    .await
+    .unwrap();
+
+    utils::warp_forward(&test_setup.program_test_ctx, 1).await;
+}
@@ -109,6 +109,7 @@ pub async fn liquidate_position() {
         &test_setup.pool_pda,
         eth_mint,
         OpenPositionParams {
+            position_id: 0,
             // max price paid (slippage implied)
             price: utils::scale(1_550, ETH_DECIMALS),
             collateral: utils::scale(1, ETH_DECIMALS),
@@ -182,7 +183,7 @@ pub async fn liquidate_position() {
 
         assert_eq!(
             martin_eth_balance,
-            utils::scale_f64(1.369834721, ETH_DECIMALS)
+            utils::scale_f64(1.295944916, ETH_DECIMALS)
         );
     }
 }
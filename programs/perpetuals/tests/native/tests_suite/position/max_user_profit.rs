@@ -103,6 +103,7 @@ pub async fn max_user_profit() {
         &test_setup.pool_pda,
         eth_mint,
         OpenPositionParams {
+            position_id: 0,
             // max price paid (slippage implied)
             price: utils::scale(1_550, ETH_DECIMALS),
             collateral: utils::scale(1, ETH_DECIMALS),
@@ -152,7 +153,8 @@ pub async fn max_user_profit() {
         &position_pda,
         ClosePositionParams {
             // lowest exit price paid (slippage implied)
-            price: utils::scale(2_970, USDC_DECIMALS),
+            price: utils::scale(2_950, USDC_DECIMALS),
+            size_usd: u64::MAX,
         },
     )
     .await
@@ -101,6 +101,7 @@ pub async fn min_max_leverage() {
         &test_setup.pool_pda,
         eth_mint,
         OpenPositionParams {
+            position_id: 0,
             // max price paid (slippage implied)
             price: utils::scale(1_550, ETH_DECIMALS),
             collateral: utils::scale(1, ETH_DECIMALS),
@@ -119,6 +120,7 @@ pub async fn min_max_leverage() {
         &test_setup.pool_pda,
         eth_mint,
         OpenPositionParams {
+            position_id: 0,
             // max price paid (slippage implied)
             price: utils::scale(1_550, ETH_DECIMALS),
             collateral: utils::scale(1, ETH_DECIMALS),
@@ -1,5 +1,13 @@
+pub mod auto_deleverage;
+pub mod execute_orders;
+pub mod increase_position;
 pub mod liquidate_position;
+pub mod margin_account;
 pub mod max_user_profit;
 pub mod min_max_leverage;
+pub mod open_position_with_swap;
 
-pub use {liquidate_position::*, max_user_profit::*, min_max_leverage::*};
+pub use {
+    auto_deleverage::*, execute_orders::*, increase_position::*, liquidate_position::*,
+    margin_account::*, max_user_profit::*, min_max_leverage::*, open_position_with_swap::*,
+};
@@ -0,0 +1,172 @@
+use {
+    crate::{instructions, utils},
+    maplit::hashmap,
+    perpetuals::{
+        instructions::OpenPositionWithSwapParams,
+        state::{custody::PricingParams, position::Side},
+    },
+};
+
+const ETH_DECIMALS: u8 = 9;
+const USDC_DECIMALS: u8 = 6;
+
+pub async fn open_position_with_swap() {
+    let test_setup = utils::TestSetup::new(
+        vec![
+            utils::UserParam {
+                name: "alice",
+                token_balances: hashmap! {
+                    "usdc" => utils::scale(1_000, USDC_DECIMALS),
+                    "eth" => utils::scale(100, ETH_DECIMALS),
+                },
+            },
+            utils::UserParam {
+                name: "martin",
+                token_balances: hashmap! {
+                    "usdc" => utils::scale(1_000, USDC_DECIMALS),
+                    "eth" => utils::scale(2, ETH_DECIMALS),
+                },
+            },
+        ],
+        vec![
+            utils::MintParam {
+                name: "usdc",
+                decimals: USDC_DECIMALS,
+            },
+            utils::MintParam {
+                name: "eth",
+                decimals: ETH_DECIMALS,
+            },
+        ],
+        vec!["admin_a", "admin_b", "admin_c"],
+        "main_pool",
+        vec![
+            utils::SetupCustodyWithLiquidityParams {
+                setup_custody_params: utils::SetupCustodyParams {
+                    mint_name: "usdc",
+                    is_stable: true,
+                    is_virtual: false,
+                    target_ratio: utils::ratio_from_percentage(50.0),
+                    min_ratio: utils::ratio_from_percentage(0.0),
+                    max_ratio: utils::ratio_from_percentage(100.0),
+                    initial_price: utils::scale(1, USDC_DECIMALS),
+                    initial_conf: utils::scale_f64(0.01, USDC_DECIMALS),
+                    pricing_params: None,
+                    permissions: None,
+                    fees: None,
+                    borrow_rate: None,
+                },
+                liquidity_amount: utils::scale(1_000, USDC_DECIMALS),
+                payer_user_name: "alice",
+            },
+            utils::SetupCustodyWithLiquidityParams {
+                setup_custody_params: utils::SetupCustodyParams {
+                    mint_name: "eth",
+                    is_stable: false,
+                    is_virtual: false,
+                    target_ratio: utils::ratio_from_percentage(100.0),
+                    min_ratio: utils::ratio_from_percentage(0.0),
+                    max_ratio: utils::ratio_from_percentage(100.0),
+                    initial_price: utils::scale(1_500, ETH_DECIMALS),
+                    initial_conf: utils::scale(10, ETH_DECIMALS),
+                    pricing_params: Some(PricingParams {
+                        // Expressed in BPS, with BPS = 10_000
+                        // 50_000 = x5, 100_000 = x10
+                        max_leverage: 100_000,
+                        ..utils::fixtures::pricing_params_regular(false)
+                    }),
+                    permissions: None,
+                    fees: None,
+                    borrow_rate: None,
+                },
+                liquidity_amount: utils::scale(100, ETH_DECIMALS),
+                payer_user_name: "alice",
+            },
+        ],
+    )
+    .await;
+
+    let martin = test_setup.get_user_keypair_by_name("martin");
+
+    let usdc_mint = &test_setup.get_mint_by_name("usdc");
+    let eth_mint = &test_setup.get_mint_by_name("eth");
+
+    // Martin: Open 1 ETH long position x5 with USDC swapped into ETH collateral
+    instructions::test_open_position_with_swap(
+        &test_setup.program_test_ctx,
+        martin,
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+        usdc_mint,
+        eth_mint,
+        OpenPositionWithSwapParams {
+            position_id: 0,
+            // max price paid (slippage implied)
+            price: utils::scale(1_550, USDC_DECIMALS),
+            amount_in: utils::scale(300, USDC_DECIMALS),
+            size: utils::scale(1, ETH_DECIMALS),
+            side: Side::Long,
+        },
+    )
+    .await
+    .unwrap();
+
+    // Martin: Open a position over max leverage should fail
+    assert!(instructions::test_open_position_with_swap(
+        &test_setup.program_test_ctx,
+        martin,
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+        usdc_mint,
+        eth_mint,
+        OpenPositionWithSwapParams {
+            position_id: 1,
+            price: utils::scale(1_550, USDC_DECIMALS),
+            amount_in: utils::scale(100, USDC_DECIMALS),
+            size: utils::scale(5, ETH_DECIMALS),
+            side: Side::Long,
+        },
+    )
+    .await
+    .is_err());
+
+    // Martin: Open a position with a max price below the entry price should fail
+    assert!(instructions::test_open_position_with_swap(
+        &test_setup.program_test_ctx,
+        martin,
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+        usdc_mint,
+        eth_mint,
+        OpenPositionWithSwapParams {
+            position_id: 1,
+            price: utils::scale(1_400, USDC_DECIMALS),
+            amount_in: utils::scale(300, USDC_DECIMALS),
+            size: utils::scale(1, ETH_DECIMALS),
+            side: Side::Long,
+        },
+    )
+    .await
+    .is_err());
+
+    utils::warp_forward(&test_setup.program_test_ctx, 1).await;
+
+    // Martin: Open a position with an already used position id should fail
+    assert!(instructions::test_open_position_with_swap(
+        &test_setup.program_test_ctx,
+        martin,
+        &test_setup.payer_keypair,
+        &test_setup.pool_pda,
+        usdc_mint,
+        eth_mint,
+        OpenPositionWithSwapParams {
+            position_id: 0,
+            price: utils::scale(1_550, USDC_DECIMALS),
+            amount_in: utils::scale(300, USDC_DECIMALS),
+            size: utils::scale(1, ETH_DECIMALS),
+            side: Side::Long,
+        },
+    )
+    .await
+    .is_err());
+}
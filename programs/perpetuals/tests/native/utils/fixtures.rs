@@ -6,7 +6,7 @@ use {
         instructions::InitParams,
         state::{
             custody::{BorrowRateParams, Fees, FeesMode, PricingParams},
-            oracle::{OracleParams, OracleType},
+            oracle::{OracleAggregation, OracleParams},
             perpetuals::Permissions,
         },
     },
@@ -48,7 +48,12 @@ pub fn fees_linear_regular() -> Fees {
         open_position: 100,
         close_position: 100,
         liquidation: 50,
+        liquidation_reward_min: 0,
+        liquidation_reward_max: 0,
+        liquidation_reward_window_sec: 0,
+        execute_order: 10,
         protocol_share: 25,
+        insurance_share: 0,
         fee_max: 0,
         fee_optimal: 0,
     }
@@ -61,23 +66,33 @@ pub fn pricing_params_regular(use_ema: bool) -> PricingParams {
         trade_spread_long: 100,
         trade_spread_short: 100,
         swap_spread: 300,
+        trade_spread_min: 0,
+        skew_spread_mult: 0,
+        price_impact_mult: 0,
+        price_impact_max: 0,
         min_initial_leverage: 10_000,
         max_initial_leverage: 100_000,
         max_leverage: 100_000,
+        liquidation_target_leverage: 0,
+        maintenance_margin: 0,
         max_payoff_mult: 10_000,
         max_utilization: 0,
+        adl_threshold: 0,
         max_position_locked_usd: 0,
         max_total_locked_usd: 0,
+        max_oi_long_usd: 0,
+        max_oi_short_usd: 0,
     }
 }
 
-pub fn oracle_params_regular(oracle_account: Pubkey) -> OracleParams {
+pub fn oracle_params_regular() -> OracleParams {
     OracleParams {
-        oracle_account,
-        oracle_type: OracleType::Custom,
         oracle_authority: Pubkey::default(),
-        max_price_error: 1_000_000,
+        max_price_error: 1_000,
         max_price_age_sec: 30,
+        max_open_price_age_sec: 30,
+        aggregation: OracleAggregation::PrimaryOnly,
+        max_divergence: 0,
     }
 }
 
@@ -92,5 +107,6 @@ pub fn init_params_permissions_full(min_signatures: u8) -> InitParams {
         allow_pnl_withdrawal: true,
         allow_collateral_withdrawal: true,
         allow_size_change: true,
+        proposal_delay: 0,
     }
 }
@@ -1,7 +1,10 @@
-use {perpetuals::state::position::Side, solana_sdk::pubkey::Pubkey};
+use {
+    perpetuals::state::{multisig::Role, position::Side},
+    solana_sdk::pubkey::Pubkey,
+};
 
-pub fn get_multisig_pda() -> (Pubkey, u8) {
-    Pubkey::find_program_address(&["multisig".as_ref()], &perpetuals::id())
+pub fn get_multisig_pda(role: Role) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&["multisig".as_ref(), &[role as u8]], &perpetuals::id())
 }
 
 pub fn get_transfer_authority_pda() -> (Pubkey, u8) {
@@ -46,6 +49,7 @@ pub fn get_position_pda(
     pool_pda: &Pubkey,
     custody_pda: &Pubkey,
     side: Side,
+    position_id: u64,
 ) -> (Pubkey, u8) {
     Pubkey::find_program_address(
         &[
@@ -54,11 +58,19 @@ pub fn get_position_pda(
             pool_pda.as_ref(),
             custody_pda.as_ref(),
             &[side as u8],
+            &position_id.to_le_bytes(),
         ],
         &perpetuals::id(),
     )
 }
 
+pub fn get_position_counter_pda(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &["position_counter".as_ref(), owner.as_ref()],
+        &perpetuals::id(),
+    )
+}
+
 pub fn get_custody_token_account_pda(
     pool_pda: &Pubkey,
     custody_token_mint: &Pubkey,
@@ -73,6 +85,52 @@ pub fn get_custody_token_account_pda(
     )
 }
 
+pub fn get_insurance_fund_token_account_pda(
+    pool_pda: &Pubkey,
+    custody_token_mint: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            "insurance_fund_token_account".as_ref(),
+            pool_pda.as_ref(),
+            custody_token_mint.as_ref(),
+        ],
+        &perpetuals::id(),
+    )
+}
+
+pub fn get_margin_account_pda(owner: &Pubkey, pool_pda: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &["margin_account".as_ref(), owner.as_ref(), pool_pda.as_ref()],
+        &perpetuals::id(),
+    )
+}
+
+pub fn get_order_pda(
+    owner: &Pubkey,
+    pool_pda: &Pubkey,
+    custody_pda: &Pubkey,
+    order_id: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            "order".as_ref(),
+            owner.as_ref(),
+            pool_pda.as_ref(),
+            custody_pda.as_ref(),
+            &order_id.to_le_bytes(),
+        ],
+        &perpetuals::id(),
+    )
+}
+
+pub fn get_order_token_account_pda(order_pda: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &["order_token_account".as_ref(), order_pda.as_ref()],
+        &perpetuals::id(),
+    )
+}
+
 pub fn get_custom_oracle_account(pool_pda: &Pubkey, custody_mint: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(
         &[
@@ -83,3 +141,10 @@ pub fn get_custom_oracle_account(pool_pda: &Pubkey, custody_mint: &Pubkey) -> (P
         &perpetuals::id(),
     )
 }
+
+pub fn get_proposal_pda(proposal_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &["proposal".as_ref(), &proposal_id.to_le_bytes()],
+        &perpetuals::id(),
+    )
+}
@@ -8,7 +8,7 @@ use {
     perpetuals::{
         instructions::{AddCustodyParams, AddLiquidityParams, SetCustomOraclePriceParams},
         state::{
-            custody::{BorrowRateParams, Fees, PricingParams},
+            custody::{BorrowRateParams, Fees, FundingRateParams, PricingParams},
             perpetuals::Permissions,
             pool::TokenRatios,
         },
@@ -282,7 +282,7 @@ impl TestSetup {
                     let add_custody_params = AddCustodyParams {
                         is_stable: custody_param.setup_custody_params.is_stable,
                         is_virtual: custody_param.setup_custody_params.is_virtual,
-                        oracle: fixtures::oracle_params_regular(custom_oracle_pda),
+                        oracle_params: fixtures::oracle_params_regular(),
                         pricing: custody_param
                             .setup_custody_params
                             .pricing_params
@@ -299,6 +299,7 @@ impl TestSetup {
                             .setup_custody_params
                             .borrow_rate
                             .unwrap_or_else(fixtures::borrow_rate_regular),
+                        funding_rate: FundingRateParams::default(),
 
                         // in BPS, 10_000 = 100%
                         ratios: ratios.clone(),
@@ -419,6 +420,7 @@ impl TestSetup {
                     &multisig_members_keypairs[0],
                     payer_keypair,
                     &custodies_info[idx].custody_pda,
+                    idx as u64,
                     ratios.clone(),
                     &multisig_signers,
                 )
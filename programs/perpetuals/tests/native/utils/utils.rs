@@ -76,6 +76,13 @@ pub async fn get_account<T: anchor_lang::AccountDeserialize>(
     T::try_deserialize(&mut account.data.as_slice()).unwrap()
 }
 
+pub async fn account_exists(program_test_ctx: &RwLock<ProgramTestContext>, key: Pubkey) -> bool {
+    let mut ctx = program_test_ctx.write().await;
+    let banks_client = &mut ctx.banks_client;
+
+    banks_client.get_account(key).await.unwrap().is_some()
+}
+
 pub async fn get_current_unix_timestamp(program_test_ctx: &RwLock<ProgramTestContext>) -> i64 {
     let mut ctx = program_test_ctx.write().await;
     let banks_client = &mut ctx.banks_client;
@@ -254,6 +261,7 @@ pub async fn set_custody_ratios(
     custody_admin: &Keypair,
     payer: &Keypair,
     custody_pda: &Pubkey,
+    proposal_id: u64,
     ratios: Vec<TokenRatios>,
     multisig_signers: &[&Keypair],
 ) {
@@ -265,14 +273,16 @@ pub async fn set_custody_ratios(
         payer,
         &custody_account.pool,
         custody_pda,
+        proposal_id,
         SetCustodyConfigParams {
             is_stable: custody_account.is_stable,
             is_virtual: custody_account.is_virtual,
-            oracle: custody_account.oracle,
+            oracle_params: custody_account.oracle_params,
             pricing: custody_account.pricing,
             permissions: custody_account.permissions,
             fees: custody_account.fees,
             borrow_rate: custody_account.borrow_rate,
+            funding_rate: custody_account.funding_rate,
             ratios,
         },
         multisig_signers,